@@ -0,0 +1,652 @@
+use crate::{Conn, Error, Filter, Priority, Recurrence, Result, add_tag, category, journal};
+use rusqlite::types::Type;
+use rusqlite::{Connection, OptionalExtension, Row};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::str::FromStr;
+
+/// Version of the export format, bump it whenever its fields change
+pub const EXPORT_VERSION: u32 = 1;
+
+/// Everything in a database as `todo export` writes it. Tasks refer to each
+/// other by their ids in the export, timestamps are UTC like the columns
+/// they come from. History and the undo journal are not part of it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Export {
+    pub schema_version: u32,
+    /// Every category path, sorted, empty ones included
+    pub categories: Vec<String>,
+    /// Every tag name, sorted, unused ones included
+    pub tags: Vec<String>,
+    /// Every task by id, trashed ones included
+    pub tasks: Vec<ExportedTask>,
+}
+
+/// A task row with its category, tags and prerequisites
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportedTask {
+    pub id: i32,
+    pub info: String,
+    pub done: bool,
+    pub due_date: Option<String>,
+    pub category: Option<String>,
+    pub priority: Priority,
+    /// Sorted by name
+    pub tags: Vec<String>,
+    pub parent: Option<i32>,
+    /// Every task this waits on, finished and trashed ones included
+    pub blocked_by: Vec<i32>,
+    /// RRULE of a repeating task
+    pub recurrence: Option<String>,
+    pub repeat_from_completion: bool,
+    /// Task whose completion added this one
+    pub repeat_of: Option<i32>,
+    pub created_at: Option<String>,
+    pub modified_at: Option<String>,
+    pub completed_at: Option<String>,
+    pub deleted_at: Option<String>,
+}
+
+impl Export {
+    /// The export as `todo export` prints it
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("exports serialize to JSON")
+    }
+
+    /// Reads an export, refusing one written by a newer todo before looking
+    /// at anything else in it
+    pub fn from_json(text: &str) -> Result<Self> {
+        #[derive(Deserialize)]
+        struct Version {
+            schema_version: u32,
+        }
+
+        let invalid = |err: serde_json::Error| Error::InvalidBackup(err.to_string());
+        let Version { schema_version } = serde_json::from_str(text).map_err(invalid)?;
+        if schema_version > EXPORT_VERSION {
+            return Err(Error::BackupTooNew {
+                found: schema_version,
+                supported: EXPORT_VERSION,
+            });
+        }
+        serde_json::from_str(text).map_err(invalid)
+    }
+
+    /// Makes sure every task id is unique and every reference points at a
+    /// task of the export
+    fn check(&self) -> Result<()> {
+        let mut ids = HashSet::new();
+        for task in &self.tasks {
+            if !ids.insert(task.id) {
+                return Err(Error::InvalidBackup(format!(
+                    "task {} appears more than once",
+                    task.id
+                )));
+            }
+        }
+        for task in &self.tasks {
+            let references = task.parent.iter().chain(&task.repeat_of);
+            if let Some(missing) = references
+                .chain(&task.blocked_by)
+                .find(|id| !ids.contains(id))
+            {
+                return Err(Error::InvalidBackup(format!(
+                    "task {} refers to task {missing}, which is not in the backup",
+                    task.id
+                )));
+            }
+            if let Some(rule) = &task.recurrence {
+                Recurrence::from_str(rule).map_err(|err| {
+                    Error::InvalidBackup(format!("task {} has an invalid rule: {err}", task.id))
+                })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What `import` does with a task the database already has, that is one
+/// with the same id and creation time. A task that only shares its id gets
+/// a new one either way.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum OnConflict {
+    /// Keep the task in the database as it is
+    #[default]
+    Skip,
+    /// Replace the task in the database with the imported one
+    Overwrite,
+    /// Add the imported task again under a new id
+    Duplicate,
+}
+
+impl FromStr for OnConflict {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "skip" => Ok(Self::Skip),
+            "overwrite" => Ok(Self::Overwrite),
+            "duplicate" => Ok(Self::Duplicate),
+            _ => Err(format!(
+                "'{s}' is not a conflict strategy, use skip, overwrite or duplicate"
+            )),
+        }
+    }
+}
+
+/// What an import did
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct ImportSummary {
+    pub added: usize,
+    pub overwritten: usize,
+    pub skipped: usize,
+    /// Id in the export of every task that got another id, with the new one
+    pub renumbered: BTreeMap<i32, i32>,
+}
+
+impl Conn {
+    /// Every category and tag in the database, with every task or only the
+    /// ones matching `filter`. References to tasks left out are dropped, so
+    /// a filtered export imports on its own.
+    pub fn export(&self, filter: Option<&Filter>) -> Result<Export> {
+        let names = |sql: &str| -> Result<Vec<String>> {
+            let names = self
+                .client
+                .prepare(sql)?
+                .query_map((), |row| row.get(0))?
+                .collect::<rusqlite::Result<_>>()?;
+            Ok(names)
+        };
+        let categories = names("SELECT name FROM categories ORDER BY name")?;
+        let tags = names("SELECT name FROM tags ORDER BY name")?;
+
+        let mut params = Vec::new();
+        let condition = match filter {
+            Some(filter) => {
+                filter.check(&self.client)?;
+                format!("WHERE {}", filter.to_sql(&mut params))
+            }
+            None => String::new(),
+        };
+        let mut tasks: Vec<ExportedTask> = self
+            .client
+            .prepare(&format!(
+                r"
+                SELECT tasks.id, info, done, due_date, categories.name, priority,
+                    (
+                        SELECT json_group_array(name) FROM (
+                            SELECT tags.name FROM task_tags
+                            JOIN tags ON tags.id = task_tags.tag_id
+                            WHERE task_tags.task_id = tasks.id
+                            ORDER BY tags.name
+                        )
+                    ),
+                    tasks.parent_id,
+                    (
+                        SELECT json_group_array(blocker_id) FROM (
+                            SELECT blocker_id FROM task_deps
+                            WHERE blocked_id = tasks.id
+                            ORDER BY blocker_id
+                        )
+                    ),
+                    recurrence, repeat_from_completion, repeat_of,
+                    created_at, modified_at, completed_at, deleted_at
+                FROM tasks
+                LEFT JOIN categories ON categories.id = tasks.category
+                {condition}
+                ORDER BY tasks.id
+                "
+            ))?
+            .query_map(rusqlite::params_from_iter(params), |row| {
+                Ok(ExportedTask {
+                    id: row.get(0)?,
+                    info: row.get(1)?,
+                    done: row.get(2)?,
+                    due_date: row.get(3)?,
+                    category: row.get(4)?,
+                    priority: row.get(5)?,
+                    tags: json_column(row, 6)?,
+                    parent: row.get(7)?,
+                    blocked_by: json_column(row, 8)?,
+                    recurrence: row.get(9)?,
+                    repeat_from_completion: row.get(10)?,
+                    repeat_of: row.get(11)?,
+                    created_at: row.get(12)?,
+                    modified_at: row.get(13)?,
+                    completed_at: row.get(14)?,
+                    deleted_at: row.get(15)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+
+        let ids: HashSet<_> = tasks.iter().map(|task| task.id).collect();
+        for task in &mut tasks {
+            task.parent = task.parent.filter(|id| ids.contains(id));
+            task.repeat_of = task.repeat_of.filter(|id| ids.contains(id));
+            task.blocked_by.retain(|id| ids.contains(id));
+        }
+
+        Ok(Export {
+            schema_version: EXPORT_VERSION,
+            categories,
+            tags,
+            tasks,
+        })
+    }
+
+    /// Adds an export to the database as a single operation. Tasks keep
+    /// their id unless another task has it, references follow them to
+    /// their new ids.
+    pub fn import(&self, export: &Export, on_conflict: OnConflict) -> Result<ImportSummary> {
+        export.check()?;
+        let tx = self.client.unchecked_transaction()?;
+        // tasks may refer to ones later in the export
+        tx.pragma_update(None, "defer_foreign_keys", true)?;
+        journal::begin(&tx)?;
+
+        for path in &export.categories {
+            category::create_path(&tx, &category::normalize(path)?)?;
+        }
+        for name in &export.tags {
+            tx.execute("INSERT OR IGNORE INTO tags (name) VALUES (?1)", [name])?;
+        }
+
+        let mut summary = ImportSummary::default();
+        let mut next_id = tx
+            .query_row("SELECT coalesce(max(id), 0) FROM tasks", (), |row| {
+                row.get::<_, i32>(0)
+            })?
+            .max(export.tasks.iter().map(|task| task.id).max().unwrap_or(0))
+            + 1;
+        let mut ids = BTreeMap::new();
+        let mut writes = Vec::new();
+        for task in &export.tasks {
+            let existing: Option<Option<String>> = tx
+                .query_row(
+                    "SELECT created_at FROM tasks WHERE id = ?1",
+                    [task.id],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            let same = existing.as_ref() == Some(&task.created_at);
+            let (id, overwrite) = match (existing, on_conflict) {
+                (None, _) => (task.id, false),
+                (Some(_), OnConflict::Skip) if same => {
+                    summary.skipped += 1;
+                    ids.insert(task.id, task.id);
+                    continue;
+                }
+                (Some(_), OnConflict::Overwrite) if same => (task.id, true),
+                (Some(_), _) => {
+                    next_id += 1;
+                    (next_id - 1, false)
+                }
+            };
+            ids.insert(task.id, id);
+            writes.push((task, id, overwrite));
+        }
+
+        for &(task, id, overwrite) in &writes {
+            write_task(&tx, task, id, overwrite, &ids)?;
+            match overwrite {
+                true => summary.overwritten += 1,
+                false => summary.added += 1,
+            }
+            if id != task.id {
+                summary.renumbered.insert(task.id, id);
+            }
+        }
+        // times go in last, every change above moves modified_at
+        for &(task, id, _) in &writes {
+            for blocker in &task.blocked_by {
+                tx.execute(
+                    "INSERT OR IGNORE INTO task_deps (blocker_id, blocked_id) VALUES (?1, ?2)",
+                    [ids[blocker], id],
+                )?;
+            }
+            tx.execute(
+                r"
+                UPDATE tasks SET created_at = ?2, modified_at = ?3, completed_at = ?4,
+                    deleted_at = ?5
+                WHERE id = ?1
+                ",
+                rusqlite::params![
+                    id,
+                    task.created_at,
+                    task.modified_at,
+                    task.completed_at,
+                    task.deleted_at,
+                ],
+            )?;
+        }
+
+        journal::end(&tx, &format!("import {} tasks", writes.len()))?;
+        tx.commit()?;
+        Ok(summary)
+    }
+}
+
+/// Reads a column holding a JSON array
+fn json_column<T: DeserializeOwned>(row: &Row, index: usize) -> rusqlite::Result<T> {
+    let text: String = row.get(index)?;
+    serde_json::from_str(&text)
+        .map_err(|err| rusqlite::Error::FromSqlConversionFailure(index, Type::Text, err.into()))
+}
+
+/// Inserts the task under `id`, or replaces the row already there along
+/// with its tags and prerequisites
+fn write_task(
+    client: &Connection,
+    task: &ExportedTask,
+    id: i32,
+    overwrite: bool,
+    ids: &BTreeMap<i32, i32>,
+) -> Result<()> {
+    let category = match &task.category {
+        Some(path) => Some(category::create_path(client, &category::normalize(path)?)?),
+        None => None,
+    };
+    let params = rusqlite::params![
+        id,
+        task.info,
+        task.done,
+        task.due_date,
+        category,
+        task.priority,
+        task.parent.map(|parent| ids[&parent]),
+        task.recurrence,
+        task.repeat_from_completion,
+        task.repeat_of.map(|of| ids[&of]),
+    ];
+    if overwrite {
+        client.execute(
+            r"
+            UPDATE tasks SET info = ?2, done = ?3, due_date = ?4, category = ?5,
+                priority = ?6, parent_id = ?7, recurrence = ?8,
+                repeat_from_completion = ?9, repeat_of = ?10
+            WHERE id = ?1
+            ",
+            params,
+        )?;
+        client.execute("DELETE FROM task_tags WHERE task_id = ?1", [id])?;
+        client.execute("DELETE FROM task_deps WHERE blocked_id = ?1", [id])?;
+    } else {
+        client.execute(
+            r"
+            INSERT INTO tasks (id, info, done, due_date, category, priority, parent_id,
+                recurrence, repeat_from_completion, repeat_of)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+            ",
+            params,
+        )?;
+    }
+    for name in &task.tags {
+        add_tag(client, id, name)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ListOptions, NewTask, TaskEdit};
+    use proptest::prelude::*;
+
+    fn get_test_conn() -> Conn {
+        Conn::build(":memory:").unwrap()
+    }
+
+    fn add(conn: &Conn, info: &str, parent: Option<i32>) -> i32 {
+        conn.add_task(&NewTask {
+            info: info.to_string(),
+            category: Some("work/reports".to_string()),
+            tags: vec!["weekly".to_string()],
+            parent,
+            ..Default::default()
+        })
+        .unwrap()
+        .id
+    }
+
+    fn infos(conn: &Conn) -> Vec<(i32, String)> {
+        conn.list_tasks(&ListOptions {
+            include_done: true,
+            ..Default::default()
+        })
+        .unwrap()
+        .into_iter()
+        .map(|task| (task.id, task.info))
+        .collect()
+    }
+
+    fn round_trip(conn: &Conn) -> (String, String) {
+        let first = conn.export(None).unwrap().to_json();
+        let copy = get_test_conn();
+        copy.import(&Export::from_json(&first).unwrap(), OnConflict::Skip)
+            .unwrap();
+        (first, copy.export(None).unwrap().to_json())
+    }
+
+    #[test]
+    fn test_export_import() {
+        let conn = get_test_conn();
+        let parent = add(&conn, "parent", None);
+        let child = add(&conn, "child", Some(parent));
+        let other = add(&conn, "other", None);
+        conn.add_dependency(child, other).unwrap();
+        conn.add_category("empty").unwrap();
+        conn.edit_task(
+            other,
+            &TaskEdit {
+                recurrence: Some("every 2 weeks".parse().unwrap()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        conn.remove_task(child).unwrap();
+
+        let (first, second) = round_trip(&conn);
+        assert_eq!(first, second);
+        let export = Export::from_json(&first).unwrap();
+        assert_eq!(export.categories, ["empty", "work", "work/reports"]);
+        assert_eq!(
+            export.tasks[1].deleted_at,
+            conn.export(None).unwrap().tasks[1].deleted_at
+        );
+        assert_eq!(export.tasks[2].blocked_by, [child]);
+    }
+
+    #[test]
+    fn test_import_conflicts() {
+        let conn = get_test_conn();
+        let parent = add(&conn, "parent", None);
+        let child = add(&conn, "child", Some(parent));
+        let mut export = conn.export(None).unwrap();
+        export.tasks[0].info = "imported parent".to_string();
+
+        let summary = conn.import(&export, OnConflict::Skip).unwrap();
+        assert_eq!((summary.added, summary.skipped), (0, 2));
+        assert_eq!(infos(&conn), [(1, "parent".into()), (2, "child".into())]);
+
+        let summary = conn.import(&export, OnConflict::Overwrite).unwrap();
+        assert_eq!((summary.added, summary.overwritten), (0, 2));
+        assert_eq!(
+            conn.export(None).unwrap().tasks[0],
+            ExportedTask {
+                info: "imported parent".to_string(),
+                ..export.tasks[0].clone()
+            }
+        );
+
+        let summary = conn.import(&export, OnConflict::Duplicate).unwrap();
+        assert_eq!(summary.added, 2);
+        assert_eq!(
+            summary.renumbered,
+            BTreeMap::from([(parent, 3), (child, 4)])
+        );
+        assert_eq!(conn.get_task(4).unwrap().parent, Some(3));
+
+        // the undo journal takes an import back in one go
+        conn.undo(None, false).unwrap();
+        assert_eq!(infos(&conn).len(), 2);
+    }
+
+    #[test]
+    fn test_import_renumbers_other_tasks() {
+        let conn = get_test_conn();
+        let blocker = add(&conn, "blocker", None);
+        let blocked = add(&conn, "blocked", None);
+        conn.add_dependency(blocker, blocked).unwrap();
+        let export = conn.export(None).unwrap();
+
+        // a different task that happens to have id 1
+        let target = get_test_conn();
+        target
+            .add_task(&NewTask {
+                info: "mine".to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+        target
+            .client
+            .execute("UPDATE tasks SET created_at = '2020-01-01 00:00:00'", ())
+            .unwrap();
+
+        let summary = target.import(&export, OnConflict::Skip).unwrap();
+        assert_eq!(summary.added, 2);
+        assert_eq!(summary.renumbered, BTreeMap::from([(blocker, 3)]));
+        assert_eq!(target.get_task(blocked).unwrap().blocked_by, [3]);
+        assert_eq!(target.get_task(1).unwrap().info, "mine");
+    }
+
+    #[test]
+    fn test_filtered_export() {
+        let conn = get_test_conn();
+        let parent = add(&conn, "parent", None);
+        let child = conn
+            .add_task(&NewTask {
+                info: "child".to_string(),
+                category: Some("home".to_string()),
+                parent: Some(parent),
+                ..Default::default()
+            })
+            .unwrap()
+            .id;
+        conn.add_dependency(parent, child).unwrap();
+
+        let today = chrono::Local::now().date_naive();
+        let filter = Filter::parse("cat:home", &today, &Default::default()).unwrap();
+        let export = conn.export(Some(&filter)).unwrap();
+        assert_eq!(export.tasks.len(), 1);
+        // the parent and the blocker it refers to are left out
+        let task = &export.tasks[0];
+        assert_eq!((task.id, task.parent), (child, None));
+        assert!(task.blocked_by.is_empty());
+        assert_eq!(export.categories, ["home", "work", "work/reports"]);
+
+        let copy = get_test_conn();
+        copy.import(&export, OnConflict::Skip).unwrap();
+        assert_eq!(infos(&copy), [(child, "child".into())]);
+    }
+
+    #[test]
+    fn test_invalid_backups() {
+        assert!(matches!(
+            Export::from_json(r#"{"schema_version": 99, "tasks": "later"}"#),
+            Err(Error::BackupTooNew { found: 99, .. })
+        ));
+        assert!(matches!(
+            Export::from_json(r#"{"tasks": []}"#),
+            Err(Error::InvalidBackup(_))
+        ));
+
+        let conn = get_test_conn();
+        add(&conn, "task", None);
+        let mut export = conn.export(None).unwrap();
+        export.tasks[0].parent = Some(7);
+        assert!(matches!(
+            conn.import(&export, OnConflict::Skip),
+            Err(Error::InvalidBackup(msg)) if msg.contains("task 7")
+        ));
+    }
+
+    /// Builds a database from a list of random operations on random tasks
+    fn build(steps: &[(u8, u8, String)]) -> Conn {
+        let conn = get_test_conn();
+        for (kind, target, text) in steps {
+            let ids: Vec<i32> = conn
+                .client
+                .prepare("SELECT id FROM tasks WHERE deleted_at IS NULL")
+                .unwrap()
+                .query_map((), |row| row.get(0))
+                .unwrap()
+                .collect::<rusqlite::Result<_>>()
+                .unwrap();
+            let target = (!ids.is_empty()).then(|| ids[*target as usize % ids.len()]);
+            // operations that break a rule, like a dependency cycle, are
+            // refused, which is just as good a database
+            let _ = match (kind % 6, target) {
+                (0, _) | (_, None) => conn
+                    .add_task(&NewTask {
+                        info: text.clone(),
+                        category: (kind % 3 == 0).then(|| format!("{text}/{kind}")),
+                        tags: vec![format!("t{}", kind % 4)],
+                        parent: target.filter(|_| kind % 4 == 0),
+                        priority: Priority::High,
+                        ..Default::default()
+                    })
+                    .map(drop),
+                (1, Some(id)) => conn
+                    .edit_task(
+                        id,
+                        &TaskEdit {
+                            finish: Some(true),
+                            cascade: true,
+                            ..Default::default()
+                        },
+                    )
+                    .map(drop),
+                (2, Some(id)) => conn.add_dependency(ids[0], id),
+                (3, Some(id)) => conn.remove_task(id).map(drop),
+                (4, Some(id)) => conn
+                    .edit_task(
+                        id,
+                        &TaskEdit {
+                            recurrence: Some("weekly".parse().unwrap()),
+                            due_date: Some("2026-01-05".to_string()),
+                            untag: vec![format!("t{}", kind % 4)],
+                            ..Default::default()
+                        },
+                    )
+                    .map(drop),
+                (_, Some(_)) => conn.add_category(text).map(drop),
+            };
+            // an hour passes between steps, so every time is told apart
+            conn.client
+                .execute_batch(
+                    r"
+                    UPDATE tasks SET
+                        created_at = datetime(created_at, '-1 hour'),
+                        modified_at = datetime(modified_at, '-1 hour'),
+                        completed_at = datetime(completed_at, '-1 hour'),
+                        deleted_at = datetime(deleted_at, '-1 hour')
+                    ",
+                )
+                .unwrap();
+        }
+        conn
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(32))]
+
+        #[test]
+        fn test_round_trip_is_identical(
+            steps in prop::collection::vec((any::<u8>(), any::<u8>(), "[a-z]{1,8}"), 0..24)
+        ) {
+            let (first, second) = round_trip(&build(&steps));
+            prop_assert_eq!(first, second);
+        }
+    }
+}
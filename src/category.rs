@@ -0,0 +1,644 @@
+use crate::{Conn, Error, Result, journal};
+use rusqlite::{Connection, OptionalExtension};
+use serde::Serialize;
+
+/// Categories nest with `/`, `work/clientA/billing` is `billing` inside
+/// `clientA` inside `work`. `categories.name` holds the full path so finding
+/// a category stays a single lookup, `parent_id` points at the level above.
+pub const SEPARATOR: char = '/';
+
+/// Orders categories as a tree, `work` then `work/a` then `work-b`
+pub(crate) const TREE_ORDER: &str = "replace(categories.name, '/', char(1))";
+
+/// Matches `categories.name` against a path and everything below it, the
+/// path has to be bound to all three plain `?` parameters
+pub(crate) const IN_SUBTREE: &str =
+    "(categories.name = ? OR substr(categories.name, 1, length(?) + 1) = ? || '/')";
+
+/// A category with the number of open, finished and trashed tasks in it and
+/// in every category below it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CategoryCount {
+    /// Full path, e.g. `work/clientA`
+    pub name: String,
+    pub open: u32,
+    pub done: u32,
+    /// Tasks in the trash, they keep the category until the trash is emptied
+    pub trashed: u32,
+}
+
+impl CategoryCount {
+    /// No task, open, finished or trashed, uses the category or one below it
+    pub fn is_orphaned(&self) -> bool {
+        self.open == 0 && self.done == 0 && self.trashed == 0
+    }
+
+    /// Levels above this category, 0 for a top level one
+    pub fn depth(&self) -> usize {
+        self.name.matches(SEPARATOR).count()
+    }
+
+    /// Last part of the path
+    pub fn leaf(&self) -> &str {
+        leaf(&self.name)
+    }
+}
+
+/// Trims every level of a path, rejecting empty ones like `work//billing`
+pub fn normalize(path: &str) -> Result<String> {
+    let parts: Vec<_> = path.split(SEPARATOR).map(str::trim).collect();
+    if parts.iter().any(|part| part.is_empty()) {
+        return Err(Error::EmptyCategoryName);
+    }
+    Ok(parts.join("/"))
+}
+
+/// Last part of a path
+pub fn leaf(path: &str) -> &str {
+    path.rsplit(SEPARATOR).next().unwrap_or(path)
+}
+
+/// Path of the level above, `None` for a top level category
+fn parent(path: &str) -> Option<&str> {
+    path.rsplit_once(SEPARATOR).map(|(parent, _)| parent)
+}
+
+/// Whether `path` is `ancestor` or somewhere below it
+fn is_within(path: &str, ancestor: &str) -> bool {
+    path.strip_prefix(ancestor)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(SEPARATOR))
+}
+
+impl Conn {
+    /// Every category in tree order, with rolled up task counts
+    pub fn list_categories(&self) -> Result<Vec<CategoryCount>> {
+        let categories = self
+            .client
+            .prepare(&format!(
+                r"
+                SELECT categories.name,
+                    count(tasks.id) FILTER (WHERE tasks.deleted_at IS NULL AND NOT tasks.done),
+                    count(tasks.id) FILTER (WHERE tasks.deleted_at IS NULL AND tasks.done),
+                    count(tasks.id) FILTER (WHERE tasks.deleted_at IS NOT NULL)
+                FROM categories
+                LEFT JOIN categories AS below
+                    ON below.id = categories.id
+                    OR substr(below.name, 1, length(categories.name) + 1)
+                        = categories.name || '/'
+                LEFT JOIN tasks ON tasks.category = below.id
+                GROUP BY categories.id
+                ORDER BY {TREE_ORDER}
+                "
+            ))?
+            .query_map((), |row| {
+                Ok(CategoryCount {
+                    name: row.get(0)?,
+                    open: row.get(1)?,
+                    done: row.get(2)?,
+                    trashed: row.get(3)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(categories)
+    }
+
+    /// Creates an empty category and any missing levels above it
+    pub fn add_category(&self, path: &str) -> Result<()> {
+        let path = normalize(path)?;
+        let tx = self.client.unchecked_transaction()?;
+        if find_id(&tx, &path)?.is_some() {
+            return Err(Error::DuplicateCategory);
+        }
+        journal::begin(&tx)?;
+        create_path(&tx, &path)?;
+        journal::end(&tx, &format!("add category {path}"))?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Renames or moves a category along with everything below it, its
+    /// tasks keep pointing at it
+    pub fn rename_category(&self, from: &str, to: &str) -> Result<()> {
+        let (from, to) = (normalize(from)?, normalize(to)?);
+        let tx = self.client.unchecked_transaction()?;
+        let id = find_category(&tx, &from)?;
+        if from == to {
+            return Ok(());
+        }
+        if is_within(&to, &from) {
+            return Err(Error::Constraint(format!(
+                "cannot move '{from}' inside itself"
+            )));
+        }
+        if find_id(&tx, &to)?.is_some() {
+            return Err(Error::DuplicateCategory);
+        }
+
+        journal::begin(&tx)?;
+        let parent_id = match parent(&to) {
+            Some(parent) => Some(create_path(&tx, parent)?),
+            None => None,
+        };
+        move_subtree(&tx, id, &from, &to, parent_id)?;
+        journal::end(&tx, &format!("rename category {from} to {to}"))?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Moves every task of `from` into `into` and deletes `from`, the
+    /// categories below `from` move below `into`, merging with any of the
+    /// same name. Returns the number of tasks that changed category.
+    pub fn merge_categories(&self, from: &str, into: &str) -> Result<usize> {
+        let (from, into) = (normalize(from)?, normalize(into)?);
+        let tx = self.client.unchecked_transaction()?;
+        let from_id = find_category(&tx, &from)?;
+        let into_id = find_category(&tx, &into)?;
+        if from_id == into_id {
+            return Ok(0);
+        }
+        if is_within(&into, &from) {
+            return Err(Error::Constraint(format!(
+                "cannot merge '{from}' into '{into}' inside it"
+            )));
+        }
+
+        journal::begin(&tx)?;
+        let moved = merge(&tx, (from_id, &from), (into_id, &into))?;
+        journal::end(&tx, &format!("merge category {from} into {into}"))?;
+        tx.commit()?;
+        Ok(moved)
+    }
+
+    /// Deletes a category and everything below it, moving their tasks to
+    /// `reassign` or leaving them without a category. Returns the number of
+    /// tasks changed.
+    pub fn delete_category(&self, path: &str, reassign: Option<&str>) -> Result<usize> {
+        let path = normalize(path)?;
+        let tx = self.client.unchecked_transaction()?;
+        find_category(&tx, &path)?;
+        let target = match reassign {
+            Some(target) if is_within(&normalize(target)?, &path) => {
+                return Err(Error::Constraint(format!(
+                    "cannot move the tasks of '{path}' into itself"
+                )));
+            }
+            Some(target) => Some(self.category_id(&tx, target)?),
+            None => None,
+        };
+
+        journal::begin(&tx)?;
+        let changed = tx.execute(
+            &format!(
+                r"
+                UPDATE tasks SET category = ?
+                WHERE category IN (SELECT id FROM categories WHERE {IN_SUBTREE})
+                "
+            ),
+            rusqlite::params![target, path, path, path],
+        )?;
+        tx.execute(
+            &format!("DELETE FROM categories WHERE {IN_SUBTREE}"),
+            [&path, &path, &path],
+        )?;
+        journal::end(&tx, &format!("delete category {path}"))?;
+        tx.commit()?;
+        Ok(changed)
+    }
+
+    /// Finds the id of the category at `path`, creating it and the levels
+    /// above it unless categories are strict
+    pub(crate) fn category_id(&self, client: &Connection, path: &str) -> Result<i64> {
+        let path = normalize(path)?;
+        if self.strict_categories {
+            return find_category(client, &path);
+        }
+        create_path(client, &path)
+    }
+}
+
+fn find_id(client: &Connection, path: &str) -> Result<Option<i64>> {
+    Ok(client
+        .query_row("SELECT id FROM categories WHERE name = ?1", [path], |row| {
+            row.get(0)
+        })
+        .optional()?)
+}
+
+/// Finds the id of the category at a normalized path
+fn find_category(client: &Connection, path: &str) -> Result<i64> {
+    find_id(client, path)?.ok_or_else(|| Error::CategoryNotFound(path.to_string()))
+}
+
+/// Finds the id of the category at a normalized path, inserting it and every
+/// missing level above it
+pub(crate) fn create_path(client: &Connection, path: &str) -> Result<i64> {
+    let mut parent_id = None;
+    let mut end = 0;
+    loop {
+        end = path[end..]
+            .find(SEPARATOR)
+            .map_or(path.len(), |index| end + index);
+        let prefix = &path[..end];
+        let id = match find_id(client, prefix)? {
+            Some(id) => id,
+            None => {
+                client.execute(
+                    "INSERT INTO categories (name, parent_id) VALUES (?1, ?2)",
+                    rusqlite::params![prefix, parent_id],
+                )?;
+                client.last_insert_rowid()
+            }
+        };
+        if end == path.len() {
+            return Ok(id);
+        }
+        parent_id = Some(id);
+        end += 1;
+    }
+}
+
+/// Renames `from` and everything below it to start with `to`, and hangs the
+/// category under `parent_id`
+fn move_subtree(
+    client: &Connection,
+    id: i64,
+    from: &str,
+    to: &str,
+    parent_id: Option<i64>,
+) -> Result<()> {
+    client.execute(
+        &format!(
+            "UPDATE categories SET name = ? || substr(name, length(?) + 1) WHERE {IN_SUBTREE}"
+        ),
+        [to, from, from, from, from],
+    )?;
+    client.execute(
+        "UPDATE categories SET parent_id = ?2 WHERE id = ?1",
+        rusqlite::params![id, parent_id],
+    )?;
+    Ok(())
+}
+
+/// Merges the category `from` into `into`, children first
+fn merge(client: &Connection, from: (i64, &str), into: (i64, &str)) -> Result<usize> {
+    let children = client
+        .prepare("SELECT id, name FROM categories WHERE parent_id = ?1")?
+        .query_map([from.0], |row| Ok((row.get(0)?, row.get::<_, String>(1)?)))?
+        .collect::<rusqlite::Result<Vec<(i64, String)>>>()?;
+
+    let mut moved = 0;
+    for (child_id, child) in children {
+        let target = format!("{}/{}", into.1, leaf(&child));
+        match find_id(client, &target)? {
+            Some(target_id) => moved += merge(client, (child_id, &child), (target_id, &target))?,
+            None => move_subtree(client, child_id, &child, &target, Some(into.0))?,
+        }
+    }
+
+    moved += client.execute(
+        "UPDATE tasks SET category = ?2 WHERE category = ?1",
+        [from.0, into.0],
+    )?;
+    client.execute("DELETE FROM categories WHERE id = ?1", [from.0])?;
+    Ok(moved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ListOptions, NewTask, TaskEdit};
+
+    fn get_test_conn() -> Conn {
+        Conn::build(":memory:").unwrap()
+    }
+
+    fn add(conn: &Conn, info: &str, category: &str) -> i32 {
+        conn.add_task(&NewTask {
+            info: info.to_string(),
+            category: Some(category.to_string()),
+            ..Default::default()
+        })
+        .unwrap()
+        .id
+    }
+
+    fn category(conn: &Conn, id: i32) -> Option<String> {
+        conn.get_task(id).unwrap().category
+    }
+
+    /// (name, open, done) for every category
+    fn category_counts(conn: &Conn) -> Vec<(String, u32, u32)> {
+        conn.list_categories()
+            .unwrap()
+            .into_iter()
+            .map(|c| (c.name, c.open, c.done))
+            .collect()
+    }
+
+    /// (name, parent name) for every category, checks the two columns agree
+    fn tree(conn: &Conn) -> Vec<(String, Option<String>)> {
+        conn.client
+            .prepare(
+                r"
+                SELECT categories.name, parent.name FROM categories
+                LEFT JOIN categories AS parent ON categories.parent_id = parent.id
+                ORDER BY categories.name
+                ",
+            )
+            .unwrap()
+            .query_map((), |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap()
+    }
+
+    fn node(name: &str, parent: Option<&str>) -> (String, Option<String>) {
+        (name.to_string(), parent.map(str::to_string))
+    }
+
+    #[test]
+    fn test_paths() {
+        assert_eq!(normalize(" work / clientA ").unwrap(), "work/clientA");
+        assert!(matches!(
+            normalize("work//a"),
+            Err(Error::EmptyCategoryName)
+        ));
+        assert!(matches!(normalize("work/"), Err(Error::EmptyCategoryName)));
+        assert!(matches!(normalize(""), Err(Error::EmptyCategoryName)));
+        assert_eq!(leaf("work/clientA"), "clientA");
+        assert_eq!(parent("work/clientA/billing"), Some("work/clientA"));
+        assert_eq!(parent("work"), None);
+        assert!(is_within("work/a", "work"));
+        assert!(is_within("work", "work"));
+        assert!(!is_within("workshop", "work"));
+    }
+
+    #[test]
+    fn test_category_commands() {
+        let conn = get_test_conn();
+        add(&conn, "a", "work");
+        add(&conn, "b", "work");
+        conn.edit_task(
+            2,
+            &TaskEdit {
+                finish: Some(true),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        conn.add_category("home").unwrap();
+
+        assert_eq!(
+            category_counts(&conn),
+            vec![("home".to_string(), 0, 0), ("work".to_string(), 1, 1)]
+        );
+        let orphaned: Vec<_> = conn
+            .list_categories()
+            .unwrap()
+            .into_iter()
+            .filter(CategoryCount::is_orphaned)
+            .map(|c| c.name)
+            .collect();
+        assert_eq!(orphaned, vec!["home"]);
+
+        assert!(matches!(
+            conn.add_category("home"),
+            Err(Error::DuplicateCategory)
+        ));
+        assert!(matches!(
+            conn.add_category(""),
+            Err(Error::EmptyCategoryName)
+        ));
+
+        conn.rename_category("work", "job").unwrap();
+        assert_eq!(category(&conn, 1).as_deref(), Some("job"));
+        assert!(matches!(
+            conn.rename_category("job", "home"),
+            Err(Error::DuplicateCategory)
+        ));
+        assert!(matches!(
+            conn.rename_category("work", "x"),
+            Err(Error::CategoryNotFound(name)) if name == "work"
+        ));
+    }
+
+    #[test]
+    fn test_nested_paths() {
+        let conn = get_test_conn();
+        let billing = add(&conn, "invoice", "work/clientA/billing");
+        add(&conn, "call", "work/clientA");
+        add(&conn, "lunch", "workshop");
+        assert_eq!(
+            category(&conn, billing).as_deref(),
+            Some("work/clientA/billing")
+        );
+
+        assert_eq!(
+            tree(&conn),
+            vec![
+                node("work", None),
+                node("work/clientA", Some("work")),
+                node("work/clientA/billing", Some("work/clientA")),
+                node("workshop", None),
+            ]
+        );
+
+        // counts roll up and the tree order keeps children under parents
+        assert_eq!(
+            category_counts(&conn),
+            vec![
+                ("work".to_string(), 2, 0),
+                ("work/clientA".to_string(), 2, 0),
+                ("work/clientA/billing".to_string(), 1, 0),
+                ("workshop".to_string(), 1, 0),
+            ]
+        );
+        let depths: Vec<_> = conn
+            .list_categories()
+            .unwrap()
+            .iter()
+            .map(|c| (c.depth(), c.leaf().to_string()))
+            .collect();
+        assert_eq!(depths[2], (2, "billing".to_string()));
+
+        conn.add_category("work/clientB").unwrap();
+        assert!(matches!(
+            conn.add_category("work/clientA"),
+            Err(Error::DuplicateCategory)
+        ));
+    }
+
+    #[test]
+    fn test_filter_includes_descendants() {
+        let conn = get_test_conn();
+        add(&conn, "a", "work");
+        add(&conn, "b", "work/clientA/billing");
+        add(&conn, "c", "workshop");
+        add(&conn, "d", "home");
+
+        let ids = |category: &str| {
+            conn.list_tasks(&ListOptions {
+                category: Some(category.to_string()),
+                ..Default::default()
+            })
+            .unwrap()
+            .iter()
+            .map(|task| task.id)
+            .collect::<Vec<_>>()
+        };
+        assert_eq!(ids("work"), vec![1, 2]);
+        assert_eq!(ids("work/clientA"), vec![2]);
+        assert_eq!(ids("missing"), Vec::<i32>::new());
+    }
+
+    #[test]
+    fn test_rename_moves_subtree() {
+        let conn = get_test_conn();
+        add(&conn, "a", "work/clientA/billing");
+        add(&conn, "b", "work/clientA");
+
+        conn.rename_category("work/clientA", "clients/acme")
+            .unwrap();
+        assert_eq!(category(&conn, 1).as_deref(), Some("clients/acme/billing"));
+        assert_eq!(category(&conn, 2).as_deref(), Some("clients/acme"));
+        assert_eq!(
+            tree(&conn),
+            vec![
+                node("clients", None),
+                node("clients/acme", Some("clients")),
+                node("clients/acme/billing", Some("clients/acme")),
+                node("work", None),
+            ]
+        );
+
+        // a node cannot move below itself
+        assert!(matches!(
+            conn.rename_category("clients", "clients/acme/old"),
+            Err(Error::Constraint(_))
+        ));
+        // moving to the top level
+        conn.rename_category("clients/acme/billing", "billing")
+            .unwrap();
+        assert_eq!(category(&conn, 1).as_deref(), Some("billing"));
+        assert_eq!(tree(&conn)[0], node("billing", None));
+    }
+
+    #[test]
+    fn test_merge_categories() {
+        let conn = get_test_conn();
+        add(&conn, "a", "work");
+        add(&conn, "b", "job");
+        add(&conn, "c", "work");
+
+        assert_eq!(conn.merge_categories("work", "job").unwrap(), 2);
+        assert_eq!(category_counts(&conn), vec![("job".to_string(), 3, 0)]);
+        assert!(matches!(
+            conn.merge_categories("job", "missing"),
+            Err(Error::CategoryNotFound(name)) if name == "missing"
+        ));
+        assert_eq!(conn.merge_categories("job", "job").unwrap(), 0);
+        assert_eq!(category_counts(&conn).len(), 1);
+    }
+
+    #[test]
+    fn test_merge_nested() {
+        let conn = get_test_conn();
+        add(&conn, "a", "old/billing");
+        add(&conn, "b", "old/support");
+        add(&conn, "c", "new/billing");
+
+        // billing merges with the existing one, support moves over
+        assert_eq!(conn.merge_categories("old", "new").unwrap(), 1);
+        assert_eq!(category(&conn, 1).as_deref(), Some("new/billing"));
+        assert_eq!(category(&conn, 2).as_deref(), Some("new/support"));
+        assert_eq!(
+            tree(&conn),
+            vec![
+                node("new", None),
+                node("new/billing", Some("new")),
+                node("new/support", Some("new")),
+            ]
+        );
+
+        assert!(matches!(
+            conn.merge_categories("new", "new/billing"),
+            Err(Error::Constraint(_))
+        ));
+    }
+
+    #[test]
+    fn test_delete_category() {
+        let conn = get_test_conn();
+        add(&conn, "a", "work");
+        add(&conn, "b", "home");
+        add(&conn, "c", "misc");
+
+        assert_eq!(conn.delete_category("work", Some("home")).unwrap(), 1);
+        assert_eq!(category(&conn, 1).as_deref(), Some("home"));
+
+        assert_eq!(conn.delete_category("misc", None).unwrap(), 1);
+        assert_eq!(category(&conn, 3), None);
+        assert_eq!(category_counts(&conn), vec![("home".to_string(), 2, 0)]);
+
+        assert!(matches!(
+            conn.delete_category("home", Some("home")),
+            Err(Error::Constraint(_))
+        ));
+        assert!(matches!(
+            conn.delete_category("misc", None),
+            Err(Error::CategoryNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_delete_subtree() {
+        let conn = get_test_conn();
+        add(&conn, "a", "work/clientA");
+        add(&conn, "b", "work");
+        add(&conn, "c", "workshop");
+
+        assert!(matches!(
+            conn.delete_category("work", Some("work/clientA/old")),
+            Err(Error::Constraint(_))
+        ));
+        assert_eq!(conn.delete_category("work", Some("archive")).unwrap(), 2);
+        assert_eq!(category(&conn, 1).as_deref(), Some("archive"));
+        assert_eq!(
+            tree(&conn),
+            vec![node("archive", None), node("workshop", None)]
+        );
+    }
+
+    #[test]
+    fn test_strict_categories() {
+        let conn = get_test_conn().strict_categories(true);
+        assert!(matches!(
+            conn.add_task(&NewTask {
+                info: "a".to_string(),
+                category: Some("work".to_string()),
+                ..Default::default()
+            }),
+            Err(Error::CategoryNotFound(name)) if name == "work"
+        ));
+        assert!(conn.list_tasks(&ListOptions::default()).unwrap().is_empty());
+
+        conn.add_category("work").unwrap();
+        add(&conn, "a", "work");
+        assert!(matches!(
+            conn.edit_task(
+                1,
+                &TaskEdit {
+                    category: Some("work/new".to_string()),
+                    ..Default::default()
+                },
+            ),
+            Err(Error::CategoryNotFound(_))
+        ));
+        // reassigning on delete follows the same rule
+        assert!(matches!(
+            conn.delete_category("work", Some("home")),
+            Err(Error::CategoryNotFound(_))
+        ));
+        assert_eq!(category(&conn, 1).as_deref(), Some("work"));
+    }
+}
@@ -0,0 +1,388 @@
+use crate::{DateOptions, Error, Priority, Result, Theme, location};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+/// Settings read from `config.toml`, every key is optional
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Database used when neither `--db` nor `TODO_DB` is given
+    pub db_path: Option<PathBuf>,
+    pub list: ListDefaults,
+    pub dates: DateOptions,
+    pub theme: Theme,
+    /// Extra subcommands, `t = "list --category"` makes `todo t` run that
+    pub aliases: BTreeMap<String, String>,
+    /// Defaults applied to tasks added to the named category
+    pub categories: BTreeMap<String, CategoryDefaults>,
+    /// Only accept categories made with `todo category add` on add and edit
+    pub strict_categories: bool,
+
+    /// File this config was read from, `None` when nothing was loaded
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+}
+
+/// Flags `list` behaves as if it was given, the `[list]` table
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ListDefaults {
+    pub category: bool,
+    pub include_done: bool,
+}
+
+/// A `[categories.<name>]` table
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CategoryDefaults {
+    /// Due date used when `add` is not given one, in any `--due-date` format
+    pub due_date: Option<String>,
+    /// Priority used when `add` is not given one
+    pub priority: Option<Priority>,
+}
+
+impl Config {
+    /// Loads the config from its default location, if there is one
+    pub fn load_default() -> Result<Self> {
+        match location::config_file() {
+            Some(path) => Self::load(&path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Loads the config at `path`, a missing file is an empty config
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(err.into()),
+        };
+        let mut config = Self::parse(&text).map_err(|message| Error::Config {
+            path: path.to_path_buf(),
+            message,
+        })?;
+        config.path = Some(path.to_path_buf());
+        Ok(config)
+    }
+
+    /// Parses config text, errors name the line and key that was rejected
+    fn parse(text: &str) -> Result<Self, String> {
+        toml::from_str(text).map_err(|err| match err.span() {
+            Some(span) => describe_position(text, span.start, err.message()),
+            None => err.message().to_string(),
+        })
+    }
+
+    /// Replaces an alias in the first argument after the program name with
+    /// the words it stands for
+    pub fn expand_alias(&self, mut args: Vec<String>) -> Vec<String> {
+        if let Some(expansion) = args.get(1).and_then(|name| self.aliases.get(name)) {
+            let words = expansion.split_whitespace().map(str::to_string);
+            args.splice(1..2, words.collect::<Vec<_>>());
+        }
+        args
+    }
+
+    /// Every setting as dotted `key = value` pairs, defaults included
+    pub fn entries(&self) -> Vec<(String, String)> {
+        let mut entries = Vec::new();
+        if let Ok(toml::Value::Table(table)) = toml::Value::try_from(self) {
+            flatten("", &toml::Value::Table(table), &mut entries);
+        }
+        entries
+    }
+
+    /// Settings at or below a dotted `key`
+    pub fn get(&self, key: &str) -> Vec<(String, String)> {
+        let prefix = format!("{key}.");
+        self.entries()
+            .into_iter()
+            .filter(|(k, _)| k == key || k.starts_with(&prefix))
+            .collect()
+    }
+}
+
+/// Sets a dotted `key` in the config file at `path`, keeping its comments and
+/// layout, and refuses to write a file that would not load
+pub fn set_value(path: &Path, key: &str, value: &str) -> Result<()> {
+    let invalid = |message: String| Error::Config {
+        path: path.to_path_buf(),
+        message,
+    };
+
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err.into()),
+    };
+    let mut doc: toml_edit::DocumentMut = text
+        .parse()
+        .map_err(|err: toml_edit::TomlError| invalid(err.message().to_string()))?;
+
+    let (tables, name) = match key.rsplit_once('.') {
+        Some((tables, name)) => (tables.split('.').collect(), name),
+        None => (Vec::new(), key),
+    };
+    if name.is_empty() || tables.iter().any(|table: &&str| table.is_empty()) {
+        return Err(invalid(format!("'{key}' is not a valid key")));
+    }
+
+    let mut table = doc.as_table_mut();
+    for part in tables {
+        let item = table.entry(part).or_insert_with(|| {
+            let mut table = toml_edit::Table::new();
+            table.set_implicit(true);
+            toml_edit::Item::Table(table)
+        });
+        table = item
+            .as_table_mut()
+            .ok_or_else(|| invalid(format!("'{part}' in '{key}' is not a table")))?;
+    }
+    // bare words like `sun` are not valid toml values, store them as strings
+    let value = value
+        .parse::<toml_edit::Value>()
+        .unwrap_or_else(|_| value.into());
+    table[name] = toml_edit::value(value);
+
+    let text = doc.to_string();
+    Config::parse(&text).map_err(invalid)?;
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, text)?;
+    Ok(())
+}
+
+/// Where `config set` writes when no config has been loaded
+pub fn default_path() -> Result<PathBuf> {
+    location::config_file().ok_or_else(|| {
+        io::Error::new(
+            ErrorKind::NotFound,
+            "no config directory, set HOME or XDG_CONFIG_HOME",
+        )
+        .into()
+    })
+}
+
+/// Turns a byte offset into `line N, key `a.b`: message`
+fn describe_position(text: &str, offset: usize, message: &str) -> String {
+    let before = &text[..offset.min(text.len())];
+    let line_number = before.matches('\n').count() + 1;
+    let line = text.lines().nth(line_number - 1).unwrap_or_default();
+
+    let table = before
+        .lines()
+        .rev()
+        .map(str::trim)
+        .find(|line| line.starts_with('['))
+        .map(|line| line.trim_matches(['[', ']']).trim());
+    let key = line
+        .split_once('=')
+        .map(|(key, _)| key.trim())
+        .filter(|key| !key.is_empty());
+
+    match (table, key) {
+        (Some(table), Some(key)) => format!("line {line_number}, key `{table}.{key}`: {message}"),
+        (None, Some(key)) => format!("line {line_number}, key `{key}`: {message}"),
+        (Some(table), None) => format!("line {line_number}, table `{table}`: {message}"),
+        (None, None) => format!("line {line_number}: {message}"),
+    }
+}
+
+fn flatten(prefix: &str, value: &toml::Value, entries: &mut Vec<(String, String)>) {
+    match value {
+        toml::Value::Table(table) => {
+            for (key, value) in table {
+                let key = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{prefix}.{key}")
+                };
+                flatten(&key, value, entries);
+            }
+        }
+        toml::Value::String(text) => entries.push((prefix.to_string(), text.clone())),
+        value => entries.push((prefix.to_string(), value.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Color, DateOrder};
+    use chrono::Weekday;
+    use std::env;
+
+    const FULL: &str = r#"
+db_path = "~/notes/todo.db"
+strict_categories = true
+
+[list]
+category = true
+
+[dates]
+order = "dmy"
+week_start = "sunday"
+
+[theme]
+color = "never"
+overdue = "magenta"
+
+[aliases]
+t = "list --category"
+
+[categories.work]
+due_date = "eow"
+priority = "high"
+"#;
+
+    fn temp_file(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("todo-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir.join("config.toml")
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Config::parse("").unwrap(), Config::default());
+
+        let config = Config::parse(FULL).unwrap();
+        assert_eq!(config.db_path, Some(PathBuf::from("~/notes/todo.db")));
+        assert!(config.strict_categories);
+        assert!(config.list.category);
+        assert!(!config.list.include_done);
+        assert_eq!(config.dates.order, DateOrder::Dmy);
+        assert_eq!(config.dates.week_start, Weekday::Sun);
+        assert_eq!(config.dates.century_window, 50);
+        assert_eq!(config.theme.overdue, Color::Magenta);
+        assert_eq!(config.theme.done, Color::Dim);
+        assert_eq!(config.aliases["t"], "list --category");
+        assert_eq!(config.categories["work"].due_date.as_deref(), Some("eow"));
+        assert_eq!(config.categories["work"].priority, Some(Priority::High));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        // the wording after the key comes from the toml crate
+        assert!(
+            Config::parse("db_pth = 'x'")
+                .unwrap_err()
+                .starts_with("line 1, key `db_pth`:")
+        );
+        assert!(
+            Config::parse("\n[dates]\nweek_start = \"funday\"\n")
+                .unwrap_err()
+                .starts_with("line 3, key `dates.week_start`:")
+        );
+        assert!(
+            Config::parse("[list]\ncategory = 3\n")
+                .unwrap_err()
+                .starts_with("line 2, key `list.category`:")
+        );
+        assert!(
+            Config::parse("[theme]\ncolor = \"sometimes\"\n")
+                .unwrap_err()
+                .starts_with("line 2, key `theme.color`:")
+        );
+        assert!(Config::parse("[list\n").unwrap_err().starts_with("line 1"));
+
+        let path = temp_file("parse-errors");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "[list]\n\ninclude_done = 'yes'\n").unwrap();
+        match Config::load(&path) {
+            Err(Error::Config { path: at, message }) => {
+                assert_eq!(at, path);
+                assert!(message.starts_with("line 3, key `list.include_done`:"));
+            }
+            other => panic!("expected a config error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_load_missing() {
+        let config = Config::load(Path::new("/nonexistent/todo/config.toml")).unwrap();
+        assert_eq!(config, Config::default());
+    }
+
+    #[test]
+    fn test_expand_alias() {
+        let config = Config::parse(FULL).unwrap();
+        let args = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            config.expand_alias(args(&["todo", "t", "-i"])),
+            args(&["todo", "list", "--category", "-i"])
+        );
+        assert_eq!(
+            config.expand_alias(args(&["todo", "add", "t"])),
+            args(&["todo", "add", "t"])
+        );
+        assert_eq!(config.expand_alias(args(&["todo"])), args(&["todo"]));
+    }
+
+    #[test]
+    fn test_entries() {
+        let config = Config::parse(FULL).unwrap();
+        let entries = config.entries();
+        assert!(entries.contains(&("db_path".to_string(), "~/notes/todo.db".to_string())));
+        assert!(entries.contains(&("list.include_done".to_string(), "false".to_string())));
+        assert!(entries.contains(&("dates.week_start".to_string(), "Sun".to_string())));
+        assert!(entries.contains(&("categories.work.due_date".to_string(), "eow".to_string())));
+
+        assert_eq!(
+            config.get("aliases"),
+            vec![("aliases.t".to_string(), "list --category".to_string())]
+        );
+        assert_eq!(
+            config.get("list.category"),
+            vec![("list.category".to_string(), "true".to_string())]
+        );
+        assert!(config.get("nothing").is_empty());
+    }
+
+    #[test]
+    fn test_set_value() {
+        let path = temp_file("set-value");
+
+        set_value(&path, "list.category", "true").unwrap();
+        set_value(&path, "dates.week_start", "sun").unwrap();
+        set_value(&path, "aliases.t", "list --category").unwrap();
+        set_value(&path, "categories.work.due_date", "+1w").unwrap();
+
+        let config = Config::load(&path).unwrap();
+        assert!(config.list.category);
+        assert_eq!(config.dates.week_start, Weekday::Sun);
+        assert_eq!(config.aliases["t"], "list --category");
+        assert_eq!(config.categories["work"].due_date.as_deref(), Some("+1w"));
+
+        // comments survive and invalid values are not written
+        let text = format!("# my settings\n{}", fs::read_to_string(&path).unwrap());
+        fs::write(&path, &text).unwrap();
+        assert!(matches!(
+            set_value(&path, "list.category", "maybe"),
+            Err(Error::Config { .. })
+        ));
+        assert!(matches!(
+            set_value(&path, "colour", "red"),
+            Err(Error::Config { .. })
+        ));
+        assert!(matches!(
+            set_value(&path, "list.category.deep", "1"),
+            Err(Error::Config { .. })
+        ));
+        assert_eq!(fs::read_to_string(&path).unwrap(), text);
+
+        set_value(&path, "list.include_done", "true").unwrap();
+        assert!(
+            fs::read_to_string(&path)
+                .unwrap()
+                .starts_with("# my settings\n")
+        );
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
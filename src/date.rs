@@ -0,0 +1,815 @@
+use chrono::{Datelike, Days, Months, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+
+/// Knobs for resolving partial dates against today, the `[dates]` table of
+/// the config file
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DateOptions {
+    /// Order of the numeric parts in `1/2/2025` style dates
+    pub order: DateOrder,
+    /// Two digit years land at most this many years after today's year,
+    /// anything later falls back into the previous century
+    pub century_window: u32,
+    /// First day of the week, `eow` resolves to the day before it
+    pub week_start: Weekday,
+}
+
+impl Default for DateOptions {
+    fn default() -> Self {
+        Self {
+            order: DateOrder::Ymd,
+            century_window: 50,
+            week_start: Weekday::Mon,
+        }
+    }
+}
+
+/// Order of year, month and day in numeric dates, a missing year keeps the
+/// month and day in the same relative order
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DateOrder {
+    #[default]
+    Ymd,
+    Mdy,
+    Dmy,
+}
+
+impl DateOrder {
+    /// Rearranges typed parts into year, month, day order
+    fn arrange<'a>(&self, parts: Vec<&'a str>) -> Vec<&'a str> {
+        match (self, parts.as_slice()) {
+            (Self::Mdy, [m, d, y]) => vec![y, m, d],
+            (Self::Dmy, [d, m, y]) => vec![y, m, d],
+            (Self::Dmy, [d, m]) => vec![m, d],
+            _ => parts,
+        }
+    }
+}
+
+/// Resolves a due date typed on the command line into YYYY-MM-DD
+pub fn format_date(
+    date_str: &str,
+    today: &NaiveDate,
+    options: &DateOptions,
+) -> Result<String, DateError> {
+    let partial_date = PartialDate::build(date_str, options.order).map_err(DateError::Format)?;
+    make_date(&partial_date, today, options)
+}
+
+#[derive(Debug, PartialEq)]
+enum PartialDate {
+    Day { day: u32 },
+    MonthDay { month: u32, day: u32 },
+    YearMonthDay { year: i32, month: u32, day: u32 },
+    Offset { amount: i32, period: Period },
+    Weekday { weekday: Weekday, next: bool },
+    EndOf { period: Period },
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Period {
+    Day,
+    Week,
+    Month,
+    Quarter,
+    Year,
+}
+
+/// Why the text of a date could not be split into numbers
+#[derive(Debug, PartialEq)]
+#[allow(clippy::enum_variant_names)]
+pub enum PartialDateError {
+    YearError(String),
+    MonthError(String),
+    DayError(String),
+    PartError(String),
+    OffsetError(String),
+    WeekdayError(String),
+}
+
+impl Display for PartialDateError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::YearError(y) => write!(f, "'{y}' is not a valid year"),
+            Self::MonthError(m) => write!(f, "'{m}' is not a valid month"),
+            Self::DayError(d) => write!(f, "'{d}' is not a valid day"),
+            Self::PartError(date) => {
+                write!(f, "'{date}' should look like YYYY-MM-DD, MM-DD or DD")
+            }
+            Self::OffsetError(offset) => {
+                write!(f, "'{offset}' should look like +3d, +2w, +1m, +1q or +1y")
+            }
+            Self::WeekdayError(weekday) => write!(f, "'{weekday}' is not a day of the week"),
+        }
+    }
+}
+
+impl std::error::Error for PartialDateError {}
+
+impl PartialDate {
+    fn build(date_str: &str, order: DateOrder) -> Result<Self, PartialDateError> {
+        let word = date_str.trim().to_lowercase();
+        match word.as_str() {
+            "today" => {
+                return Ok(Self::Offset {
+                    amount: 0,
+                    period: Period::Day,
+                });
+            }
+            "tomorrow" | "tmr" => {
+                return Ok(Self::Offset {
+                    amount: 1,
+                    period: Period::Day,
+                });
+            }
+            "yesterday" => {
+                return Ok(Self::Offset {
+                    amount: -1,
+                    period: Period::Day,
+                });
+            }
+            "eow" => {
+                return Ok(Self::EndOf {
+                    period: Period::Week,
+                });
+            }
+            "eom" => {
+                return Ok(Self::EndOf {
+                    period: Period::Month,
+                });
+            }
+            "eoq" => {
+                return Ok(Self::EndOf {
+                    period: Period::Quarter,
+                });
+            }
+            "eoy" => {
+                return Ok(Self::EndOf {
+                    period: Period::Year,
+                });
+            }
+            _ => {}
+        }
+
+        if word.starts_with(['+', '-']) {
+            return Self::build_offset(&word);
+        }
+
+        if let Some(weekday) = word.strip_prefix("next ") {
+            return Ok(Self::Weekday {
+                weekday: weekday
+                    .trim()
+                    .parse()
+                    .map_err(|_| PartialDateError::WeekdayError(weekday.trim().to_string()))?,
+                next: true,
+            });
+        }
+
+        if let Ok(weekday) = word.parse() {
+            return Ok(Self::Weekday {
+                weekday,
+                next: false,
+            });
+        }
+
+        let cleaned_date = date_str.trim().replace('/', "-");
+        let parts = order.arrange(cleaned_date.split('-').collect());
+        Ok(match parts.as_slice() {
+            [y, m, d] => Self::YearMonthDay {
+                year: y
+                    .parse()
+                    .map_err(|_| PartialDateError::YearError(y.to_string()))?,
+                month: m
+                    .parse()
+                    .map_err(|_| PartialDateError::MonthError(m.to_string()))?,
+                day: d
+                    .parse()
+                    .map_err(|_| PartialDateError::DayError(d.to_string()))?,
+            },
+            [m, d] => Self::MonthDay {
+                month: m
+                    .parse()
+                    .map_err(|_| PartialDateError::MonthError(m.to_string()))?,
+                day: d
+                    .parse()
+                    .map_err(|_| PartialDateError::DayError(d.to_string()))?,
+            },
+            [d] => Self::Day {
+                day: d
+                    .parse()
+                    .map_err(|_| PartialDateError::DayError(d.to_string()))?,
+            },
+            _ => Err(PartialDateError::PartError(cleaned_date))?,
+        })
+    }
+
+    /// Parses a signed count followed by a unit, like `+3d` or `-1w`
+    fn build_offset(offset: &str) -> Result<Self, PartialDateError> {
+        let error = || PartialDateError::OffsetError(offset.to_string());
+        let (index, unit) = offset.char_indices().last().ok_or_else(error)?;
+        let amount = &offset[..index];
+        let period = match unit {
+            'd' => Period::Day,
+            'w' => Period::Week,
+            'm' => Period::Month,
+            'q' => Period::Quarter,
+            'y' => Period::Year,
+            _ => return Err(error()),
+        };
+        Ok(Self::Offset {
+            amount: amount.parse().map_err(|_| error())?,
+            period,
+        })
+    }
+}
+
+/// Why a date could not be resolved
+#[derive(Debug, PartialEq)]
+pub enum DateError {
+    Format(PartialDateError),
+    Year(i32),
+    Month(u32),
+    Day(u32),
+    NoSuchDay { year: i32, month: u32, day: u32 },
+    OutOfRange,
+}
+
+impl Display for DateError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Format(err) => write!(f, "{err}"),
+            Self::Year(year) => write!(f, "year {year} should have 2 or 4 digits"),
+            Self::Month(month) => write!(f, "month {month} is not between 1 and 12"),
+            Self::Day(day) => write!(f, "day {day} is not between 1 and 31"),
+            Self::NoSuchDay { year, month, day } => {
+                write!(f, "{year}-{month:02} does not have a day {day}")
+            }
+            Self::OutOfRange => write!(f, "date is too far away"),
+        }
+    }
+}
+
+impl std::error::Error for DateError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Format(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Fills in the missing parts of a date so that it lands on or after `today`,
+/// relative dates are counted from `today`
+fn make_date(
+    partial_date: &PartialDate,
+    today: &NaiveDate,
+    options: &DateOptions,
+) -> Result<String, DateError> {
+    let date = match *partial_date {
+        PartialDate::YearMonthDay { year, month, day } => {
+            let year = match year {
+                1000.. => year,
+                0..100 => full_year(year, today.year(), options.century_window),
+                _ => return Err(DateError::Year(year)),
+            };
+            ymd(year, month, day)?
+        }
+        PartialDate::MonthDay { month, day } => {
+            check_month_day(month, day)?;
+            if (month, day) >= (today.month(), today.day()) {
+                ymd(today.year(), month, day)?
+            } else {
+                ymd(today.year() + 1, month, day)?
+            }
+        }
+        PartialDate::Day { day } => {
+            check_month_day(today.month(), day)?;
+            if day >= today.day() {
+                ymd(today.year(), today.month(), day)?
+            } else {
+                let next_month = today
+                    .with_day(1)
+                    .and_then(|first| first.checked_add_months(Months::new(1)))
+                    .ok_or(DateError::OutOfRange)?;
+                ymd(next_month.year(), next_month.month(), day)?
+            }
+        }
+        PartialDate::Offset { amount, period } => shift(*today, amount, period)?,
+        PartialDate::Weekday { weekday, next } => {
+            let days_ahead =
+                (weekday.num_days_from_monday() + 7 - today.weekday().num_days_from_monday()) % 7;
+            let days_ahead = if next { days_ahead + 7 } else { days_ahead };
+            shift(*today, days_ahead as i32, Period::Day)?
+        }
+        PartialDate::EndOf { period } => end_of(*today, period, options.week_start)?,
+    };
+
+    Ok(date.format("%Y-%m-%d").to_string())
+}
+
+/// Moves `date` by `amount` periods, clamping to the end of shorter months
+fn shift(date: NaiveDate, amount: i32, period: Period) -> Result<NaiveDate, DateError> {
+    let days = |count: i32| Days::new(count.unsigned_abs() as u64);
+    let months = |count: i32| Months::new(count.unsigned_abs());
+    let count = match period {
+        Period::Day | Period::Month => amount,
+        Period::Week => amount.checked_mul(7).ok_or(DateError::OutOfRange)?,
+        Period::Quarter => amount.checked_mul(3).ok_or(DateError::OutOfRange)?,
+        Period::Year => amount.checked_mul(12).ok_or(DateError::OutOfRange)?,
+    };
+    match (period, count >= 0) {
+        (Period::Day | Period::Week, true) => date.checked_add_days(days(count)),
+        (Period::Day | Period::Week, false) => date.checked_sub_days(days(count)),
+        (_, true) => date.checked_add_months(months(count)),
+        (_, false) => date.checked_sub_months(months(count)),
+    }
+    .ok_or(DateError::OutOfRange)
+}
+
+/// Last day of the week, month, quarter or year containing `date`
+fn end_of(date: NaiveDate, period: Period, week_start: Weekday) -> Result<NaiveDate, DateError> {
+    let last_of_month = |month: u32| {
+        NaiveDate::from_ymd_opt(date.year(), month, 1)
+            .and_then(|first| first.checked_add_months(Months::new(1)))
+            .and_then(|next| next.pred_opt())
+            .ok_or(DateError::OutOfRange)
+    };
+    match period {
+        Period::Day => Ok(date),
+        Period::Week => {
+            let last_day = week_start.pred().num_days_from_monday();
+            let days_left = (last_day + 7 - date.weekday().num_days_from_monday()) % 7;
+            shift(date, days_left as i32, Period::Day)
+        }
+        Period::Month => last_of_month(date.month()),
+        Period::Quarter => last_of_month(date.month0() / 3 * 3 + 3),
+        Period::Year => last_of_month(12),
+    }
+}
+
+/// Places a two digit year in the century window around `this_year`
+fn full_year(short_year: i32, this_year: i32, century_window: u32) -> i32 {
+    let latest = this_year + century_window as i32;
+    let year = this_year - this_year.rem_euclid(100) + short_year;
+    if year > latest {
+        year - 100
+    } else if year <= latest - 100 {
+        year + 100
+    } else {
+        year
+    }
+}
+
+fn check_month_day(month: u32, day: u32) -> Result<(), DateError> {
+    if !(1..=12).contains(&month) {
+        Err(DateError::Month(month))
+    } else if !(1..=31).contains(&day) {
+        Err(DateError::Day(day))
+    } else {
+        Ok(())
+    }
+}
+
+fn ymd(year: i32, month: u32, day: u32) -> Result<NaiveDate, DateError> {
+    check_month_day(month, day)?;
+    NaiveDate::from_ymd_opt(year, month, day).ok_or(DateError::NoSuchDay { year, month, day })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_make_date() {
+        assert_eq!(
+            make_date(
+                &PartialDate::YearMonthDay {
+                    year: 2021,
+                    month: 12,
+                    day: 1
+                },
+                &NaiveDate::from_ymd_opt(2021, 12, 1).unwrap(),
+                &DateOptions::default(),
+            )
+            .unwrap(),
+            "2021-12-01".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_partial_date() {
+        // sanity
+        assert_eq!(
+            PartialDate::build("2024-02-12", DateOrder::Ymd).unwrap(),
+            PartialDate::YearMonthDay {
+                year: 2024,
+                month: 2,
+                day: 12,
+            }
+        );
+
+        // trim
+        assert_eq!(
+            PartialDate::build("    2024-02-12   \n ", DateOrder::Ymd).unwrap(),
+            PartialDate::YearMonthDay {
+                year: 2024,
+                month: 2,
+                day: 12,
+            }
+        );
+
+        // replacement
+        assert_eq!(
+            PartialDate::build("2024/02/12", DateOrder::Ymd).unwrap(),
+            PartialDate::YearMonthDay {
+                year: 2024,
+                month: 2,
+                day: 12,
+            }
+        );
+
+        // 0 parts
+        assert_eq!(
+            PartialDate::build("   ", DateOrder::Ymd),
+            Err(PartialDateError::DayError("".to_string())),
+        );
+
+        // 1 part
+        assert_eq!(
+            PartialDate::build("22", DateOrder::Ymd).unwrap(),
+            PartialDate::Day { day: 22 }
+        );
+
+        // 2 thing
+        assert_eq!(
+            PartialDate::build("02-22", DateOrder::Ymd).unwrap(),
+            PartialDate::MonthDay { month: 2, day: 22 }
+        );
+
+        // 3 thing
+        assert_eq!(
+            PartialDate::build("2024-02-22", DateOrder::Ymd).unwrap(),
+            PartialDate::YearMonthDay {
+                year: 2024,
+                month: 2,
+                day: 22
+            }
+        );
+
+        // 4 thing
+        assert_eq!(
+            PartialDate::build("20-20-20-20", DateOrder::Ymd),
+            Err(PartialDateError::PartError("20-20-20-20".to_string())),
+        );
+
+        // test invalid part
+        assert_eq!(
+            PartialDate::build("20-20-hello", DateOrder::Ymd),
+            Err(PartialDateError::DayError("hello".to_string())),
+        );
+    }
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 9, 20).unwrap()
+    }
+
+    fn fmt(input: &str) -> Result<String, DateError> {
+        format_date(input, &today(), &DateOptions::default())
+    }
+
+    #[test]
+    fn test_format_date_absolute() {
+        assert_eq!(fmt("2025-09-20"), Ok("2025-09-20".to_string()));
+        assert_eq!(fmt("\t  2025/9/2 \n "), Ok("2025-09-02".to_string()));
+        assert_eq!(fmt("2024-02-29"), Ok("2024-02-29".to_string()));
+        assert_eq!(
+            fmt("2025-02-29"),
+            Err(DateError::NoSuchDay {
+                year: 2025,
+                month: 2,
+                day: 29
+            })
+        );
+        assert_eq!(fmt("2025-13-01"), Err(DateError::Month(13)));
+        assert_eq!(fmt("2025-01-00"), Err(DateError::Day(0)));
+    }
+
+    #[test]
+    fn test_format_date_short_year() {
+        assert_eq!(fmt("25-09-20"), Ok("2025-09-20".to_string()));
+        assert_eq!(fmt("5-1-1"), Ok("2005-01-01".to_string()));
+        assert_eq!(fmt("75-1-1"), Ok("2075-01-01".to_string()));
+        assert_eq!(fmt("76-1-1"), Ok("1976-01-01".to_string()));
+        assert_eq!(fmt("99-12-31"), Ok("1999-12-31".to_string()));
+        assert_eq!(fmt("999-1-1"), Err(DateError::Year(999)));
+        assert_eq!(fmt("100-1-1"), Err(DateError::Year(100)));
+
+        let narrow = DateOptions {
+            century_window: 0,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_date("26-1-1", &today(), &narrow),
+            Ok("1926-01-01".to_string())
+        );
+        assert_eq!(
+            format_date("25-1-1", &today(), &narrow),
+            Ok("2025-01-01".to_string())
+        );
+    }
+
+    #[test]
+    fn test_full_year_window() {
+        assert_eq!(full_year(0, 2099, 50), 2100);
+        assert_eq!(full_year(49, 2099, 50), 2149);
+        assert_eq!(full_year(50, 2099, 50), 2050);
+        assert_eq!(full_year(99, 2000, 99), 2099);
+        assert_eq!(full_year(0, 2000, 99), 2000);
+    }
+
+    #[test]
+    fn test_format_date_month_day() {
+        // today
+        assert_eq!(fmt("9-20"), Ok("2025-09-20".to_string()));
+        // later this year
+        assert_eq!(fmt("12/31"), Ok("2025-12-31".to_string()));
+        // already passed rolls into next year
+        assert_eq!(fmt("9-19"), Ok("2026-09-19".to_string()));
+        assert_eq!(fmt("1-1"), Ok("2026-01-01".to_string()));
+        // no leap day next year
+        assert_eq!(
+            fmt("2-29"),
+            Err(DateError::NoSuchDay {
+                year: 2026,
+                month: 2,
+                day: 29
+            })
+        );
+        assert_eq!(
+            format_date(
+                "2-29",
+                &NaiveDate::from_ymd_opt(2027, 3, 1).unwrap(),
+                &DateOptions::default()
+            ),
+            Ok("2028-02-29".to_string())
+        );
+        assert_eq!(
+            fmt("2-30"),
+            Err(DateError::NoSuchDay {
+                year: 2026,
+                month: 2,
+                day: 30
+            })
+        );
+        assert_eq!(fmt("0-10"), Err(DateError::Month(0)));
+        assert_eq!(fmt("13-10"), Err(DateError::Month(13)));
+        assert_eq!(fmt("10-32"), Err(DateError::Day(32)));
+    }
+
+    #[test]
+    fn test_format_date_day() {
+        // today
+        assert_eq!(fmt("20"), Ok("2025-09-20".to_string()));
+        // later this month
+        assert_eq!(fmt("30"), Ok("2025-09-30".to_string()));
+        // already passed rolls into next month
+        assert_eq!(fmt("19"), Ok("2025-10-19".to_string()));
+        assert_eq!(fmt("01"), Ok("2025-10-01".to_string()));
+        // september has no 31st
+        assert_eq!(
+            fmt("31"),
+            Err(DateError::NoSuchDay {
+                year: 2025,
+                month: 9,
+                day: 31
+            })
+        );
+        // rolls over the end of the year
+        let december = NaiveDate::from_ymd_opt(2025, 12, 15).unwrap();
+        assert_eq!(
+            format_date("3", &december, &DateOptions::default()),
+            Ok("2026-01-03".to_string())
+        );
+        // rolling from the 31st into a 30 day month
+        let january = NaiveDate::from_ymd_opt(2026, 1, 31).unwrap();
+        assert_eq!(
+            format_date("30", &january, &DateOptions::default()),
+            Err(DateError::NoSuchDay {
+                year: 2026,
+                month: 2,
+                day: 30
+            })
+        );
+        assert_eq!(fmt("0"), Err(DateError::Day(0)));
+        assert_eq!(fmt("32"), Err(DateError::Day(32)));
+    }
+
+    #[test]
+    fn test_date_errors() {
+        assert_eq!(
+            fmt("20-20-20-20"),
+            Err(DateError::Format(PartialDateError::PartError(
+                "20-20-20-20".to_string()
+            )))
+        );
+        assert_eq!(
+            fmt("2025-02-30").unwrap_err().to_string(),
+            "2025-02 does not have a day 30"
+        );
+        assert_eq!(
+            fmt("someday").unwrap_err().to_string(),
+            "'someday' is not a valid day"
+        );
+        assert_eq!(
+            fmt("next week").unwrap_err().to_string(),
+            "'week' is not a day of the week"
+        );
+        assert_eq!(
+            fmt("+3x").unwrap_err().to_string(),
+            "'+3x' should look like +3d, +2w, +1m, +1q or +1y"
+        );
+        assert_eq!(fmt("+999999999y"), Err(DateError::OutOfRange));
+    }
+
+    #[test]
+    fn test_partial_date_words() {
+        assert_eq!(
+            PartialDate::build(" Today ", DateOrder::Ymd).unwrap(),
+            PartialDate::Offset {
+                amount: 0,
+                period: Period::Day
+            }
+        );
+        assert_eq!(
+            PartialDate::build("yesterday", DateOrder::Ymd).unwrap(),
+            PartialDate::Offset {
+                amount: -1,
+                period: Period::Day
+            }
+        );
+        assert_eq!(
+            PartialDate::build("FRI", DateOrder::Ymd).unwrap(),
+            PartialDate::Weekday {
+                weekday: Weekday::Fri,
+                next: false
+            }
+        );
+        assert_eq!(
+            PartialDate::build("next  tuesday", DateOrder::Ymd).unwrap(),
+            PartialDate::Weekday {
+                weekday: Weekday::Tue,
+                next: true
+            }
+        );
+        assert_eq!(
+            PartialDate::build("+2w", DateOrder::Ymd).unwrap(),
+            PartialDate::Offset {
+                amount: 2,
+                period: Period::Week
+            }
+        );
+        assert_eq!(
+            PartialDate::build("eoq", DateOrder::Ymd).unwrap(),
+            PartialDate::EndOf {
+                period: Period::Quarter
+            }
+        );
+        assert_eq!(
+            PartialDate::build("+d", DateOrder::Ymd),
+            Err(PartialDateError::OffsetError("+d".to_string()))
+        );
+        assert_eq!(
+            PartialDate::build("-", DateOrder::Ymd),
+            Err(PartialDateError::OffsetError("-".to_string()))
+        );
+        assert_eq!(
+            PartialDate::build("+é", DateOrder::Ymd),
+            Err(PartialDateError::OffsetError("+é".to_string()))
+        );
+        assert_eq!(
+            PartialDate::build("next", DateOrder::Ymd),
+            Err(PartialDateError::DayError("next".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_format_date_relative() {
+        // 2025-09-20 is a saturday
+        assert_eq!(fmt("today"), Ok("2025-09-20".to_string()));
+        assert_eq!(fmt("tomorrow"), Ok("2025-09-21".to_string()));
+        assert_eq!(fmt("yesterday"), Ok("2025-09-19".to_string()));
+        assert_eq!(fmt("+3d"), Ok("2025-09-23".to_string()));
+        assert_eq!(fmt("+15d"), Ok("2025-10-05".to_string()));
+        assert_eq!(fmt("-1w"), Ok("2025-09-13".to_string()));
+        assert_eq!(fmt("+2w"), Ok("2025-10-04".to_string()));
+        assert_eq!(fmt("+1m"), Ok("2025-10-20".to_string()));
+        assert_eq!(fmt("+1q"), Ok("2025-12-20".to_string()));
+        assert_eq!(fmt("+1y"), Ok("2026-09-20".to_string()));
+
+        // months clamp to the end of shorter months
+        let jan_31 = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+        let options = DateOptions::default();
+        assert_eq!(
+            format_date("+1m", &jan_31, &options),
+            Ok("2024-02-29".to_string())
+        );
+        let leap_day = NaiveDate::from_ymd_opt(2024, 2, 29).unwrap();
+        assert_eq!(
+            format_date("+1y", &leap_day, &options),
+            Ok("2025-02-28".to_string())
+        );
+    }
+
+    #[test]
+    fn test_format_date_weekday() {
+        // 2025-09-20 is a saturday
+        assert_eq!(fmt("sat"), Ok("2025-09-20".to_string()));
+        assert_eq!(fmt("sun"), Ok("2025-09-21".to_string()));
+        assert_eq!(fmt("friday"), Ok("2025-09-26".to_string()));
+        assert_eq!(fmt("next sat"), Ok("2025-09-27".to_string()));
+        assert_eq!(fmt("next tue"), Ok("2025-09-30".to_string()));
+    }
+
+    #[test]
+    fn test_format_date_end_of() {
+        // 2025-09-20 is a saturday
+        assert_eq!(fmt("eow"), Ok("2025-09-21".to_string()));
+        assert_eq!(fmt("eom"), Ok("2025-09-30".to_string()));
+        assert_eq!(fmt("eoq"), Ok("2025-09-30".to_string()));
+        assert_eq!(fmt("eoy"), Ok("2025-12-31".to_string()));
+
+        let sunday_start = DateOptions {
+            week_start: Weekday::Sun,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_date("eow", &today(), &sunday_start),
+            Ok("2025-09-20".to_string())
+        );
+
+        let feb = NaiveDate::from_ymd_opt(2024, 2, 3).unwrap();
+        let options = DateOptions::default();
+        assert_eq!(
+            format_date("eom", &feb, &options),
+            Ok("2024-02-29".to_string())
+        );
+        assert_eq!(
+            format_date("eoq", &feb, &options),
+            Ok("2024-03-31".to_string())
+        );
+        let nov = NaiveDate::from_ymd_opt(2025, 11, 30).unwrap();
+        assert_eq!(
+            format_date("eoq", &nov, &options),
+            Ok("2025-12-31".to_string())
+        );
+    }
+
+    #[test]
+    fn test_date_order() {
+        assert_eq!(
+            PartialDate::build("2/12/2024", DateOrder::Mdy).unwrap(),
+            PartialDate::YearMonthDay {
+                year: 2024,
+                month: 2,
+                day: 12,
+            }
+        );
+        assert_eq!(
+            PartialDate::build("12/2/2024", DateOrder::Dmy).unwrap(),
+            PartialDate::YearMonthDay {
+                year: 2024,
+                month: 2,
+                day: 12,
+            }
+        );
+        assert_eq!(
+            PartialDate::build("12/2", DateOrder::Dmy).unwrap(),
+            PartialDate::MonthDay { month: 2, day: 12 }
+        );
+        assert_eq!(
+            PartialDate::build("2/12", DateOrder::Mdy).unwrap(),
+            PartialDate::MonthDay { month: 2, day: 12 }
+        );
+        // errors still name the part that was wrong
+        assert_eq!(
+            PartialDate::build("40/x/2024", DateOrder::Dmy),
+            Err(PartialDateError::MonthError("x".to_string()))
+        );
+
+        let dmy = DateOptions {
+            order: DateOrder::Dmy,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_date("1/2/26", &today(), &dmy),
+            Ok("2026-02-01".to_string())
+        );
+        assert_eq!(
+            format_date("+1w", &today(), &dmy),
+            Ok("2025-09-27".to_string())
+        );
+    }
+}
@@ -0,0 +1,220 @@
+use crate::{Conn, Error, Result, ensure_task, journal};
+use rusqlite::Connection;
+use serde::Serialize;
+
+/// Matches tasks with at least one open prerequisite
+pub(crate) const BLOCKED: &str = r"
+    EXISTS (SELECT 1 FROM task_deps
+        JOIN tasks AS blocker ON task_deps.blocker_id = blocker.id
+        WHERE task_deps.blocked_id = tasks.id AND NOT blocker.done
+            AND blocker.deleted_at IS NULL)";
+
+/// `task_deps` row, `blocker` has to be done before `blocked` can be
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Dependency {
+    pub blocker: i32,
+    pub blocked: i32,
+}
+
+impl Conn {
+    /// Records that `blocker` has to be done before `blocked`, refusing
+    /// dependencies that would make a cycle
+    pub fn add_dependency(&self, blocker: i32, blocked: i32) -> Result<()> {
+        let tx = self.client.unchecked_transaction()?;
+        ensure_task(&tx, blocker)?;
+        ensure_task(&tx, blocked)?;
+        if blocker == blocked || blocks(&tx, blocked, blocker)? {
+            return Err(Error::DependencyCycle { blocker, blocked });
+        }
+
+        journal::begin(&tx)?;
+        tx.execute(
+            "INSERT OR IGNORE INTO task_deps (blocker_id, blocked_id) VALUES (?1, ?2)",
+            [blocker, blocked],
+        )?;
+        journal::end(&tx, &format!("make task {blocked} wait on task {blocker}"))?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Drops a dependency, one that does not exist is ignored
+    pub fn remove_dependency(&self, blocker: i32, blocked: i32) -> Result<()> {
+        let tx = self.client.unchecked_transaction()?;
+        journal::begin(&tx)?;
+        tx.execute(
+            "DELETE FROM task_deps WHERE blocker_id = ?1 AND blocked_id = ?2",
+            [blocker, blocked],
+        )?;
+        journal::end(
+            &tx,
+            &format!("stop task {blocked} waiting on task {blocker}"),
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Every dependency, by blocker then blocked task
+    pub fn list_dependencies(&self) -> Result<Vec<Dependency>> {
+        let deps = self
+            .client
+            .prepare(
+                r"
+                SELECT blocker_id, blocked_id FROM task_deps
+                JOIN tasks AS blocker ON blocker.id = blocker_id
+                JOIN tasks AS blocked ON blocked.id = blocked_id
+                WHERE blocker.deleted_at IS NULL AND blocked.deleted_at IS NULL
+                ORDER BY blocker_id, blocked_id
+                ",
+            )?
+            .query_map((), |row| {
+                Ok(Dependency {
+                    blocker: row.get(0)?,
+                    blocked: row.get(1)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(deps)
+    }
+}
+
+/// Whether `from` has to be done before `to`, directly or through other tasks
+fn blocks(client: &Connection, from: i32, to: i32) -> Result<bool> {
+    Ok(client.query_row(
+        r"
+        WITH RECURSIVE after(id) AS (
+            SELECT ?1
+            UNION
+            SELECT task_deps.blocked_id FROM task_deps
+            JOIN after ON task_deps.blocker_id = after.id
+        )
+        SELECT EXISTS (SELECT 1 FROM after WHERE id = ?2)
+        ",
+        [from, to],
+        |row| row.get(0),
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ListOptions, NewTask, TaskEdit};
+
+    fn get_test_conn() -> Conn {
+        let conn = Conn::build(":memory:").unwrap();
+        for info in ["design", "build", "ship", "party"] {
+            conn.add_task(&NewTask {
+                info: info.to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+        }
+        conn
+    }
+
+    fn finish(conn: &Conn, id: i32) -> Result<()> {
+        let edit = TaskEdit {
+            finish: Some(true),
+            ..Default::default()
+        };
+        conn.edit_task(id, &edit).map(|_| ())
+    }
+
+    fn ready(conn: &Conn) -> Vec<i32> {
+        let options = ListOptions {
+            ready: true,
+            ..Default::default()
+        };
+        conn.list_tasks(&options)
+            .unwrap()
+            .iter()
+            .map(|task| task.id)
+            .collect()
+    }
+
+    #[test]
+    fn test_blocked_state() {
+        let conn = get_test_conn();
+        conn.add_dependency(1, 2).unwrap();
+        conn.add_dependency(1, 3).unwrap();
+        conn.add_dependency(2, 3).unwrap();
+        // adding twice is a no-op
+        conn.add_dependency(2, 3).unwrap();
+        assert_eq!(conn.list_dependencies().unwrap().len(), 3);
+
+        assert_eq!(conn.get_task(3).unwrap().blocked_by, vec![1, 2]);
+        assert!(conn.get_task(1).unwrap().blocked_by.is_empty());
+        assert_eq!(ready(&conn), vec![1, 4]);
+
+        // finishing a prerequisite unblocks what waited on it
+        finish(&conn, 1).unwrap();
+        assert_eq!(conn.get_task(3).unwrap().blocked_by, vec![2]);
+        assert_eq!(ready(&conn), vec![2, 4]);
+
+        conn.remove_dependency(2, 3).unwrap();
+        assert_eq!(ready(&conn), vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn test_finish_blocked() {
+        let conn = get_test_conn();
+        conn.add_dependency(1, 2).unwrap();
+        assert!(matches!(
+            finish(&conn, 2),
+            Err(Error::Blocked { id: 2, by }) if by == vec![1]
+        ));
+        assert!(!conn.get_task(2).unwrap().done);
+
+        // a cascade cannot finish a subtask whose prerequisite stays open
+        conn.add_task(&NewTask {
+            info: "sub".to_string(),
+            parent: Some(4),
+            ..Default::default()
+        })
+        .unwrap();
+        conn.add_dependency(3, 5).unwrap();
+        let cascade = TaskEdit {
+            finish: Some(true),
+            cascade: true,
+            ..Default::default()
+        };
+        assert!(matches!(
+            conn.edit_task(4, &cascade),
+            Err(Error::Blocked { id: 5, .. })
+        ));
+        assert!(!conn.get_task(5).unwrap().done);
+    }
+
+    #[test]
+    fn test_cycles() {
+        let conn = get_test_conn();
+        conn.add_dependency(1, 2).unwrap();
+        conn.add_dependency(2, 3).unwrap();
+
+        assert!(matches!(
+            conn.add_dependency(3, 1),
+            Err(Error::DependencyCycle {
+                blocker: 3,
+                blocked: 1
+            })
+        ));
+        assert!(matches!(
+            conn.add_dependency(2, 2),
+            Err(Error::DependencyCycle { .. })
+        ));
+        assert!(matches!(
+            conn.add_dependency(1, 99),
+            Err(Error::TaskNotFound(99))
+        ));
+        // a second path in the same direction is fine
+        conn.add_dependency(1, 3).unwrap();
+    }
+
+    #[test]
+    fn test_removed_task_drops_dependencies() {
+        let conn = get_test_conn();
+        conn.add_dependency(1, 2).unwrap();
+        conn.remove_task(1).unwrap();
+        assert!(conn.list_dependencies().unwrap().is_empty());
+        assert!(conn.get_task(2).unwrap().blocked_by.is_empty());
+    }
+}
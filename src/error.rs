@@ -0,0 +1,283 @@
+use crate::{DateError, FilterError};
+use rusqlite::{Error::SqliteFailure, ffi};
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::path::PathBuf;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Everything that can go wrong while working with the todo list
+#[derive(Debug)]
+pub enum Error {
+    /// The database itself failed, e.g. it could not be opened or is corrupt
+    Storage(rusqlite::Error),
+    /// A file or directory around the database could not be read or created
+    Io(io::Error),
+    /// The config file exists but is not valid
+    Config {
+        path: PathBuf,
+        message: String,
+    },
+    EmptyTaskInfo,
+    EmptyCategoryName,
+    EmptyTagName,
+    DuplicateCategory,
+    /// A due date reached the database without being YYYY-MM-DD
+    InvalidDueDate,
+    /// A due date typed by the user could not be resolved
+    Date(DateError),
+    TaskNotFound(i32),
+    /// Finishing a task that waits on open prerequisites
+    Blocked {
+        id: i32,
+        by: Vec<i32>,
+    },
+    /// `blocker` already waits on `blocked`, directly or through other tasks
+    DependencyCycle {
+        blocker: i32,
+        blocked: i32,
+    },
+    /// Finishing a task whose subtasks are not all done, without cascading
+    OpenSubtasks {
+        id: i32,
+        open: u32,
+    },
+    CategoryNotFound(String),
+    /// A search query FTS5 could not parse, with its message
+    InvalidSearch(String),
+    /// A `--filter` expression that does not parse
+    InvalidFilter(FilterError),
+    /// No applied operation to undo, or the given one is already undone
+    NothingToUndo(Option<i64>),
+    /// No undone operation to redo, or the given one is still applied
+    NothingToRedo(Option<i64>),
+    OperationNotFound(i64),
+    /// Restoring a task that is not in the trash
+    NotInTrash(i32),
+    /// Restoring a subtask whose parent is still in the trash
+    ParentInTrash {
+        id: i32,
+        parent: i32,
+    },
+    /// Undoing or redoing `id` would overwrite what the `later` operations
+    /// changed since
+    UndoConflict {
+        id: i64,
+        later: Vec<i64>,
+    },
+    /// The database was written by a newer build with a schema we do not know
+    SchemaTooNew {
+        found: u32,
+        supported: u32,
+    },
+    /// A file given to `import` that is not an export, or refers to tasks
+    /// it does not contain
+    InvalidBackup(String),
+    /// An export written by a newer build in a format we do not know
+    BackupTooNew {
+        found: u32,
+        supported: u32,
+    },
+    /// Any other constraint the schema enforces, with SQLite's message
+    Constraint(String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Storage(err) => write!(f, "database error: {err}"),
+            Self::Io(err) => write!(f, "{err}"),
+            Self::Config { path, message } => {
+                write!(f, "invalid config {}: {message}", path.display())
+            }
+            Self::EmptyTaskInfo => write!(f, "task info cannot be empty"),
+            Self::EmptyCategoryName => write!(f, "category name cannot be empty"),
+            Self::EmptyTagName => write!(f, "tag name cannot be empty"),
+            Self::DuplicateCategory => write!(f, "category already exists"),
+            Self::InvalidDueDate => write!(f, "due date must be a valid YYYY-MM-DD date"),
+            Self::Date(err) => write!(f, "invalid due date: {err}"),
+            Self::TaskNotFound(id) => write!(f, "no task with id {id}"),
+            Self::Blocked { id, by } => {
+                let by: Vec<_> = by.iter().map(i32::to_string).collect();
+                write!(
+                    f,
+                    "task {id} is blocked by task {}, finish it first or remove the dependency",
+                    by.join(", ")
+                )
+            }
+            Self::DependencyCycle { blocker, blocked } => write!(
+                f,
+                "task {blocker} cannot block task {blocked}, it already waits on it"
+            ),
+            Self::OpenSubtasks { id, open } => write!(
+                f,
+                "task {id} has {open} open subtasks, finish them first or use --force"
+            ),
+            Self::CategoryNotFound(name) => write!(f, "no category named '{name}'"),
+            Self::InvalidSearch(msg) => write!(f, "invalid search: {msg}"),
+            Self::InvalidFilter(err) => write!(f, "invalid filter, {err}"),
+            Self::NothingToUndo(None) => write!(f, "nothing to undo"),
+            Self::NothingToUndo(Some(id)) => write!(f, "operation {id} is already undone"),
+            Self::NothingToRedo(None) => write!(f, "nothing to redo"),
+            Self::NothingToRedo(Some(id)) => write!(f, "operation {id} has not been undone"),
+            Self::OperationNotFound(id) => write!(f, "no operation with id {id}"),
+            Self::NotInTrash(id) => write!(f, "task {id} is not in the trash"),
+            Self::ParentInTrash { id, parent } => write!(
+                f,
+                "task {id} is a subtask of task {parent}, which is still in the trash"
+            ),
+            Self::UndoConflict { id, later } => {
+                let later: Vec<_> = later.iter().map(i64::to_string).collect();
+                write!(
+                    f,
+                    "operation {id} changed rows that operation {} changed since, undo those first or use --force",
+                    later.join(", ")
+                )
+            }
+            Self::SchemaTooNew { found, supported } => write!(
+                f,
+                "database schema version {found} is newer than the supported version {supported}, upgrade todo"
+            ),
+            Self::InvalidBackup(msg) => write!(f, "invalid backup: {msg}"),
+            Self::BackupTooNew { found, supported } => write!(
+                f,
+                "backup schema version {found} is newer than the supported version {supported}, upgrade todo"
+            ),
+            Self::Constraint(msg) => write!(f, "{msg}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Storage(err) => Some(err),
+            Self::Io(err) => Some(err),
+            Self::Date(err) => Some(err),
+            Self::InvalidFilter(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<DateError> for Error {
+    fn from(err: DateError) -> Self {
+        Self::Date(err)
+    }
+}
+
+impl From<FilterError> for Error {
+    fn from(err: FilterError) -> Self {
+        Self::InvalidFilter(err)
+    }
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from(err: rusqlite::Error) -> Self {
+        let SqliteFailure(failure, Some(msg)) = &err else {
+            return Self::Storage(err);
+        };
+        let column = |name: &str| msg.contains(name);
+        match failure.extended_code {
+            ffi::SQLITE_CONSTRAINT_CHECK if column("info") => Self::EmptyTaskInfo,
+            ffi::SQLITE_CONSTRAINT_CHECK if column("tag_name") => Self::EmptyTagName,
+            ffi::SQLITE_CONSTRAINT_CHECK if column("name") => Self::EmptyCategoryName,
+            ffi::SQLITE_CONSTRAINT_CHECK if column("due_date") => Self::InvalidDueDate,
+            ffi::SQLITE_CONSTRAINT_NOTNULL if column("tasks.info") => Self::EmptyTaskInfo,
+            ffi::SQLITE_CONSTRAINT_NOTNULL if column("categories.name") => Self::EmptyCategoryName,
+            ffi::SQLITE_CONSTRAINT_NOTNULL if column("tags.name") => Self::EmptyTagName,
+            ffi::SQLITE_CONSTRAINT_UNIQUE if column("categories.name") => Self::DuplicateCategory,
+            ffi::SQLITE_CONSTRAINT_CHECK
+            | ffi::SQLITE_CONSTRAINT_NOTNULL
+            | ffi::SQLITE_CONSTRAINT_UNIQUE
+            | ffi::SQLITE_CONSTRAINT_FOREIGNKEY => Self::Constraint(msg.clone()),
+            _ => Self::Storage(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::Connection;
+
+    fn failure(sql: &str) -> Error {
+        let client = Connection::open_in_memory().unwrap();
+        client
+            .execute_batch(
+                r"
+                CREATE TABLE categories (name TEXT NOT NULL UNIQUE CHECK(name != ''));
+                CREATE TABLE tags (
+                    name TEXT NOT NULL UNIQUE CONSTRAINT tag_name CHECK(name != '')
+                );
+                CREATE TABLE tasks (
+                    info TEXT NOT NULL CHECK(info != ''),
+                    done BOOLEAN NOT NULL DEFAULT false CHECK(done IN (0,1)),
+                    due_date TEXT CHECK(due_date IS NULL OR date(due_date) IS NOT NULL)
+                );
+                INSERT INTO categories (name) VALUES ('work');
+                ",
+            )
+            .unwrap();
+        client.execute(sql, ()).unwrap_err().into()
+    }
+
+    #[test]
+    fn test_from_constraint() {
+        assert!(matches!(
+            failure("INSERT INTO tasks (info) VALUES ('')"),
+            Error::EmptyTaskInfo
+        ));
+        assert!(matches!(
+            failure("INSERT INTO tasks (info) VALUES (NULL)"),
+            Error::EmptyTaskInfo
+        ));
+        assert!(matches!(
+            failure("INSERT INTO categories (name) VALUES ('')"),
+            Error::EmptyCategoryName
+        ));
+        assert!(matches!(
+            failure("INSERT INTO categories (name) VALUES (NULL)"),
+            Error::EmptyCategoryName
+        ));
+        assert!(matches!(
+            failure("INSERT INTO categories (name) VALUES ('work')"),
+            Error::DuplicateCategory
+        ));
+        assert!(matches!(
+            failure("INSERT INTO tags (name) VALUES ('')"),
+            Error::EmptyTagName
+        ));
+        assert!(matches!(
+            failure("INSERT INTO tags (name) VALUES (NULL)"),
+            Error::EmptyTagName
+        ));
+        assert!(matches!(
+            failure("INSERT INTO tasks (info, due_date) VALUES ('a', 'soon')"),
+            Error::InvalidDueDate
+        ));
+        assert!(matches!(
+            failure("INSERT INTO tasks (info, done) VALUES ('a', 2)"),
+            Error::Constraint(msg) if msg == "CHECK constraint failed: done IN (0,1)"
+        ));
+        assert!(matches!(
+            failure("SELECT * FROM missing"),
+            Error::Storage(_)
+        ));
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Error::TaskNotFound(3).to_string(), "no task with id 3");
+        assert_eq!(
+            Error::Date(DateError::Month(13)).to_string(),
+            "invalid due date: month 13 is not between 1 and 12"
+        );
+    }
+}
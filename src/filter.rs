@@ -0,0 +1,747 @@
+use crate::{DateOptions, HAS_TAG, Priority, Result, category, deps, format_date, search};
+use chrono::NaiveDate;
+use rusqlite::Connection;
+use rusqlite::types::Value;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// A parsed `--filter` expression like
+/// `due:<eow and (cat:work or tag:urgent) and not done`. Words next to each
+/// other without `and` or `or` between them must all match, `and` binds
+/// tighter than `or` and `not` tighter than both.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>),
+    /// `done`, `open` is its negation
+    Done,
+    /// `blocked`, waits on an open task
+    Blocked,
+    /// `repeating`
+    Repeating,
+    /// `due:<eow`, a `None` date matches tasks without one
+    Date {
+        field: DateField,
+        compare: Compare,
+        date: Option<String>,
+    },
+    /// `cat:work` matches `work` and everything below it, `cat:none` tasks
+    /// without a category
+    Category(Option<String>),
+    /// `tag:urgent`, `tag:none` for tasks without any tag
+    Tag(Option<String>),
+    /// `pri:high`
+    Priority(Priority),
+    /// `id:>10`
+    Id {
+        compare: Compare,
+        id: i32,
+    },
+    /// `parent:3`, `parent:none` for top level tasks
+    Parent(Option<i32>),
+    /// `text:kitchen`, a full text query on the info
+    Text(String),
+}
+
+/// Which date a `Filter::Date` compares
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DateField {
+    Due,
+    Created,
+    Modified,
+    Completed,
+}
+
+impl DateField {
+    /// The column as a local YYYY-MM-DD date
+    fn column(&self) -> &'static str {
+        match self {
+            Self::Due => "tasks.due_date",
+            Self::Created => "date(tasks.created_at, 'localtime')",
+            Self::Modified => "date(tasks.modified_at, 'localtime')",
+            Self::Completed => "date(tasks.completed_at, 'localtime')",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compare {
+    Eq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Compare {
+    fn operator(&self) -> &'static str {
+        match self {
+            Self::Eq => "=",
+            Self::Lt => "<",
+            Self::Le => "<=",
+            Self::Gt => ">",
+            Self::Ge => ">=",
+        }
+    }
+
+    /// Splits a leading `<`, `<=`, `>`, `>=` or `=` off `value`
+    fn split(value: &str) -> (Self, &str) {
+        for (prefix, compare) in [
+            ("<=", Self::Le),
+            (">=", Self::Ge),
+            ("<", Self::Lt),
+            (">", Self::Gt),
+            ("=", Self::Eq),
+        ] {
+            if let Some(rest) = value.strip_prefix(prefix) {
+                return (compare, rest);
+            }
+        }
+        (Self::Eq, value)
+    }
+}
+
+/// Why a filter could not be parsed, `column` counts characters from 1
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterError {
+    pub column: usize,
+    pub message: String,
+}
+
+impl FilterError {
+    fn new(column: usize, message: impl Into<String>) -> Self {
+        Self {
+            column,
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for FilterError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "column {}: {}", self.column, self.message)
+    }
+}
+
+impl std::error::Error for FilterError {}
+
+const FIELDS: &str = "due, created, modified, completed, cat, tag, pri, id, parent or text";
+const KEYWORDS: &str = "done, open, blocked, ready, overdue or repeating";
+
+impl Filter {
+    /// Parses `text`, resolving dates like `eow` against `today`
+    pub fn parse(text: &str, today: &NaiveDate, dates: &DateOptions) -> Result<Self, FilterError> {
+        let mut parser = Parser {
+            tokens: lex(text)?,
+            pos: 0,
+            end: text.chars().count() + 1,
+            today,
+            dates,
+        };
+        let filter = parser.or()?;
+        match parser.tokens.get(parser.pos) {
+            None => Ok(filter),
+            Some((column, Token::Close)) => Err(FilterError::new(*column, "unexpected ')'")),
+            Some((column, _)) => Err(FilterError::new(*column, "expected 'and' or 'or'")),
+        }
+    }
+
+    /// Whether the filter says anything about tasks being done, `list` only
+    /// hides finished tasks by default when it does not
+    pub fn mentions_done(&self) -> bool {
+        match self {
+            Self::And(left, right) | Self::Or(left, right) => {
+                left.mentions_done() || right.mentions_done()
+            }
+            Self::Not(inner) => inner.mentions_done(),
+            Self::Done => true,
+            Self::Date { field, .. } => *field == DateField::Completed,
+            _ => false,
+        }
+    }
+
+    /// Fails with `InvalidSearch` unless FTS5 understands every `text:` query
+    pub(crate) fn check(&self, client: &Connection) -> Result<()> {
+        match self {
+            Self::And(left, right) | Self::Or(left, right) => {
+                left.check(client)?;
+                right.check(client)
+            }
+            Self::Not(inner) => inner.check(client),
+            Self::Text(query) => search::check_query(client, query),
+            _ => Ok(()),
+        }
+    }
+
+    /// The filter as an SQL condition on `tasks` joined with `categories`,
+    /// pushing a value for each of its plain `?` parameters in order
+    pub(crate) fn to_sql(&self, params: &mut Vec<Value>) -> String {
+        match self {
+            Self::And(left, right) => {
+                format!("({} AND {})", left.to_sql(params), right.to_sql(params))
+            }
+            Self::Or(left, right) => {
+                format!("({} OR {})", left.to_sql(params), right.to_sql(params))
+            }
+            Self::Not(inner) => format!("NOT ({})", inner.to_sql(params)),
+            Self::Done => "tasks.done".to_string(),
+            Self::Blocked => deps::BLOCKED.to_string(),
+            Self::Repeating => "tasks.recurrence IS NOT NULL".to_string(),
+            Self::Date {
+                field,
+                compare,
+                date,
+            } => {
+                let column = field.column();
+                match date {
+                    None => format!("{column} IS NULL"),
+                    Some(date) => {
+                        params.push(date.clone().into());
+                        format!(
+                            "({column} IS NOT NULL AND {column} {} ?)",
+                            compare.operator()
+                        )
+                    }
+                }
+            }
+            Self::Category(None) => "tasks.category IS NULL".to_string(),
+            Self::Category(Some(path)) => {
+                params.extend([path, path, path].map(|path| Value::from(path.clone())));
+                format!("(tasks.category IS NOT NULL AND {})", category::IN_SUBTREE)
+            }
+            Self::Tag(None) => {
+                "NOT EXISTS (SELECT 1 FROM task_tags WHERE task_tags.task_id = tasks.id)"
+                    .to_string()
+            }
+            Self::Tag(Some(name)) => {
+                params.push(name.clone().into());
+                format!("{HAS_TAG} (?))")
+            }
+            Self::Priority(priority) => {
+                params.push((*priority as i64).into());
+                "tasks.priority = ?".to_string()
+            }
+            Self::Id { compare, id } => {
+                params.push((*id).into());
+                format!("tasks.id {} ?", compare.operator())
+            }
+            Self::Parent(parent) => {
+                params.push(parent.map_or(Value::Null, Value::from));
+                "tasks.parent_id IS ?".to_string()
+            }
+            Self::Text(query) => {
+                params.push(query.clone().into());
+                search::MATCHES.to_string()
+            }
+        }
+    }
+}
+
+/// A task id or an inclusive range of them like `3-7`, as typed on the
+/// command line
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IdRange {
+    pub first: i32,
+    pub last: i32,
+}
+
+impl IdRange {
+    /// Matches every task in the range
+    pub fn filter(&self) -> Filter {
+        let id = |compare, id| Filter::Id { compare, id };
+        Filter::And(
+            Box::new(id(Compare::Ge, self.first)),
+            Box::new(id(Compare::Le, self.last)),
+        )
+    }
+}
+
+impl FromStr for IdRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (first, last) = s.split_once('-').unwrap_or((s, s));
+        let (Ok(first), Ok(last)) = (first.trim().parse(), last.trim().parse()) else {
+            return Err(format!("'{s}' is not a task id or a range like 3-7"));
+        };
+        if first > last {
+            return Err(format!("'{s}' ends before it starts, use {last}-{first}"));
+        }
+        Ok(Self { first, last })
+    }
+}
+
+/// `task 3` or `tasks 3, 4`, for messages and journal descriptions
+pub fn describe_tasks(ids: &[i32]) -> String {
+    let ids: Vec<_> = ids.iter().map(i32::to_string).collect();
+    match ids.as_slice() {
+        [id] => format!("task {id}"),
+        _ => format!("tasks {}", ids.join(", ")),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    /// Text between spaces and parentheses, without its quotes
+    Word {
+        text: String,
+        quoted: bool,
+    },
+}
+
+/// Splits a filter into parentheses and words, each with its column.
+/// Double quotes keep spaces and parentheses inside a word.
+fn lex(text: &str) -> Result<Vec<(usize, Token)>, FilterError> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().enumerate().peekable();
+    while let Some(&(i, c)) = chars.peek() {
+        let column = i + 1;
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' => {
+                chars.next();
+                tokens.push((column, if c == '(' { Token::Open } else { Token::Close }));
+            }
+            _ => {
+                let mut word = String::new();
+                let mut quoted = false;
+                let mut open_quote = None;
+                while let Some(&(j, c)) = chars.peek() {
+                    match (c, open_quote) {
+                        ('"', None) => {
+                            open_quote = Some(j + 1);
+                            quoted = true;
+                        }
+                        ('"', Some(_)) => open_quote = None,
+                        (c, None) if c.is_whitespace() || c == '(' || c == ')' => break,
+                        (c, _) => word.push(c),
+                    }
+                    chars.next();
+                }
+                if let Some(quote) = open_quote {
+                    return Err(FilterError::new(quote, "this quote is never closed"));
+                }
+                tokens.push((column, Token::Word { text: word, quoted }));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    /// Column just past the end of the text
+    end: usize,
+    today: &'a NaiveDate,
+    dates: &'a DateOptions,
+}
+
+impl Parser<'_> {
+    /// The next word if it is `keyword`, in any case
+    fn keyword(&self, keyword: &str) -> bool {
+        matches!(
+            self.tokens.get(self.pos),
+            Some((_, Token::Word { text, quoted: false })) if text.eq_ignore_ascii_case(keyword)
+        )
+    }
+
+    fn eat(&mut self, keyword: &str) -> bool {
+        let found = self.keyword(keyword);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn or(&mut self) -> Result<Filter, FilterError> {
+        let mut filter = self.and()?;
+        while self.eat("or") {
+            filter = Filter::Or(Box::new(filter), Box::new(self.and()?));
+        }
+        Ok(filter)
+    }
+
+    fn and(&mut self) -> Result<Filter, FilterError> {
+        let mut filter = self.not()?;
+        loop {
+            let end = matches!(self.tokens.get(self.pos), Some((_, Token::Close)) | None);
+            if end || self.keyword("or") {
+                break;
+            }
+            // words next to each other must all match
+            self.eat("and");
+            let next = self.not()?;
+            filter = Filter::And(Box::new(filter), Box::new(next));
+        }
+        Ok(filter)
+    }
+
+    fn not(&mut self) -> Result<Filter, FilterError> {
+        if self.eat("not") {
+            return Ok(Filter::Not(Box::new(self.not()?)));
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Filter, FilterError> {
+        let Some((column, token)) = self.tokens.get(self.pos) else {
+            return Err(FilterError::new(self.end, "expected a filter"));
+        };
+        let column = *column;
+        match token {
+            Token::Open => {
+                self.pos += 1;
+                let filter = self.or()?;
+                match self.tokens.get(self.pos) {
+                    Some((_, Token::Close)) => {
+                        self.pos += 1;
+                        Ok(filter)
+                    }
+                    _ => Err(FilterError::new(column, "this parenthesis is never closed")),
+                }
+            }
+            Token::Close => Err(FilterError::new(column, "expected a filter before ')'")),
+            Token::Word {
+                text,
+                quoted: false,
+            } if ["and", "or"].contains(&text.to_lowercase().as_str()) => Err(FilterError::new(
+                column,
+                format!("expected a filter before '{text}'"),
+            )),
+            Token::Word { text, quoted } => {
+                let filter = self.term(column, text, *quoted)?;
+                self.pos += 1;
+                Ok(filter)
+            }
+        }
+    }
+
+    /// A `field:value` pair or a keyword like `done`
+    fn term(&self, column: usize, text: &str, quoted: bool) -> Result<Filter, FilterError> {
+        let Some((field, value)) = text.split_once(':') else {
+            let not = |filter| Filter::Not(Box::new(filter));
+            let and = |left, right| Filter::And(Box::new(left), Box::new(right));
+            return match text.to_lowercase().as_str() {
+                "done" => Ok(Filter::Done),
+                "open" => Ok(not(Filter::Done)),
+                "blocked" => Ok(Filter::Blocked),
+                "ready" => Ok(and(not(Filter::Done), not(Filter::Blocked))),
+                "overdue" => Ok(and(
+                    not(Filter::Done),
+                    Filter::Date {
+                        field: DateField::Due,
+                        compare: Compare::Lt,
+                        date: Some(self.today.format("%Y-%m-%d").to_string()),
+                    },
+                )),
+                "repeating" => Ok(Filter::Repeating),
+                _ => Err(FilterError::new(
+                    column,
+                    format!(
+                        "unknown filter '{text}', use field:value with {FIELDS}, or {KEYWORDS}"
+                    ),
+                )),
+            };
+        };
+
+        // columns count chars of the text as typed, lowercasing can change
+        // its length
+        let typed = field;
+        let field = field.to_lowercase();
+        let (compare, value) = match field.as_str() {
+            "due" | "created" | "modified" | "completed" | "id" => Compare::split(value),
+            _ => (Compare::Eq, value),
+        };
+        let value_column = column + text.chars().count() - value.chars().count();
+        let error = |message: String| Err(FilterError::new(value_column, message));
+        if value.is_empty() {
+            return error(format!("expected a value after '{typed}:'"));
+        }
+        let none = !quoted && value.eq_ignore_ascii_case("none");
+
+        match field.as_str() {
+            "due" | "created" | "modified" | "completed" => {
+                let field = match field.as_str() {
+                    "due" => DateField::Due,
+                    "created" => DateField::Created,
+                    "modified" => DateField::Modified,
+                    _ => DateField::Completed,
+                };
+                let date = match none {
+                    true if compare == Compare::Eq => None,
+                    true => return error("'none' cannot be compared".to_string()),
+                    false => match format_date(value, self.today, self.dates) {
+                        Ok(date) => Some(date),
+                        Err(err) => return error(err.to_string()),
+                    },
+                };
+                Ok(Filter::Date {
+                    field,
+                    compare,
+                    date,
+                })
+            }
+            "cat" | "category" if none => Ok(Filter::Category(None)),
+            "cat" | "category" => match category::normalize(value) {
+                Ok(path) => Ok(Filter::Category(Some(path))),
+                Err(err) => error(err.to_string()),
+            },
+            "tag" if none => Ok(Filter::Tag(None)),
+            "tag" => Ok(Filter::Tag(Some(value.to_string()))),
+            "pri" | "priority" => match value.parse() {
+                Ok(priority) => Ok(Filter::Priority(priority)),
+                Err(err) => error(err),
+            },
+            "id" => match value.parse() {
+                Ok(id) => Ok(Filter::Id { compare, id }),
+                Err(_) => error(format!("'{value}' is not a task id")),
+            },
+            "parent" if none => Ok(Filter::Parent(None)),
+            "parent" => match value.parse() {
+                Ok(id) => Ok(Filter::Parent(Some(id))),
+                Err(_) => error(format!("'{value}' is not a task id")),
+            },
+            // a quoted value is one phrase, anything else full text syntax
+            "text" if quoted => Ok(Filter::Text(format!("\"{}\"", value.replace('"', "\"\"")))),
+            "text" => Ok(Filter::Text(value.to_string())),
+            _ => Err(FilterError::new(
+                column,
+                format!("unknown field '{typed}', use {FIELDS}"),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Conn, ListOptions, NewTask, TaskEdit};
+
+    fn today() -> NaiveDate {
+        // a Wednesday
+        NaiveDate::from_ymd_opt(2025, 10, 1).unwrap()
+    }
+
+    fn parse(text: &str) -> Result<Filter, FilterError> {
+        Filter::parse(text, &today(), &DateOptions::default())
+    }
+
+    fn error(text: &str) -> (usize, String) {
+        let err = parse(text).unwrap_err();
+        (err.column, err.message)
+    }
+
+    fn and(left: Filter, right: Filter) -> Filter {
+        Filter::And(Box::new(left), Box::new(right))
+    }
+
+    fn or(left: Filter, right: Filter) -> Filter {
+        Filter::Or(Box::new(left), Box::new(right))
+    }
+
+    fn not(inner: Filter) -> Filter {
+        Filter::Not(Box::new(inner))
+    }
+
+    fn tag(name: &str) -> Filter {
+        Filter::Tag(Some(name.to_string()))
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("due:<eow and (cat:work or tag:urgent) and not done"),
+            Ok(and(
+                and(
+                    Filter::Date {
+                        field: DateField::Due,
+                        compare: Compare::Lt,
+                        date: Some("2025-10-05".to_string()),
+                    },
+                    or(Filter::Category(Some("work".to_string())), tag("urgent")),
+                ),
+                not(Filter::Done),
+            ))
+        );
+        // and binds tighter than or, words side by side are and
+        assert_eq!(
+            parse("tag:a OR tag:b tag:c"),
+            Ok(or(tag("a"), and(tag("b"), tag("c"))))
+        );
+        assert_eq!(parse("not not done"), Ok(not(not(Filter::Done))));
+        assert_eq!(
+            parse("cat:none tag:none parent:none due:none"),
+            Ok(and(
+                and(
+                    and(Filter::Category(None), Filter::Tag(None)),
+                    Filter::Parent(None)
+                ),
+                Filter::Date {
+                    field: DateField::Due,
+                    compare: Compare::Eq,
+                    date: None
+                }
+            ))
+        );
+        assert_eq!(
+            parse("id:>=3 pri:h completed:2025-09-30"),
+            Ok(and(
+                and(
+                    Filter::Id {
+                        compare: Compare::Ge,
+                        id: 3
+                    },
+                    Filter::Priority(Priority::High)
+                ),
+                Filter::Date {
+                    field: DateField::Completed,
+                    compare: Compare::Eq,
+                    date: Some("2025-09-30".to_string()),
+                }
+            ))
+        );
+        assert_eq!(
+            parse(r#"text:"kitchen sink" cat:"home office""#),
+            Ok(and(
+                Filter::Text("\"kitchen sink\"".to_string()),
+                Filter::Category(Some("home office".to_string()))
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(error("due:<eow and"), (13, "expected a filter".to_string()));
+        assert_eq!(
+            error("(cat:work or tag:x"),
+            (1, "this parenthesis is never closed".to_string())
+        );
+        assert_eq!(error("done)"), (5, "unexpected ')'".to_string()));
+        assert_eq!(
+            error("done and or open"),
+            (10, "expected a filter before 'or'".to_string())
+        );
+        assert_eq!(
+            error("done and colour:red"),
+            (10, format!("unknown field 'colour', use {FIELDS}"))
+        );
+        assert_eq!(
+            error("open and due:<soon"),
+            (15, "'soon' is not a valid day".to_string())
+        );
+        assert_eq!(
+            error("pri:"),
+            (5, "expected a value after 'pri:'".to_string())
+        );
+        assert_eq!(
+            error("tag:x \"open"),
+            (7, "this quote is never closed".to_string())
+        );
+        assert_eq!(
+            error("due:<none"),
+            (6, "'none' cannot be compared".to_string())
+        );
+        assert_eq!(
+            error("open and İ:x"),
+            (10, format!("unknown field 'İ', use {FIELDS}"))
+        );
+        assert_eq!(
+            error("tag:é and due:<soon"),
+            (16, "'soon' is not a valid day".to_string())
+        );
+        assert!(error("finished").1.starts_with("unknown filter 'finished'"));
+        assert_eq!(error(""), (1, "expected a filter".to_string()));
+    }
+
+    #[test]
+    fn test_id_range() {
+        assert_eq!("4".parse(), Ok(IdRange { first: 4, last: 4 }));
+        assert_eq!("3-7".parse(), Ok(IdRange { first: 3, last: 7 }));
+        assert!("7-3".parse::<IdRange>().is_err());
+        assert!("3-".parse::<IdRange>().is_err());
+        assert!("a".parse::<IdRange>().is_err());
+    }
+
+    #[test]
+    fn test_sql_is_parameterized() {
+        let mut params = Vec::new();
+        let sql = parse("tag:\"x' OR 1=1 --\" or id:<3")
+            .unwrap()
+            .to_sql(&mut params);
+        assert!(!sql.contains("OR 1=1"));
+        assert_eq!(
+            params,
+            vec![Value::from("x' OR 1=1 --".to_string()), Value::from(3)]
+        );
+    }
+
+    #[test]
+    fn test_list_with_filter() {
+        let conn = Conn::build(":memory:").unwrap();
+        let add = |info: &str, category: Option<&str>, due_date: Option<&str>, tags: &[&str]| {
+            conn.add_task(&NewTask {
+                info: info.to_string(),
+                category: category.map(str::to_string),
+                due_date: due_date.map(str::to_string),
+                tags: tags.iter().map(|tag| tag.to_string()).collect(),
+                ..Default::default()
+            })
+            .unwrap()
+        };
+        add("report", Some("work"), Some("2025-10-03"), &[]);
+        add("invoice", Some("work/billing"), Some("2025-10-20"), &[]);
+        add("dentist", Some("home"), Some("2025-10-02"), &["urgent"]);
+        add("groceries", Some("home"), None, &[]);
+        add("old", Some("work"), Some("2025-09-01"), &[]);
+        conn.edit_task(
+            5,
+            &TaskEdit {
+                finish: Some(true),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let ids = |text: &str| {
+            let options = ListOptions {
+                filter: Some(parse(text).unwrap()),
+                ..Default::default()
+            };
+            let mut ids: Vec<_> = conn
+                .list_tasks(&options)
+                .unwrap()
+                .iter()
+                .map(|task| task.id)
+                .collect();
+            ids.sort();
+            ids
+        };
+
+        assert_eq!(
+            ids("due:<eow and (cat:work or tag:urgent) and not done"),
+            vec![1, 3]
+        );
+        // done tasks are hidden unless the filter talks about them
+        assert_eq!(ids("cat:work"), vec![1, 2]);
+        assert_eq!(ids("cat:work done"), vec![5]);
+        assert_eq!(ids("cat:work (done or open)"), vec![1, 2, 5]);
+        // tasks without a due date are not before or after anything
+        assert_eq!(ids("not due:<eow"), vec![2, 4]);
+        assert_eq!(ids("due:none or tag:none cat:home"), vec![4]);
+        assert_eq!(ids("id:>2 id:<=4"), vec![3, 4]);
+        assert_eq!(
+            conn.count_tasks(&ListOptions {
+                filter: Some(parse("cat:home").unwrap()),
+                ..Default::default()
+            })
+            .unwrap(),
+            2
+        );
+    }
+}
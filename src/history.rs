@@ -0,0 +1,677 @@
+use crate::{Conn, Error, Result, journal};
+use rusqlite::{Connection, Row};
+use serde::Serialize;
+use std::env;
+use std::fmt::{self, Display, Formatter};
+
+/// What happened to a row
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Insert,
+    Update,
+    Delete,
+}
+
+impl Action {
+    fn parse(text: &str) -> Self {
+        match text {
+            "insert" => Self::Insert,
+            "delete" => Self::Delete,
+            _ => Self::Update,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Insert => "insert",
+            Self::Update => "update",
+            Self::Delete => "delete",
+        }
+    }
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Insert => write!(f, "added"),
+            Self::Update => write!(f, "changed"),
+            Self::Delete => write!(f, "deleted"),
+        }
+    }
+}
+
+/// One field of a row before and after a change, `None` where it was empty
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// A change to a task or a category as kept in `task_history`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HistoryEntry {
+    pub id: i64,
+    /// `tasks` or `categories`
+    pub table: String,
+    pub row_id: i64,
+    pub action: Action,
+    /// Local `YYYY-MM-DD HH:MM:SS` time
+    pub at: String,
+    /// Login of whoever made the change, if it was known
+    pub user: Option<String>,
+    /// Every field that changed, a task's category by its path at the time
+    pub changes: Vec<FieldChange>,
+}
+
+impl Display for HistoryEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let row = match self.table.as_str() {
+            "tasks" => "task",
+            _ => "category",
+        };
+        write!(
+            f,
+            "{}  {}  {} {row} {}",
+            self.at,
+            self.user.as_deref().unwrap_or("unknown"),
+            self.action,
+            self.row_id
+        )
+    }
+}
+
+const ENTRY_SELECT: &str = r"
+    SELECT id, row_table, row_id, action, datetime(at, 'localtime'), user FROM task_history
+    ";
+
+/// Fields that changed between the images `?1` and `?2`, as `{field: [old,
+/// new]}`. Empty fields count as missing, so an added row lists only what
+/// it was given.
+const CHANGES: &str = r"
+    SELECT json_group_object(key, json_array(json(?1 -> fullkey), json(?2 -> fullkey)))
+    FROM json_each(coalesce(?2, ?1))
+    WHERE coalesce(?1 -> fullkey, 'null') IS NOT coalesce(?2 -> fullkey, 'null')
+    ";
+
+impl HistoryEntry {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get(0)?,
+            table: row.get(1)?,
+            row_id: row.get(2)?,
+            action: Action::parse(&row.get::<_, String>(3)?),
+            at: row.get(4)?,
+            user: row.get(5)?,
+            changes: Vec::new(),
+        })
+    }
+}
+
+impl Conn {
+    /// Every change to task `id`, oldest first, including ones from after
+    /// it was deleted
+    pub fn task_history(&self, id: i32) -> Result<Vec<HistoryEntry>> {
+        let entries = self.history_entries(
+            &format!("{ENTRY_SELECT} WHERE row_table = 'tasks' AND row_id = ?1 ORDER BY id"),
+            id,
+        )?;
+        match entries.is_empty() {
+            true => Err(Error::TaskNotFound(id)),
+            false => Ok(entries),
+        }
+    }
+
+    /// Every change to any task or category in the last `days` days, oldest
+    /// first
+    pub fn history_since(&self, days: u32) -> Result<Vec<HistoryEntry>> {
+        self.history_entries(
+            &format!(
+                r"
+                {ENTRY_SELECT}
+                WHERE at >= strftime('%Y-%m-%d %H:%M:%S', 'now', '-' || ?1 || ' days')
+                ORDER BY id
+                "
+            ),
+            days,
+        )
+    }
+
+    fn history_entries(&self, sql: &str, param: impl rusqlite::ToSql) -> Result<Vec<HistoryEntry>> {
+        let mut entries = self
+            .client
+            .prepare(sql)?
+            .query_map([param], HistoryEntry::from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let mut changes = self.client.prepare(
+            r"
+            SELECT key,
+                CASE WHEN history.row_table = 'tasks' AND key = 'category'
+                    THEN (
+                        SELECT category.image ->> '$.name' FROM task_history AS category
+                        WHERE category.row_table = 'categories'
+                            AND category.row_id = value ->> '$[0]' AND category.id < history.id
+                            AND category.image IS NOT NULL
+                        ORDER BY category.id DESC LIMIT 1
+                    )
+                    ELSE CAST(value ->> '$[0]' AS TEXT)
+                END,
+                CASE WHEN history.row_table = 'tasks' AND key = 'category'
+                    THEN (
+                        SELECT category.image ->> '$.name' FROM task_history AS category
+                        WHERE category.row_table = 'categories'
+                            AND category.row_id = value ->> '$[1]' AND category.id < history.id
+                            AND category.image IS NOT NULL
+                        ORDER BY category.id DESC LIMIT 1
+                    )
+                    ELSE CAST(value ->> '$[1]' AS TEXT)
+                END
+            FROM task_history AS history, json_each(history.changes)
+            WHERE history.id = ?1
+            ",
+        )?;
+        for entry in &mut entries {
+            entry.changes = changes
+                .query_map([entry.id], |row| {
+                    Ok(FieldChange {
+                        field: row.get(0)?,
+                        old: row.get(1)?,
+                        new: row.get(2)?,
+                    })
+                })?
+                .collect::<rusqlite::Result<_>>()?;
+        }
+        Ok(entries)
+    }
+
+    /// The list as it stood at the end of `date` (YYYY-MM-DD), rebuilt from
+    /// the history into an in-memory database. Tasks deleted or finished
+    /// since are back the way they were, and anything that reads a `Conn`,
+    /// like `list_tasks` with its sorting and filters, works on it as usual.
+    pub fn as_of(&self, date: &str) -> Result<Conn> {
+        let images = |table: &str| -> Result<Vec<String>> {
+            let images = self
+                .client
+                .prepare(
+                    r"
+                    SELECT image FROM task_history
+                    WHERE id IN (
+                        SELECT max(id) FROM task_history
+                        WHERE row_table = ?1 AND datetime(at, 'localtime') < date(?2, '+1 day')
+                        GROUP BY row_id
+                    )
+                    AND image IS NOT NULL
+                    ",
+                )?
+                .query_map([table, date], |row| row.get(0))?
+                .collect::<rusqlite::Result<_>>()?;
+            Ok(images)
+        };
+        let categories = images("categories")?;
+        let tasks = images("tasks")?;
+
+        let past = Conn::build(":memory:")?;
+        let tx = past.client.unchecked_transaction()?;
+        tx.pragma_update(None, "defer_foreign_keys", true)?;
+        // tags go in before their tasks, so adding them does not touch
+        // modified_at
+        for image in &tasks {
+            tx.execute(
+                "INSERT OR IGNORE INTO tags (name) SELECT value FROM json_each(?1, '$.tags')",
+                [image],
+            )?;
+            tx.execute(
+                r"
+                INSERT INTO task_tags (task_id, tag_id)
+                SELECT ?1 ->> '$.id', tags.id FROM json_each(?1, '$.tags')
+                JOIN tags ON tags.name = value
+                ",
+                [image],
+            )?;
+        }
+        for (table, images) in [("categories", &categories), ("tasks", &tasks)] {
+            let columns = journal::columns(&tx, table)?;
+            let values: Vec<_> = columns
+                .iter()
+                .map(|column| format!("?1 ->> '$.{column}'"))
+                .collect();
+            let insert = format!(
+                "INSERT INTO {table} ({}) VALUES ({})",
+                columns.join(", "),
+                values.join(", ")
+            );
+            for image in images {
+                tx.execute(&insert, [image])?;
+            }
+        }
+        for image in &tasks {
+            tx.execute(
+                r"
+                INSERT INTO task_deps (blocker_id, blocked_id)
+                SELECT value, ?1 ->> '$.id' FROM json_each(?1, '$.blockers')
+                WHERE value IN (SELECT id FROM tasks)
+                ",
+                [image],
+            )?;
+        }
+        // references to rows the history does not know, only possible for
+        // changes made outside todo
+        tx.execute_batch(
+            r"
+            UPDATE tasks SET parent_id = NULL WHERE parent_id NOT IN (SELECT id FROM tasks);
+            UPDATE tasks SET repeat_of = NULL WHERE repeat_of NOT IN (SELECT id FROM tasks);
+            UPDATE tasks SET category = NULL WHERE category NOT IN (SELECT id FROM categories);
+            UPDATE categories SET parent_id = NULL
+            WHERE parent_id NOT IN (SELECT id FROM categories);
+            ",
+        )?;
+        tx.commit()?;
+        Ok(past)
+    }
+}
+
+/// Adds a history entry for every task and category operation `id` of the
+/// journal touched whose row differs from its last recorded image
+pub(crate) fn record(client: &Connection, id: i64) -> Result<()> {
+    let rows = client
+        .prepare(
+            r"
+            SELECT row_table, row_key ->> '$[0]' FROM journal_rows
+            WHERE operation_id = ?1 AND row_table IN ('categories', 'tasks')
+            UNION
+            SELECT 'tasks', row_key ->> '$[0]' FROM journal_rows
+            WHERE operation_id = ?1 AND row_table = 'task_tags'
+            UNION
+            SELECT 'tasks', row_key ->> '$[1]' FROM journal_rows
+            WHERE operation_id = ?1 AND row_table = 'task_deps'
+            ORDER BY 1, 2
+            ",
+        )?
+        .query_map([id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<Vec<(String, i64)>>>()?;
+
+    let user = os_user();
+    for (table, row_id) in rows {
+        let old: Option<String> = client.query_row(
+            r"
+            SELECT (
+                SELECT image FROM task_history WHERE row_table = ?1 AND row_id = ?2
+                ORDER BY id DESC LIMIT 1
+            )
+            ",
+            rusqlite::params![table, row_id],
+            |row| row.get(0),
+        )?;
+        let new: Option<String> = client.query_row(
+            &format!(
+                "SELECT (SELECT {} FROM {table} WHERE id = ?1)",
+                image(client, &table)?
+            ),
+            [row_id],
+            |row| row.get(0),
+        )?;
+        let changes: String =
+            client.query_row(CHANGES, rusqlite::params![old, new], |row| row.get(0))?;
+        if changes == "{}" {
+            continue;
+        }
+
+        let action = match (&old, &new) {
+            (None, _) => Action::Insert,
+            (_, None) => Action::Delete,
+            _ => Action::Update,
+        };
+        client.execute(
+            r"
+            INSERT INTO task_history (row_table, row_id, action, changes, image, user)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            ",
+            rusqlite::params![table, row_id, action.as_str(), changes, new, user],
+        )?;
+    }
+    Ok(())
+}
+
+/// A row of `table` as a JSON object, tasks with their tag names and the
+/// ids of the tasks they wait on
+pub(crate) fn image(client: &Connection, table: &str) -> Result<String> {
+    let mut pairs: Vec<_> = journal::columns(client, table)?
+        .iter()
+        .map(|column| format!("'{column}', {table}.{column}"))
+        .collect();
+    if table == "tasks" {
+        pairs.push(
+            r"
+            'tags', json((
+                SELECT json_group_array(name) FROM (
+                    SELECT tags.name FROM task_tags
+                    JOIN tags ON tags.id = task_tags.tag_id
+                    WHERE task_tags.task_id = tasks.id
+                    ORDER BY tags.name
+                )
+            )),
+            'blockers', json((
+                SELECT json_group_array(blocker_id) FROM (
+                    SELECT blocker_id FROM task_deps
+                    WHERE blocked_id = tasks.id
+                    ORDER BY blocker_id
+                )
+            ))"
+            .to_string(),
+        );
+    }
+    Ok(format!("json_object({})", pairs.join(", ")))
+}
+
+/// Login name of the user running todo
+fn os_user() -> Option<String> {
+    ["USER", "USERNAME", "LOGNAME"]
+        .iter()
+        .find_map(|name| env::var(name).ok().filter(|user| !user.is_empty()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Filter, ListOptions, NewTask, Priority, TaskEdit};
+
+    fn get_test_conn() -> Conn {
+        Conn::build(":memory:").unwrap()
+    }
+
+    fn add(conn: &Conn, info: &str) -> i32 {
+        conn.add_task(&NewTask {
+            info: info.to_string(),
+            category: Some("work".to_string()),
+            tags: vec!["b".to_string(), "a".to_string()],
+            ..Default::default()
+        })
+        .unwrap()
+        .id
+    }
+
+    /// (action, changed fields other than timestamps) for every entry
+    fn timeline(entries: &[HistoryEntry]) -> Vec<(Action, Vec<String>)> {
+        entries
+            .iter()
+            .map(|entry| {
+                let fields = entry
+                    .changes
+                    .iter()
+                    .filter(|change| !change.field.ends_with("_at"))
+                    .map(|change| {
+                        let show = |value: &Option<String>| {
+                            value.clone().unwrap_or_else(|| "-".to_string())
+                        };
+                        format!(
+                            "{} {} {}",
+                            change.field,
+                            show(&change.old),
+                            show(&change.new)
+                        )
+                    })
+                    .collect();
+                (entry.action, fields)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_task_history() {
+        let conn = get_test_conn();
+        let id = add(&conn, "write report");
+        let other = add(&conn, "gather numbers");
+        conn.edit_task(
+            id,
+            &TaskEdit {
+                due_date: Some("2026-10-20".to_string()),
+                untag: vec!["b".to_string()],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        conn.add_dependency(other, id).unwrap();
+        conn.remove_task(id).unwrap();
+        conn.empty_trash(0).unwrap();
+
+        let entries = conn.task_history(id).unwrap();
+        assert_eq!(
+            timeline(&entries),
+            vec![
+                (
+                    Action::Insert,
+                    vec![
+                        "id - 1".to_string(),
+                        "info - write report".to_string(),
+                        "done - 0".to_string(),
+                        "category - work".to_string(),
+                        "priority - 2".to_string(),
+                        "repeat_from_completion - 0".to_string(),
+                        r#"tags - ["a","b"]"#.to_string(),
+                        "blockers - []".to_string(),
+                    ]
+                ),
+                (
+                    Action::Update,
+                    vec![
+                        "due_date - 2026-10-20".to_string(),
+                        r#"tags ["a","b"] ["a"]"#.to_string(),
+                    ]
+                ),
+                (Action::Update, vec!["blockers [] [2]".to_string()]),
+                (Action::Update, vec![]),
+                (
+                    Action::Delete,
+                    vec![
+                        "id 1 -".to_string(),
+                        "info write report -".to_string(),
+                        "done 0 -".to_string(),
+                        "due_date 2026-10-20 -".to_string(),
+                        "category work -".to_string(),
+                        "priority 2 -".to_string(),
+                        "repeat_from_completion 0 -".to_string(),
+                        r#"tags ["a"] -"#.to_string(),
+                        "blockers [2] -".to_string(),
+                    ]
+                ),
+            ]
+        );
+        // moving to the trash shows as setting deleted_at
+        assert_eq!(entries[3].changes[0].field, "deleted_at");
+        assert!(entries.iter().all(|entry| entry.user == os_user()));
+        assert!(matches!(
+            conn.task_history(99),
+            Err(Error::TaskNotFound(99))
+        ));
+    }
+
+    #[test]
+    fn test_undo_is_recorded() {
+        let conn = get_test_conn();
+        let id = add(&conn, "task");
+        conn.edit_task(
+            id,
+            &TaskEdit {
+                info: Some("renamed".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        conn.undo(None, false).unwrap();
+        conn.redo(None, false).unwrap();
+
+        let infos: Vec<_> = conn
+            .task_history(id)
+            .unwrap()
+            .iter()
+            .skip(1)
+            .map(|entry| {
+                let change = &entry.changes[0];
+                (change.old.clone().unwrap(), change.new.clone().unwrap())
+            })
+            .collect();
+        assert_eq!(
+            infos,
+            vec![
+                ("task".to_string(), "renamed".to_string()),
+                ("renamed".to_string(), "task".to_string()),
+                ("task".to_string(), "renamed".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_category_history() {
+        let conn = get_test_conn();
+        add(&conn, "task");
+        conn.rename_category("work", "job").unwrap();
+
+        let entries = conn.history_since(1).unwrap();
+        let categories: Vec<_> = entries
+            .iter()
+            .filter(|entry| entry.table == "categories")
+            .map(|entry| (entry.action, entry.changes.len()))
+            .collect();
+        assert_eq!(categories, vec![(Action::Insert, 2), (Action::Update, 1)]);
+        // the task keeps pointing at the same category, so it did not change
+        assert_eq!(entries.len(), 3);
+    }
+
+    /// Moves every recorded change `days` further into the past
+    fn days_pass(conn: &Conn, days: u32) {
+        conn.client
+            .execute_batch(&format!(
+                r"
+                DROP TRIGGER IF EXISTS task_history_no_update;
+                UPDATE task_history SET at = datetime(at, '-{days} days');
+                "
+            ))
+            .unwrap();
+    }
+
+    fn day(days_ago: i64) -> String {
+        (chrono::Local::now().date_naive() - chrono::Duration::days(days_ago))
+            .format("%Y-%m-%d")
+            .to_string()
+    }
+
+    fn listed(conn: &Conn, options: &ListOptions) -> Vec<(i32, String)> {
+        conn.list_tasks(options)
+            .unwrap()
+            .into_iter()
+            .map(|task| (task.id, task.info))
+            .collect()
+    }
+
+    #[test]
+    fn test_as_of() {
+        let conn = get_test_conn();
+        // three days ago
+        let report = add(&conn, "write report");
+        let slides = add(&conn, "make slides");
+        days_pass(&conn, 1);
+        // two days ago
+        conn.edit_task(
+            report,
+            &TaskEdit {
+                priority: Some(Priority::High),
+                tags: vec!["urgent".to_string()],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        conn.edit_task(
+            slides,
+            &TaskEdit {
+                finish: Some(true),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let review = add(&conn, "review");
+        conn.add_dependency(report, review).unwrap();
+        days_pass(&conn, 1);
+        // yesterday
+        conn.remove_task(report).unwrap();
+        conn.empty_trash(0).unwrap();
+        conn.rename_category("work", "job").unwrap();
+        days_pass(&conn, 1);
+
+        let open = ListOptions::default();
+        let everything = ListOptions {
+            include_done: true,
+            ..Default::default()
+        };
+        let task = |id: i32, info: &str| (id, info.to_string());
+
+        assert!(listed(&conn.as_of(&day(4)).unwrap(), &everything).is_empty());
+        assert_eq!(
+            listed(&conn.as_of(&day(3)).unwrap(), &open),
+            vec![task(report, "write report"), task(slides, "make slides")]
+        );
+
+        let two_days_ago = conn.as_of(&day(2)).unwrap();
+        assert_eq!(
+            listed(&two_days_ago, &open),
+            vec![task(report, "write report"), task(review, "review")]
+        );
+        assert_eq!(
+            listed(&two_days_ago, &everything),
+            vec![
+                task(report, "write report"),
+                task(review, "review"),
+                task(slides, "make slides"),
+            ]
+        );
+        let past_report = two_days_ago.get_task(report).unwrap();
+        assert_eq!(past_report.priority, Priority::High);
+        assert_eq!(past_report.tags, vec!["a", "b", "urgent"]);
+        assert_eq!(past_report.category.as_deref(), Some("work"));
+        assert_eq!(
+            two_days_ago.get_task(review).unwrap().blocked_by,
+            vec![report]
+        );
+        // filters work on the past as they do on the present
+        let urgent = ListOptions {
+            filter: Some(
+                Filter::parse(
+                    "tag:urgent or done",
+                    &chrono::Local::now().date_naive(),
+                    &Default::default(),
+                )
+                .unwrap(),
+            ),
+            ..Default::default()
+        };
+        assert_eq!(
+            listed(&two_days_ago, &urgent),
+            vec![task(report, "write report"), task(slides, "make slides")]
+        );
+
+        let yesterday = conn.as_of(&day(1)).unwrap();
+        assert_eq!(listed(&yesterday, &open), vec![task(review, "review")]);
+        assert!(yesterday.get_task(review).unwrap().blocked_by.is_empty());
+        assert_eq!(
+            yesterday.get_task(review).unwrap().category.as_deref(),
+            Some("job")
+        );
+        assert_eq!(
+            listed(&conn.as_of(&day(0)).unwrap(), &everything),
+            listed(&conn, &everything)
+        );
+    }
+
+    #[test]
+    fn test_history_is_append_only() {
+        let conn = get_test_conn();
+        add(&conn, "task");
+        assert!(conn.client.execute("DELETE FROM task_history", ()).is_err());
+        assert!(
+            conn.client
+                .execute("UPDATE task_history SET user = 'someone else'", ())
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,503 @@
+use crate::{Conn, Error, Result, history};
+use rusqlite::{Connection, OptionalExtension, Row};
+use serde::Serialize;
+
+/// Tables the journal follows, parents before children, with the columns
+/// that make up a row's `row_key`
+const TABLES: &[(&str, &[&str])] = &[
+    ("categories", &["id"]),
+    ("tags", &["id"]),
+    ("tasks", &["id"]),
+    ("task_tags", &["task_id", "tag_id"]),
+    ("task_deps", &["blocker_id", "blocked_id"]),
+];
+
+/// One change to the todo list as recorded for `undo` and `redo`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Operation {
+    pub id: i64,
+    /// What was done, e.g. `edit task 3`
+    pub description: String,
+    /// Local `YYYY-MM-DD HH:MM:SS` time
+    pub at: String,
+    pub undone: bool,
+}
+
+const OPERATION_SELECT: &str = r"
+    SELECT id, description, datetime(at, 'localtime'), undone IS NOT NULL FROM journal
+    ";
+
+impl Operation {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get(0)?,
+            description: row.get(1)?,
+            at: row.get(2)?,
+            undone: row.get(3)?,
+        })
+    }
+}
+
+impl Conn {
+    /// The latest `limit` operations, newest first, undone ones included
+    pub fn operations(&self, limit: u32) -> Result<Vec<Operation>> {
+        let operations = self
+            .client
+            .prepare(&format!("{OPERATION_SELECT} ORDER BY id DESC LIMIT ?1"))?
+            .query_map([limit], Operation::from_row)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(operations)
+    }
+
+    /// Reverts operation `id`, or the latest one still applied. Fails with
+    /// `UndoConflict` when a later applied operation changed the same rows,
+    /// unless `force` says to overwrite them.
+    pub fn undo(&self, id: Option<i64>, force: bool) -> Result<Operation> {
+        let tx = self.client.unchecked_transaction()?;
+        let operation = match id {
+            Some(id) => find_operation(&tx, id)?,
+            None => tx
+                .query_row(
+                    &format!("{OPERATION_SELECT} WHERE undone IS NULL ORDER BY id DESC"),
+                    (),
+                    Operation::from_row,
+                )
+                .optional()?
+                .ok_or(Error::NothingToUndo(None))?,
+        };
+        if operation.undone {
+            return Err(Error::NothingToUndo(Some(operation.id)));
+        }
+
+        check_later(&tx, operation.id, force)?;
+        replay(&tx, operation.id, "before")?;
+        history::record(&tx, operation.id)?;
+        tx.execute(
+            "UPDATE journal SET undone = (SELECT coalesce(max(undone), 0) + 1 FROM journal) WHERE id = ?1",
+            [operation.id],
+        )?;
+        tx.commit()?;
+        Ok(Operation {
+            undone: true,
+            ..operation
+        })
+    }
+
+    /// Applies undone operation `id` again, or the one undone last, with the
+    /// same conflict check as `undo`
+    pub fn redo(&self, id: Option<i64>, force: bool) -> Result<Operation> {
+        let tx = self.client.unchecked_transaction()?;
+        let operation = match id {
+            Some(id) => find_operation(&tx, id)?,
+            None => tx
+                .query_row(
+                    &format!("{OPERATION_SELECT} WHERE undone IS NOT NULL ORDER BY undone DESC"),
+                    (),
+                    Operation::from_row,
+                )
+                .optional()?
+                .ok_or(Error::NothingToRedo(None))?,
+        };
+        if !operation.undone {
+            return Err(Error::NothingToRedo(Some(operation.id)));
+        }
+
+        check_later(&tx, operation.id, force)?;
+        replay(&tx, operation.id, "after")?;
+        history::record(&tx, operation.id)?;
+        tx.execute(
+            "UPDATE journal SET undone = NULL WHERE id = ?1",
+            [operation.id],
+        )?;
+        tx.commit()?;
+        Ok(Operation {
+            undone: false,
+            ..operation
+        })
+    }
+}
+
+/// Starts recording the changes of an operation, inside the caller's
+/// transaction
+pub(crate) fn begin(client: &Connection) -> Result<()> {
+    client.execute("INSERT INTO journal (description) VALUES ('')", ())?;
+    client.execute(
+        "UPDATE journal_state SET operation_id = last_insert_rowid()",
+        (),
+    )?;
+    Ok(())
+}
+
+/// Stops recording and keeps every changed row as it is now. An operation
+/// that changed nothing is dropped, any other one ends what can be redone.
+pub(crate) fn end(client: &Connection, description: &str) -> Result<()> {
+    let id: i64 = client.query_row("SELECT operation_id FROM journal_state", (), |row| {
+        row.get(0)
+    })?;
+    client.execute("UPDATE journal_state SET operation_id = NULL", ())?;
+
+    for (table, keys) in TABLES {
+        client.execute(
+            &format!(
+                r"
+                UPDATE journal_rows SET after = (
+                    SELECT {} FROM {table} WHERE {}
+                )
+                WHERE operation_id = ?1 AND row_table = ?2
+                ",
+                image(client, table)?,
+                matches(keys, "journal_rows.row_key"),
+            ),
+            rusqlite::params![id, table],
+        )?;
+    }
+    // rows that ended up the way they started
+    client.execute(
+        "DELETE FROM journal_rows WHERE operation_id = ?1 AND before IS after",
+        [id],
+    )?;
+
+    let changed: u32 = client.query_row(
+        "SELECT count(*) FROM journal_rows WHERE operation_id = ?1",
+        [id],
+        |row| row.get(0),
+    )?;
+    if changed == 0 {
+        client.execute("DELETE FROM journal WHERE id = ?1", [id])?;
+        return Ok(());
+    }
+    history::record(client, id)?;
+    client.execute("DELETE FROM journal WHERE undone IS NOT NULL", ())?;
+    client.execute(
+        "UPDATE journal SET description = ?1 WHERE id = ?2",
+        rusqlite::params![description, id],
+    )?;
+    Ok(())
+}
+
+fn find_operation(client: &Connection, id: i64) -> Result<Operation> {
+    client
+        .query_row(
+            &format!("{OPERATION_SELECT} WHERE id = ?1"),
+            [id],
+            Operation::from_row,
+        )
+        .optional()?
+        .ok_or(Error::OperationNotFound(id))
+}
+
+/// Fails with `UndoConflict` if an applied operation after `id` changed one
+/// of its rows, unless `force`
+fn check_later(client: &Connection, id: i64, force: bool) -> Result<()> {
+    let later = client
+        .prepare(
+            r"
+            SELECT DISTINCT later.operation_id FROM journal_rows AS mine
+            JOIN journal_rows AS later
+                ON later.row_table = mine.row_table AND later.row_key = mine.row_key
+            JOIN journal ON journal.id = later.operation_id
+            WHERE mine.operation_id = ?1 AND later.operation_id > ?1 AND journal.undone IS NULL
+            ORDER BY later.operation_id
+            ",
+        )?
+        .query_map([id], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<i64>>>()?;
+    match later.is_empty() || force {
+        true => Ok(()),
+        false => Err(Error::UndoConflict { id, later }),
+    }
+}
+
+/// Puts every row operation `id` changed back to its `before` or `after`
+/// image. Removals go first, children before parents, then the others
+/// parents first.
+fn replay(client: &Connection, id: i64, image: &str) -> Result<()> {
+    client.pragma_update(None, "defer_foreign_keys", true)?;
+    let rows = client
+        .prepare(&format!(
+            "SELECT row_table, row_key, {image} FROM journal_rows WHERE operation_id = ?1"
+        ))?
+        .query_map([id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect::<rusqlite::Result<Vec<(String, String, Option<String>)>>>()?;
+
+    for (table, keys) in TABLES.iter().rev() {
+        for (_, key, _) in rows
+            .iter()
+            .filter(|(name, _, image)| name == table && image.is_none())
+        {
+            client.execute(
+                &format!("DELETE FROM {table} WHERE {}", matches(keys, "?1")),
+                [key],
+            )?;
+        }
+    }
+    for (table, keys) in TABLES {
+        for (_, key, image) in rows.iter().filter(|(name, _, _)| name == table) {
+            if let Some(image) = image {
+                restore(client, table, keys, key, image)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Writes `image` into the row of `table` with `key`, inserting it if it
+/// is missing
+fn restore(client: &Connection, table: &str, keys: &[&str], key: &str, image: &str) -> Result<()> {
+    let columns = columns(client, table)?;
+    let matches = matches(keys, "?1");
+    let exists = client
+        .query_row(
+            &format!("SELECT 1 FROM {table} WHERE {matches}"),
+            [key],
+            |_| Ok(()),
+        )
+        .optional()?
+        .is_some();
+    if !exists {
+        let values: Vec<_> = columns
+            .iter()
+            .map(|column| format!("json_extract(?1, '$.{column}')"))
+            .collect();
+        client.execute(
+            &format!(
+                "INSERT INTO {table} ({}) VALUES ({})",
+                columns.join(", "),
+                values.join(", ")
+            ),
+            [image],
+        )?;
+    }
+
+    let set: Vec<_> = columns
+        .iter()
+        .map(|column| format!("{column} = json_extract(?2, '$.{column}')"))
+        .collect();
+    // the first write can set off the timestamp triggers, the second puts
+    // the recorded times back
+    for _ in 0..2 {
+        client.execute(
+            &format!("UPDATE {table} SET {} WHERE {matches}", set.join(", ")),
+            [key, image],
+        )?;
+    }
+    Ok(())
+}
+
+pub(crate) fn columns(client: &Connection, table: &str) -> Result<Vec<String>> {
+    let columns = client
+        .prepare("SELECT name FROM pragma_table_info(?1) ORDER BY cid")?
+        .query_map([table], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(columns)
+}
+
+/// A row of `table` as a JSON object, the same shape its triggers record
+fn image(client: &Connection, table: &str) -> Result<String> {
+    let pairs: Vec<_> = columns(client, table)?
+        .iter()
+        .map(|column| format!("'{column}', {table}.{column}"))
+        .collect();
+    Ok(format!("json_object({})", pairs.join(", ")))
+}
+
+/// Matches the row whose key columns equal the JSON array `key`
+fn matches(keys: &[&str], key: &str) -> String {
+    let conditions: Vec<_> = keys
+        .iter()
+        .enumerate()
+        .map(|(i, column)| format!("{column} = json_extract({key}, '$[{i}]')"))
+        .collect();
+    conditions.join(" AND ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ListOptions, NewTask, Priority, Task, TaskEdit};
+
+    fn get_test_conn() -> Conn {
+        let conn = Conn::build(":memory:").unwrap();
+        conn.add_task(&NewTask {
+            info: "write report".to_string(),
+            category: Some("work".to_string()),
+            tags: vec!["q3".to_string()],
+            ..Default::default()
+        })
+        .unwrap();
+        conn
+    }
+
+    fn all_tasks(conn: &Conn) -> Vec<Task> {
+        conn.list_tasks(&ListOptions {
+            include_done: true,
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    fn descriptions(conn: &Conn) -> Vec<(String, bool)> {
+        conn.operations(10)
+            .unwrap()
+            .into_iter()
+            .map(|op| (op.description, op.undone))
+            .collect()
+    }
+
+    #[test]
+    fn test_triggers_record_every_column() {
+        let conn = get_test_conn();
+        conn.remove_task(1).unwrap();
+        conn.empty_trash(0).unwrap();
+        conn.delete_category("work", None).unwrap();
+        let recorded: Vec<(String, String)> = conn
+            .client
+            .prepare(
+                r"
+                SELECT row_table, (SELECT group_concat(key) FROM json_each(before))
+                FROM journal_rows WHERE before IS NOT NULL
+                ",
+            )
+            .unwrap()
+            .query_map((), |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        let tables: Vec<_> = recorded.iter().map(|(table, _)| table.as_str()).collect();
+        for table in ["categories", "tasks", "task_tags"] {
+            assert!(tables.contains(&table), "{table}");
+        }
+        for (table, keys) in recorded {
+            assert_eq!(keys, columns(&conn.client, &table).unwrap().join(","));
+        }
+    }
+
+    #[test]
+    fn test_undo_and_redo() {
+        let conn = get_test_conn();
+        let added = conn.get_task(1).unwrap();
+
+        conn.edit_task(
+            1,
+            &TaskEdit {
+                priority: Some(Priority::High),
+                finish: Some(true),
+                untag: vec!["q3".to_string()],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let edited = conn.get_task(1).unwrap();
+        conn.remove_task(1).unwrap();
+        assert!(all_tasks(&conn).is_empty());
+        assert_eq!(
+            descriptions(&conn),
+            vec![
+                ("remove task 1".to_string(), false),
+                ("edit task 1".to_string(), false),
+                ("add task 1".to_string(), false),
+            ]
+        );
+
+        assert_eq!(conn.undo(None, false).unwrap().description, "remove task 1");
+        assert_eq!(all_tasks(&conn), vec![edited.clone()]);
+        conn.undo(None, false).unwrap();
+        assert_eq!(all_tasks(&conn), vec![added.clone()]);
+        conn.undo(None, false).unwrap();
+        assert!(all_tasks(&conn).is_empty());
+        assert!(matches!(
+            conn.undo(None, false),
+            Err(Error::NothingToUndo(None))
+        ));
+
+        assert_eq!(conn.redo(None, false).unwrap().description, "add task 1");
+        assert_eq!(all_tasks(&conn), vec![added]);
+        conn.redo(None, false).unwrap();
+        assert_eq!(all_tasks(&conn), vec![edited]);
+
+        // a new operation ends what could be redone
+        conn.add_task(&NewTask {
+            info: "other".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(matches!(
+            conn.redo(None, false),
+            Err(Error::NothingToRedo(None))
+        ));
+        assert_eq!(conn.get_task(1).unwrap().info, "write report");
+    }
+
+    #[test]
+    fn test_undo_categories() {
+        let conn = get_test_conn();
+        conn.add_category("work/clients").unwrap();
+        conn.rename_category("work", "job").unwrap();
+        conn.undo(None, false).unwrap();
+        let names: Vec<_> = conn
+            .list_categories()
+            .unwrap()
+            .into_iter()
+            .map(|category| category.name)
+            .collect();
+        assert_eq!(names, vec!["work", "work/clients"]);
+
+        conn.delete_category("work", None).unwrap();
+        assert_eq!(conn.get_task(1).unwrap().category, None);
+        conn.undo(None, false).unwrap();
+        assert_eq!(conn.get_task(1).unwrap().category.as_deref(), Some("work"));
+    }
+
+    #[test]
+    fn test_undo_conflicts() {
+        let conn = get_test_conn();
+        conn.add_task(&NewTask {
+            info: "other".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        let rename = |id, info: &str| {
+            conn.edit_task(
+                id,
+                &TaskEdit {
+                    info: Some(info.to_string()),
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+        };
+        rename(1, "first");
+        let first = conn.operations(1).unwrap()[0].id;
+        rename(2, "second");
+        rename(1, "third");
+
+        // a later operation changed task 1 again
+        assert!(matches!(
+            conn.undo(Some(first), false),
+            Err(Error::UndoConflict { id, ref later }) if id == first && *later == vec![first + 2]
+        ));
+        assert_eq!(conn.get_task(1).unwrap().info, "third");
+
+        // once that is undone there is nothing in the way
+        conn.undo(None, false).unwrap();
+        conn.undo(Some(first), false).unwrap();
+        assert_eq!(conn.get_task(1).unwrap().info, "write report");
+        assert_eq!(conn.get_task(2).unwrap().info, "second");
+        assert!(matches!(
+            conn.undo(Some(first), false),
+            Err(Error::NothingToUndo(Some(_)))
+        ));
+        assert!(matches!(
+            conn.undo(Some(99), false),
+            Err(Error::OperationNotFound(99))
+        ));
+    }
+
+    #[test]
+    fn test_unchanged_operations_are_dropped() {
+        let conn = get_test_conn();
+        conn.remove_dependency(1, 1).unwrap();
+        conn.edit_task(1, &TaskEdit::default()).unwrap();
+        assert_eq!(descriptions(&conn), vec![("add task 1".to_string(), false)]);
+    }
+}
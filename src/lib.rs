@@ -1,217 +1,886 @@
-use chrono::NaiveDate;
-use rusqlite::{Connection, Result};
+use rusqlite::types::Value;
+use rusqlite::{Connection, OptionalExtension, Row};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+mod backup;
+mod category;
+pub mod config;
+mod date;
+mod deps;
+mod error;
+mod filter;
+mod history;
+mod journal;
+pub mod location;
+pub mod migrations;
+mod priority;
+mod recur;
+mod search;
+mod theme;
+mod trash;
+
+pub use backup::{EXPORT_VERSION, Export, ExportedTask, ImportSummary, OnConflict};
+pub use category::CategoryCount;
+pub use config::Config;
+pub use date::{DateError, DateOptions, DateOrder, PartialDateError, format_date};
+pub use deps::Dependency;
+pub use error::{Error, Result};
+pub use filter::{Compare, DateField, Filter, FilterError, IdRange, describe_tasks};
+pub use history::{Action, FieldChange, HistoryEntry};
+pub use journal::Operation;
+pub use location::{DbLocation, DbSource};
+pub use priority::Priority;
+pub use recur::{Frequency, Recurrence};
+pub use search::SearchHit;
+pub use theme::{Color, ColorMode, Theme};
+pub use trash::TrashedTask;
 
 pub struct Conn {
     client: Connection,
+    /// Refuse unknown categories on add and edit instead of creating them
+    strict_categories: bool,
 }
 
-impl Conn {
-    pub fn build(file_path: &str) -> Result<Self> {
-        let client = Connection::open(file_path)?;
+/// A single row of the todo list, joined with its category and tag names
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Task {
+    pub id: i32,
+    pub info: String,
+    pub done: bool,
+    pub due_date: Option<String>,
+    pub category: Option<String>,
+    pub priority: Priority,
+    /// Sorted by name
+    pub tags: Vec<String>,
+    /// Task this is a subtask of
+    pub parent: Option<i32>,
+    /// Finished and total direct subtasks, `None` without any
+    pub progress: Option<Progress>,
+    /// Open tasks that have to be done before this one, sorted
+    pub blocked_by: Vec<i32>,
+    /// Finishing the task adds its next instance
+    pub recurrence: Option<Recurrence>,
+    /// Local `YYYY-MM-DD HH:MM:SS` times, `None` for tasks made before they
+    /// were recorded
+    pub created_at: Option<String>,
+    pub modified_at: Option<String>,
+    /// Set while the task is done
+    pub completed_at: Option<String>,
+}
 
-        client.execute(
-            r"
-            CREATE TABLE IF NOT EXISTS categories (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                name TEXT NOT NULL UNIQUE CHECK(name != '')
-            )
-            ",
-            (),
-        )?;
+/// How many of a task's subtasks are done, shown as `3/5`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Progress {
+    pub done: u32,
+    pub total: u32,
+}
 
-        client.execute(
-            r"
-            CREATE TABLE IF NOT EXISTS tasks (
-                id INTEGER PRIMARY KEY,
-                info TEXT NOT NULL CHECK(info != ''),
-                done BOOLEAN NOT NULL DEFAULT false CHECK(done IN (0,1)),
-                due_date TEXT CHECK(
-                    due_date IS NULL OR
-                    (due_date GLOB '[0-9][0-9][0-9][0-9]-[0-1][0-9]-[0-3][0-9]' AND
-                        date(due_date) IS NOT NULL)
-            ),
-            category INTEGER,
-            FOREIGN KEY(category) REFERENCES categories(id)
-            )",
-            (),
+impl Display for Progress {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.done, self.total)
+    }
+}
+
+impl Display for Task {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>3}. [{}] ",
+            self.id,
+            if self.done { 'x' } else { ' ' }
         )?;
+        if self.priority != Priority::Medium {
+            write!(f, "({}) ", self.priority.letter())?;
+        }
+        write!(f, "{}", self.info)?;
+        if let Some(progress) = &self.progress {
+            write!(f, " [{progress}]")?;
+        }
+        if let Some(due_date) = &self.due_date {
+            write!(f, " | due {due_date}")?;
+        }
+        if let Some(recurrence) = &self.recurrence {
+            write!(f, " | repeats {recurrence}")?;
+        }
+        if let Some(category) = &self.category {
+            write!(f, " | {category}")?;
+        }
+        if !self.tags.is_empty() {
+            write!(f, " | {}", self.hashtags())?;
+        }
+        if !self.blocked_by.is_empty() {
+            write!(f, " | {}", self.blockers())?;
+        }
+        Ok(())
+    }
+}
+
+/// Fields for a task that has not been inserted yet
+#[derive(Debug, Default, Clone)]
+pub struct NewTask {
+    pub info: String,
+    pub category: Option<String>,
+    /// Due date, already formatted as YYYY-MM-DD
+    pub due_date: Option<String>,
+    pub priority: Priority,
+    pub tags: Vec<String>,
+    /// Makes this a subtask of the given task
+    pub parent: Option<i32>,
+    pub recurrence: Option<Recurrence>,
+}
+
+/// Fields to change on an existing task, `None` leaves the field untouched
+#[derive(Debug, Default, Clone)]
+pub struct TaskEdit {
+    pub finish: Option<bool>,
+    /// Due date, already formatted as YYYY-MM-DD
+    pub due_date: Option<String>,
+    pub category: Option<String>,
+    pub info: Option<String>,
+    pub priority: Option<Priority>,
+    /// Tags to add, ones the task already has are ignored
+    pub tags: Vec<String>,
+    /// Tags to remove, ones the task does not have are ignored
+    pub untag: Vec<String>,
+    /// Finishing also finishes every open subtask, without it finishing a
+    /// task with open subtasks fails with `OpenSubtasks`
+    pub cascade: bool,
+    pub recurrence: Option<Recurrence>,
+    /// Stop the task from repeating, wins over `recurrence`
+    pub stop_repeating: bool,
+}
+
+/// How `Conn::list_tasks` filters and orders its results
+#[derive(Debug, Default, Clone)]
+pub struct ListOptions {
+    pub sort_by_category: bool,
+    /// Only show tasks in this category or one below it
+    pub category: Option<String>,
+    pub include_done: bool,
+    /// Only show open tasks that are not blocked
+    pub ready: bool,
+    /// Only show these priorities, all of them when empty
+    pub priorities: Vec<Priority>,
+    pub tags: TagFilter,
+    /// Only show tasks created on or after this YYYY-MM-DD date
+    pub created_since: Option<String>,
+    /// Only show tasks finished between these YYYY-MM-DD dates, both
+    /// included, done tasks are listed even without `include_done`
+    pub completed_between: Option<(String, String)>,
+    /// Only show tasks whose info matches this full text query
+    pub search: Option<String>,
+    /// Only show tasks matching this expression, which decides on done tasks
+    /// itself when it mentions them
+    pub filter: Option<Filter>,
+    pub sort: SortBy,
+}
 
-        Ok(Self { client })
+impl ListOptions {
+    /// Whether finished tasks can show up at all
+    fn include_done(&self) -> bool {
+        self.include_done
+            || self.completed_between.is_some()
+            || self.filter.as_ref().is_some_and(Filter::mentions_done)
     }
 }
 
-#[derive(Debug, PartialEq)]
-enum PartialDate {
-    Day { day: u32 },
-    MonthDay { month: u32, day: u32 },
-    YearMonthDay { year: i32, month: u32, day: u32 },
+/// Order of `list`, every timestamp puts the most recent first and tasks
+/// without one last
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum SortBy {
+    /// Priority, then due date
+    #[default]
+    Priority,
+    Created,
+    Modified,
+    Completed,
 }
 
-#[derive(Debug, PartialEq)]
-enum PartialDateError {
-    YearError(String),
-    MonthError(String),
-    DayError(String),
-    PartError(String),
+impl SortBy {
+    /// Timestamp column sorted on, if any
+    fn column(&self) -> Option<&'static str> {
+        match self {
+            Self::Priority => None,
+            Self::Created => Some("tasks.created_at"),
+            Self::Modified => Some("tasks.modified_at"),
+            Self::Completed => Some("tasks.completed_at"),
+        }
+    }
+
+    /// The timestamp of `task` this sorts on
+    pub fn timestamp<'a>(&self, task: &'a Task) -> Option<&'a str> {
+        match self {
+            Self::Priority => None,
+            Self::Created => task.created_at.as_deref(),
+            Self::Modified => task.modified_at.as_deref(),
+            Self::Completed => task.completed_at.as_deref(),
+        }
+    }
 }
 
-impl PartialDate {
-    fn build(date_str: &str) -> Result<Self, PartialDateError> {
-        let cleaned_date = date_str.trim().replace('/', "-");
-        let parts: Vec<_> = cleaned_date.split('-').collect();
-        Ok(match parts.as_slice() {
-            [y, m, d] => Self::YearMonthDay {
-                year: y
-                    .parse()
-                    .map_err(|_| PartialDateError::YearError(y.to_string()))?,
-                month: m
-                    .parse()
-                    .map_err(|_| PartialDateError::MonthError(m.to_string()))?,
-                day: d
-                    .parse()
-                    .map_err(|_| PartialDateError::DayError(d.to_string()))?,
+impl FromStr for SortBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "priority" => Ok(Self::Priority),
+            "created" => Ok(Self::Created),
+            "modified" => Ok(Self::Modified),
+            "completed" => Ok(Self::Completed),
+            _ => Err(format!(
+                "'{s}' is not a sort order, use priority, created, modified or completed"
+            )),
+        }
+    }
+}
+
+/// Which tags a listed task must have, every empty list matches all tasks
+#[derive(Debug, Default, Clone)]
+pub struct TagFilter {
+    /// Tasks must have every one of these
+    pub all: Vec<String>,
+    /// Tasks must have at least one of these
+    pub any: Vec<String>,
+    /// Tasks must have none of these
+    pub none: Vec<String>,
+}
+
+/// A tag with the number of open and finished tasks carrying it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TagCount {
+    pub name: String,
+    pub open: u32,
+    pub done: u32,
+}
+
+/// Tag names are joined with the ASCII unit separator, which no one types
+const TAG_SEPARATOR: char = '\u{1f}';
+
+const TASK_SELECT: &str = r"
+    SELECT tasks.id, tasks.info, tasks.done, tasks.due_date, categories.name,
+        tasks.priority,
+        (SELECT group_concat(name, char(31)) FROM (
+            SELECT tags.name FROM task_tags
+            JOIN tags ON task_tags.tag_id = tags.id
+            WHERE task_tags.task_id = tasks.id
+            ORDER BY tags.name
+        )),
+        tasks.parent_id,
+        (SELECT count(*) FROM tasks AS sub
+            WHERE sub.parent_id = tasks.id AND sub.deleted_at IS NULL),
+        (SELECT count(*) FROM tasks AS sub
+            WHERE sub.parent_id = tasks.id AND sub.deleted_at IS NULL AND sub.done),
+        (SELECT group_concat(blocker.id) FROM task_deps
+            JOIN tasks AS blocker ON task_deps.blocker_id = blocker.id
+            WHERE task_deps.blocked_id = tasks.id AND NOT blocker.done
+                AND blocker.deleted_at IS NULL),
+        datetime(tasks.created_at, 'localtime'),
+        datetime(tasks.modified_at, 'localtime'),
+        datetime(tasks.completed_at, 'localtime'),
+        tasks.recurrence,
+        tasks.repeat_from_completion
+    FROM tasks
+    LEFT JOIN categories ON tasks.category = categories.id
+    ";
+
+/// `subtree` holds the ids of every subtask below task `?1`, trashed ones
+/// left out
+const SUBTREE: &str = r"
+    WITH RECURSIVE subtree(id) AS (
+        SELECT id FROM tasks WHERE parent_id = ?1 AND deleted_at IS NULL
+        UNION
+        SELECT tasks.id FROM tasks JOIN subtree ON tasks.parent_id = subtree.id
+        WHERE tasks.deleted_at IS NULL
+    )";
+
+/// Matches tasks carrying a tag whose name is in the list that follows
+const HAS_TAG: &str = r"
+    EXISTS (SELECT 1 FROM task_tags
+        JOIN tags ON task_tags.tag_id = tags.id
+        WHERE task_tags.task_id = tasks.id AND tags.name IN";
+
+impl Task {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get(0)?,
+            info: row.get(1)?,
+            done: row.get(2)?,
+            due_date: row.get(3)?,
+            category: row.get(4)?,
+            priority: row.get(5)?,
+            tags: row
+                .get::<_, Option<String>>(6)?
+                .map(|tags| tags.split(TAG_SEPARATOR).map(str::to_string).collect())
+                .unwrap_or_default(),
+            parent: row.get(7)?,
+            progress: match row.get(8)? {
+                0 => None,
+                total => Some(Progress {
+                    done: row.get(9)?,
+                    total,
+                }),
             },
-            [m, d] => Self::MonthDay {
-                month: m
-                    .parse()
-                    .map_err(|_| PartialDateError::MonthError(m.to_string()))?,
-                day: d
-                    .parse()
-                    .map_err(|_| PartialDateError::DayError(d.to_string()))?,
+            blocked_by: {
+                let ids: Option<String> = row.get(10)?;
+                let mut ids: Vec<i32> = ids
+                    .iter()
+                    .flat_map(|ids| ids.split(','))
+                    .filter_map(|id| id.parse().ok())
+                    .collect();
+                ids.sort();
+                ids
             },
-            [d] => Self::Day {
-                day: d
-                    .parse()
-                    .map_err(|_| PartialDateError::DayError(d.to_string()))?,
+            created_at: row.get(11)?,
+            modified_at: row.get(12)?,
+            completed_at: row.get(13)?,
+            recurrence: match row.get::<_, Option<Recurrence>>(14)? {
+                Some(rule) => Some(Recurrence {
+                    from_completion: row.get(15)?,
+                    ..rule
+                }),
+                None => None,
             },
-            _ => Err(PartialDateError::PartError(cleaned_date))?,
         })
     }
+
+    /// `blocked by 3, 7`, the way `list` shows open prerequisites
+    pub fn blockers(&self) -> String {
+        let ids: Vec<_> = self.blocked_by.iter().map(i32::to_string).collect();
+        format!("blocked by {}", ids.join(", "))
+    }
+
+    /// The tags as `#name` words, the way `list` shows them
+    pub fn hashtags(&self) -> String {
+        self.tags
+            .iter()
+            .map(|tag| format!("#{tag}"))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
 }
 
-#[derive(Debug)]
-struct DateError;
-
-fn make_date(partial_date: &PartialDate, today: &NaiveDate) -> Result<String, DateError> {
-    Ok(match partial_date {
-        PartialDate::YearMonthDay { year, month, day } => {
-            if *year >= 1000 {
-                chrono::NaiveDate::from_ymd_opt(*year, *month, *day)
-                    .ok_or(DateError)?
-                    .to_string()
-            } else if *year >= 100 {
-                unimplemented!()
-            } else if *year >= 10 {
-                unimplemented!()
-            } else if *year >= 0 {
-                unimplemented!()
-            } else {
-                unimplemented!()
-            }
+impl Conn {
+    /// Opens the database, migrating its schema to the latest version and
+    /// creating any missing parent directories
+    pub fn build(file_path: impl AsRef<Path>) -> Result<Self> {
+        let file_path = file_path.as_ref();
+        if let Some(dir) = file_path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
         }
-        PartialDate::MonthDay { month, day } => unimplemented!(),
-        PartialDate::Day { day } => unimplemented!(),
-    })
+        let mut client = Connection::open(file_path)?;
 
-    // make sure to add the leading 0 thing
-    // 2001-01-11
-    // handle invalid numbers
-}
+        migrations::migrate(&mut client)?;
+        client.pragma_update(None, "foreign_keys", true)?;
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use rusqlite::{Error::SqliteFailure, Result, ffi};
+        Ok(Self {
+            client,
+            strict_categories: false,
+        })
+    }
 
-    #[test]
-    fn test_make_date() {
-        assert_eq!(
-            make_date(
-                &PartialDate::YearMonthDay {
-                    year: 2021,
-                    month: 12,
-                    day: 1
-                },
-                &NaiveDate::from_ymd_opt(2021, 12, 1).unwrap(),
+    /// Makes `add_task` and `edit_task` fail with `CategoryNotFound` instead
+    /// of creating categories that do not exist yet
+    pub fn strict_categories(mut self, strict: bool) -> Self {
+        self.strict_categories = strict;
+        self
+    }
+
+    /// Inserts a task, creating its category and tags if they do not exist yet
+    pub fn add_task(&self, task: &NewTask) -> Result<Task> {
+        let tx = self.client.unchecked_transaction()?;
+        journal::begin(&tx)?;
+
+        if let Some(parent) = task.parent {
+            ensure_task(&tx, parent)?;
+        }
+
+        let category = match &task.category {
+            Some(name) => Some(self.category_id(&tx, name)?),
+            None => None,
+        };
+
+        tx.execute(
+            r"
+            INSERT INTO tasks (info, due_date, category, priority, parent_id, recurrence,
+                repeat_from_completion)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+            ",
+            rusqlite::params![
+                task.info,
+                task.due_date,
+                category,
+                task.priority,
+                task.parent,
+                task.recurrence.as_ref().map(Recurrence::rrule),
+                task.recurrence
+                    .as_ref()
+                    .is_some_and(|rule| rule.from_completion),
+            ],
+        )?;
+        let id = tx.last_insert_rowid() as i32;
+        for name in &task.tags {
+            add_tag(&tx, id, name)?;
+        }
+        journal::end(&tx, &format!("add task {id}"))?;
+        tx.commit()?;
+
+        self.get_task(id)
+    }
+
+    /// Looks up a single task by id
+    pub fn get_task(&self, id: i32) -> Result<Task> {
+        self.client
+            .query_row(
+                &format!("{TASK_SELECT} WHERE tasks.id = ?1 AND tasks.deleted_at IS NULL"),
+                [id],
+                Task::from_row,
             )
-            .unwrap(),
-            "2021-12-01".to_string(),
-        )
+            .optional()?
+            .ok_or(Error::TaskNotFound(id))
     }
 
-    #[test]
-    fn test_partial_date() {
-        // sanity
-        assert_eq!(
-            PartialDate::build("2024-02-12").unwrap(),
-            PartialDate::YearMonthDay {
-                year: 2024,
-                month: 2,
-                day: 12,
-            }
-        );
+    /// Lists tasks, open ones first, then by category, priority and due date
+    pub fn list_tasks(&self, options: &ListOptions) -> Result<Vec<Task>> {
+        let (conditions, params) = self.conditions(options)?;
+        let include_done = options.include_done();
+        let mut sql = format!("{TASK_SELECT}{conditions}");
+        sql.push_str("ORDER BY ");
+        if let Some(column) = options.sort.column() {
+            sql.push_str(&format!("{column} IS NULL, {column} DESC, "));
+        } else if include_done {
+            sql.push_str("tasks.done, ");
+        }
+        if options.sort_by_category {
+            sql.push_str(&format!(
+                "categories.name IS NULL, {}, ",
+                category::TREE_ORDER
+            ));
+        }
+        sql.push_str("tasks.priority, tasks.due_date IS NULL, tasks.due_date, tasks.id");
 
-        // trim
-        assert_eq!(
-            PartialDate::build("    2024-02-12   \n ").unwrap(),
-            PartialDate::YearMonthDay {
-                year: 2024,
-                month: 2,
-                day: 12,
-            }
-        );
+        let tasks = self
+            .client
+            .prepare(&sql)?
+            .query_map(rusqlite::params_from_iter(&params), Task::from_row)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(tasks)
+    }
 
-        // replacement
-        assert_eq!(
-            PartialDate::build("2024/02/12").unwrap(),
-            PartialDate::YearMonthDay {
-                year: 2024,
-                month: 2,
-                day: 12,
-            }
-        );
+    /// Number of tasks `list_tasks` would return for `options`
+    pub fn count_tasks(&self, options: &ListOptions) -> Result<u32> {
+        let (conditions, params) = self.conditions(options)?;
+        let count = self.client.query_row(
+            &format!(
+                r"
+                SELECT count(*) FROM tasks
+                LEFT JOIN categories ON tasks.category = categories.id
+                {conditions}
+                "
+            ),
+            rusqlite::params_from_iter(&params),
+            |row| row.get(0),
+        )?;
+        Ok(count)
+    }
 
-        // 0 parts
-        assert_eq!(
-            PartialDate::build("   "),
-            Err(PartialDateError::DayError("".to_string())),
-        );
+    /// The `WHERE` clause picking the tasks `options` asks for, with its
+    /// parameters in order
+    fn conditions(&self, options: &ListOptions) -> Result<(String, Vec<Value>)> {
+        let mut conditions = vec!["tasks.deleted_at IS NULL".to_string()];
+        let mut params: Vec<Value> = Vec::new();
+        if !options.include_done() || options.ready {
+            conditions.push("tasks.done = false".to_string());
+        }
+        if options.ready {
+            conditions.push(format!("NOT {}", deps::BLOCKED));
+        }
+        if !options.priorities.is_empty() {
+            let levels: Vec<_> = options
+                .priorities
+                .iter()
+                .map(|priority| (*priority as i64).to_string())
+                .collect();
+            conditions.push(format!("tasks.priority IN ({})", levels.join(", ")));
+        }
+        if let Some(path) = &options.category {
+            let path = category::normalize(path)?;
+            conditions.push(category::IN_SUBTREE.to_string());
+            params.extend([path.clone(), path.clone(), path].map(Value::from));
+        }
+        let filter = &options.tags;
+        for name in &filter.all {
+            conditions.push(format!("{HAS_TAG} (?))"));
+            params.push(name.clone().into());
+        }
+        if !filter.any.is_empty() {
+            conditions.push(format!("{HAS_TAG} ({}))", placeholders(filter.any.len())));
+            params.extend(filter.any.iter().cloned().map(Value::from));
+        }
+        if !filter.none.is_empty() {
+            conditions.push(format!(
+                "NOT {HAS_TAG} ({}))",
+                placeholders(filter.none.len())
+            ));
+            params.extend(filter.none.iter().cloned().map(Value::from));
+        }
+        if let Some(since) = &options.created_since {
+            conditions.push("date(tasks.created_at, 'localtime') >= ?".to_string());
+            params.push(since.clone().into());
+        }
+        if let Some((from, to)) = &options.completed_between {
+            conditions.push("date(tasks.completed_at, 'localtime') BETWEEN ? AND ?".to_string());
+            params.extend([from.clone(), to.clone()].map(Value::from));
+        }
+        if let Some(query) = &options.search {
+            search::check_query(&self.client, query)?;
+            conditions.push(search::MATCHES.to_string());
+            params.push(query.clone().into());
+        }
+        if let Some(filter) = &options.filter {
+            filter.check(&self.client)?;
+            conditions.push(filter.to_sql(&mut params));
+        }
 
-        // 1 part
-        assert_eq!(
-            PartialDate::build("22").unwrap(),
-            PartialDate::Day { day: 22 }
-        );
+        Ok((format!("WHERE {}\n", conditions.join(" AND ")), params))
+    }
 
-        // 2 thing
-        assert_eq!(
-            PartialDate::build("02-22").unwrap(),
-            PartialDate::MonthDay { month: 2, day: 22 }
-        );
+    /// Applies every set field of `edit` to the task, all or nothing.
+    /// Finishing a repeating task, or cascading to repeating subtasks, adds
+    /// their next instances without copying their subtasks.
+    pub fn edit_task(&self, id: i32, edit: &TaskEdit) -> Result<Task> {
+        let tx = self.client.unchecked_transaction()?;
+        journal::begin(&tx)?;
+        self.apply_edit(&tx, id, edit)?;
+        journal::end(&tx, &format!("edit task {id}"))?;
+        tx.commit()?;
+        self.get_task(id)
+    }
 
-        // 3 thing
-        assert_eq!(
-            PartialDate::build("2024-02-22").unwrap(),
-            PartialDate::YearMonthDay {
-                year: 2024,
-                month: 2,
-                day: 22
+    /// Applies `edit` to every task in `ids` in a single transaction, pairing
+    /// each task as it was with how it ends up. Nothing changes when one of
+    /// them fails or with `dry_run`, which still reports what would change.
+    pub fn edit_tasks(
+        &self,
+        ids: &[i32],
+        edit: &TaskEdit,
+        dry_run: bool,
+    ) -> Result<Vec<(Task, Task)>> {
+        let tx = self.client.unchecked_transaction()?;
+        journal::begin(&tx)?;
+        let mut changes = Vec::new();
+        for &id in ids {
+            let before = self.get_task(id)?;
+            self.apply_edit(&tx, id, edit)?;
+            changes.push((before, self.get_task(id)?));
+        }
+        journal::end(&tx, &format!("edit {}", describe_tasks(ids)))?;
+        if !dry_run {
+            tx.commit()?;
+        }
+        Ok(changes)
+    }
+
+    /// The body of `edit_task`, inside the caller's transaction
+    fn apply_edit(&self, tx: &Connection, id: i32, edit: &TaskEdit) -> Result<()> {
+        ensure_task(tx, id)?;
+
+        let mut repeating = Vec::new();
+        if edit.finish == Some(true) {
+            repeating = tx
+                .prepare(&format!(
+                    r"
+                    {SUBTREE}
+                    SELECT id FROM tasks
+                    WHERE (id = ?1 OR id IN subtree) AND NOT done AND recurrence IS NOT NULL
+                    ORDER BY id
+                    "
+                ))?
+                .query_map([id], |row| row.get::<_, i32>(0))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            let open: u32 = tx.query_row(
+                &format!("{SUBTREE} SELECT count(*) FROM tasks WHERE id IN subtree AND NOT done"),
+                [id],
+                |row| row.get(0),
+            )?;
+            if open > 0 && !edit.cascade {
+                return Err(Error::OpenSubtasks { id, open });
             }
-        );
+            if let Some((blocked, by)) = open_blockers(tx, id)? {
+                return Err(Error::Blocked { id: blocked, by });
+            }
+            tx.execute(
+                &format!("{SUBTREE} UPDATE tasks SET done = true WHERE id IN subtree"),
+                [id],
+            )?;
+        }
 
-        // 4 thing
-        assert_eq!(
-            PartialDate::build("20-20-20-20"),
-            Err(PartialDateError::PartError("20-20-20-20".to_string())),
-        );
+        if let Some(finish) = edit.finish {
+            tx.execute(
+                "UPDATE tasks SET done = ?1 WHERE id = ?2",
+                rusqlite::params![finish, id],
+            )?;
+        }
 
-        // test invalid part
-        assert_eq!(
-            PartialDate::build("20-20-hello"),
-            Err(PartialDateError::DayError("hello".to_string())),
-        );
+        if let Some(info) = &edit.info {
+            tx.execute(
+                "UPDATE tasks SET info = ?1 WHERE id = ?2",
+                rusqlite::params![info, id],
+            )?;
+        }
+
+        if let Some(due_date) = &edit.due_date {
+            tx.execute(
+                "UPDATE tasks SET due_date = ?1 WHERE id = ?2",
+                rusqlite::params![due_date, id],
+            )?;
+        }
+
+        if let Some(priority) = edit.priority {
+            tx.execute(
+                "UPDATE tasks SET priority = ?1 WHERE id = ?2",
+                rusqlite::params![priority, id],
+            )?;
+        }
+
+        if let Some(name) = &edit.category {
+            let category = self.category_id(tx, name)?;
+            tx.execute(
+                "UPDATE tasks SET category = ?1 WHERE id = ?2",
+                rusqlite::params![category, id],
+            )?;
+        }
+
+        for name in &edit.tags {
+            add_tag(tx, id, name)?;
+        }
+
+        for name in &edit.untag {
+            tx.execute(
+                r"
+                DELETE FROM task_tags
+                WHERE task_id = ?1 AND tag_id = (SELECT id FROM tags WHERE name = ?2)
+                ",
+                rusqlite::params![id, name],
+            )?;
+        }
+
+        if edit.stop_repeating {
+            tx.execute(
+                "UPDATE tasks SET recurrence = NULL, repeat_from_completion = false WHERE id = ?1",
+                [id],
+            )?;
+        } else if let Some(rule) = &edit.recurrence {
+            tx.execute(
+                "UPDATE tasks SET recurrence = ?1, repeat_from_completion = ?2 WHERE id = ?3",
+                rusqlite::params![rule.rrule(), rule.from_completion, id],
+            )?;
+        }
+
+        // after the other fields so that the next instance takes them along
+        let today = chrono::Local::now().date_naive();
+        for task in repeating {
+            recur::repeat(tx, task, today)?;
+        }
+        Ok(())
     }
 
+    /// The instance finishing task `id` added, if it repeats
+    pub fn next_instance(&self, id: i32) -> Result<Option<Task>> {
+        let next = self.client.query_row(
+            "SELECT max(id) FROM tasks WHERE repeat_of = ?1",
+            [id],
+            |row| row.get::<_, Option<i32>>(0),
+        )?;
+        next.map(|next| self.get_task(next)).transpose()
+    }
+
+    /// Number of subtasks below a task, at any depth
+    pub fn count_subtasks(&self, id: i32) -> Result<u32> {
+        ensure_task(&self.client, id)?;
+        Ok(self.client.query_row(
+            &format!("{SUBTREE} SELECT count(*) FROM subtree"),
+            [id],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// Moves a task and all of its subtasks to the trash, returning the row
+    /// as it was before removal
+    pub fn remove_task(&self, id: i32) -> Result<Task> {
+        let mut tasks = self.remove_tasks(&[id])?;
+        Ok(tasks.remove(0))
+    }
+
+    /// Moves every task in `ids` with their subtasks to the trash in a single
+    /// transaction, returning the rows as they were before removal
+    pub fn remove_tasks(&self, ids: &[i32]) -> Result<Vec<Task>> {
+        let tx = self.client.unchecked_transaction()?;
+        let tasks = ids
+            .iter()
+            .map(|&id| self.get_task(id))
+            .collect::<Result<Vec<_>>>()?;
+        journal::begin(&tx)?;
+        for id in ids {
+            tx.execute(
+                &format!(
+                    r"
+                    {SUBTREE}
+                    UPDATE tasks SET deleted_at = strftime('%Y-%m-%d %H:%M:%S')
+                    WHERE id = ?1 OR id IN subtree
+                    "
+                ),
+                [id],
+            )?;
+        }
+        journal::end(&tx, &format!("remove {}", describe_tasks(ids)))?;
+        tx.commit()?;
+        Ok(tasks)
+    }
+
+    /// Every tag by name, with how many open and finished tasks carry it
+    pub fn list_tags(&self) -> Result<Vec<TagCount>> {
+        let tags = self
+            .client
+            .prepare(
+                r"
+                SELECT tags.name,
+                    count(tasks.id) FILTER (WHERE tasks.done = false),
+                    count(tasks.id) FILTER (WHERE tasks.done = true)
+                FROM tags
+                LEFT JOIN task_tags ON task_tags.tag_id = tags.id
+                LEFT JOIN tasks ON task_tags.task_id = tasks.id AND tasks.deleted_at IS NULL
+                GROUP BY tags.id
+                ORDER BY tags.name
+                ",
+            )?
+            .query_map((), |row| {
+                Ok(TagCount {
+                    name: row.get(0)?,
+                    open: row.get(1)?,
+                    done: row.get(2)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(tags)
+    }
+}
+
+/// Moves subtasks right after their parent, keeping the order otherwise,
+/// paired with how deep each task is. Tasks whose parent is not in `tasks`
+/// stay at the top level.
+pub fn nest_subtasks(tasks: Vec<Task>) -> Vec<(usize, Task)> {
+    let ids: HashSet<_> = tasks.iter().map(|task| task.id).collect();
+    let mut roots = Vec::new();
+    let mut children: HashMap<i32, Vec<Task>> = HashMap::new();
+    for task in tasks {
+        match task.parent.filter(|parent| ids.contains(parent)) {
+            Some(parent) => children.entry(parent).or_default().push(task),
+            None => roots.push(task),
+        }
+    }
+
+    fn visit(
+        task: Task,
+        depth: usize,
+        children: &mut HashMap<i32, Vec<Task>>,
+        out: &mut Vec<(usize, Task)>,
+    ) {
+        let below = children.remove(&task.id).unwrap_or_default();
+        out.push((depth, task));
+        for child in below {
+            visit(child, depth + 1, children, out);
+        }
+    }
+
+    let mut nested = Vec::new();
+    for task in roots {
+        visit(task, 0, &mut children, &mut nested);
+    }
+    nested
+}
+
+/// The first task of `id` and its open subtasks that waits on an open task
+/// outside of them, with those prerequisites
+fn open_blockers(client: &Connection, id: i32) -> Result<Option<(i32, Vec<i32>)>> {
+    let mut rows = client
+        .prepare(&format!(
+            r"
+            {SUBTREE},
+            finishing(id) AS (
+                SELECT ?1 UNION SELECT id FROM tasks WHERE id IN subtree AND NOT done
+            )
+            SELECT task_deps.blocked_id, task_deps.blocker_id FROM task_deps
+            JOIN tasks AS blocker ON task_deps.blocker_id = blocker.id
+            WHERE task_deps.blocked_id IN finishing
+                AND blocker.id NOT IN finishing
+                AND NOT blocker.done
+                AND blocker.deleted_at IS NULL
+            ORDER BY task_deps.blocked_id, task_deps.blocker_id
+            "
+        ))?
+        .query_map([id], |row| {
+            Ok((row.get::<_, i32>(0)?, row.get::<_, i32>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let Some(&(blocked, _)) = rows.first() else {
+        return Ok(None);
+    };
+    rows.retain(|(task, _)| *task == blocked);
+    Ok(Some((
+        blocked,
+        rows.into_iter().map(|(_, by)| by).collect(),
+    )))
+}
+
+/// Fails with `TaskNotFound` unless a task has this id
+fn ensure_task(client: &Connection, id: i32) -> Result<()> {
+    client
+        .query_row(
+            "SELECT 1 FROM tasks WHERE id = ?1 AND deleted_at IS NULL",
+            [id],
+            |_| Ok(()),
+        )
+        .optional()?
+        .ok_or(Error::TaskNotFound(id))
+}
+
+/// `?, ?, ?` for an `IN` list of `count` values
+fn placeholders(count: usize) -> String {
+    vec!["?"; count].join(", ")
+}
+
+/// Tags the task, creating the tag if missing
+fn add_tag(client: &Connection, task_id: i32, name: &str) -> rusqlite::Result<()> {
+    let existing = client
+        .query_row("SELECT id FROM tags WHERE name = ?1", [name], |row| {
+            row.get::<_, i64>(0)
+        })
+        .optional()?;
+    let tag_id = match existing {
+        Some(id) => id,
+        None => {
+            client.execute("INSERT INTO tags (name) VALUES (?1)", [name])?;
+            client.last_insert_rowid()
+        }
+    };
+    client.execute(
+        "INSERT OR IGNORE INTO task_tags (task_id, tag_id) VALUES (?1, ?2)",
+        rusqlite::params![task_id, tag_id],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::{Error::SqliteFailure, Result, ffi};
+
     fn assert_err<T>(res: Result<T>, err_code: i32, err_msg: &str) {
         match res {
             Err(SqliteFailure(err, msg)) => {
@@ -236,6 +905,17 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_db_invalid_priority() {
+        let conn = get_test_conn();
+        assert_err(
+            conn.client
+                .execute("INSERT INTO tasks (info, priority) VALUES ('foo', ?)", [4]),
+            ffi::SQLITE_CONSTRAINT_CHECK,
+            "CHECK constraint failed: priority IN (1,2,3)",
+        );
+    }
+
     #[test]
     fn test_db_empty_duedate() {
         let conn = get_test_conn();
@@ -296,6 +976,33 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_db_unique_tag_name() {
+        let conn = get_test_conn();
+
+        conn.client
+            .execute("INSERT INTO tags (name) VALUES ('hello')", ())
+            .unwrap();
+
+        assert_err(
+            conn.client
+                .execute("INSERT INTO tags (name) VALUES ('hello')", ()),
+            ffi::SQLITE_CONSTRAINT_UNIQUE,
+            "UNIQUE constraint failed: tags.name",
+        );
+    }
+
+    #[test]
+    fn test_db_empty_tag_name() {
+        assert_err(
+            get_test_conn()
+                .client
+                .execute("INSERT INTO tags (name) VALUES ('')", ()),
+            ffi::SQLITE_CONSTRAINT_CHECK,
+            "CHECK constraint failed: tag_name",
+        );
+    }
+
     #[test]
     fn test_db_null_cat_name() {
         assert_err(
@@ -410,307 +1117,820 @@ mod tests {
 
         assert_eq!(row.id, 1);
         assert_eq!(row.info, "This is a test".to_string());
-        assert_eq!(row.done, false);
+        assert!(!row.done);
         assert_eq!(row.due_date, "2025-09-23".to_string());
         assert_eq!(row.category, "test".to_string());
     }
+
+    fn new_task(info: &str, category: Option<&str>, due_date: Option<&str>) -> NewTask {
+        NewTask {
+            info: info.to_string(),
+            category: category.map(str::to_string),
+            due_date: due_date.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_add_task() {
+        let conn = get_test_conn();
+
+        let task = conn
+            .add_task(&new_task("write docs", Some("work"), Some("2025-09-23")))
+            .unwrap();
+        assert_eq!(
+            task,
+            Task {
+                id: 1,
+                info: "write docs".to_string(),
+                done: false,
+                due_date: Some("2025-09-23".to_string()),
+                category: Some("work".to_string()),
+                priority: Priority::Medium,
+                tags: Vec::new(),
+                parent: None,
+                progress: None,
+                blocked_by: Vec::new(),
+                recurrence: None,
+                created_at: task.created_at.clone(),
+                modified_at: task.created_at.clone(),
+                completed_at: None,
+            }
+        );
+        assert!(task.created_at.is_some());
+
+        // existing category is reused
+        conn.add_task(&new_task("review", Some("work"), None))
+            .unwrap();
+        assert_eq!(
+            conn.client
+                .query_row("SELECT COUNT(*) FROM categories", (), |row| row
+                    .get::<_, i32>(0))
+                .unwrap(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_add_task_invalid() {
+        let conn = get_test_conn();
+        assert!(matches!(
+            conn.add_task(&new_task("", None, None)),
+            Err(Error::EmptyTaskInfo)
+        ));
+        assert!(matches!(
+            conn.add_task(&new_task("foo", None, Some("2025-13-01"))),
+            Err(Error::InvalidDueDate)
+        ));
+        assert!(matches!(
+            conn.add_task(&new_task("foo", Some(""), None)),
+            Err(Error::EmptyCategoryName)
+        ));
+        assert!(conn.list_tasks(&ListOptions::default()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_list_tasks() {
+        let conn = get_test_conn();
+        conn.add_task(&new_task("no date", Some("b"), None))
+            .unwrap();
+        conn.add_task(&new_task("late", Some("a"), Some("2025-10-01")))
+            .unwrap();
+        conn.add_task(&new_task("early", None, Some("2025-09-01")))
+            .unwrap();
+        conn.add_task(&new_task("finished", Some("a"), Some("2025-08-01")))
+            .unwrap();
+        conn.edit_task(
+            4,
+            &TaskEdit {
+                finish: Some(true),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let ids = |options: ListOptions| {
+            conn.list_tasks(&options)
+                .unwrap()
+                .iter()
+                .map(|task| task.id)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(ids(ListOptions::default()), vec![3, 2, 1]);
+        assert_eq!(
+            ids(ListOptions {
+                sort_by_category: true,
+                ..Default::default()
+            }),
+            vec![2, 1, 3]
+        );
+        assert_eq!(
+            ids(ListOptions {
+                include_done: true,
+                ..Default::default()
+            }),
+            vec![3, 2, 1, 4]
+        );
+        assert_eq!(
+            ids(ListOptions {
+                sort_by_category: true,
+                include_done: true,
+                ..Default::default()
+            }),
+            vec![2, 1, 3, 4]
+        );
+
+        // priority comes before the due date, after the category
+        conn.edit_task(
+            1,
+            &TaskEdit {
+                priority: Some(Priority::High),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        conn.edit_task(
+            3,
+            &TaskEdit {
+                priority: Some(Priority::Low),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(ids(ListOptions::default()), vec![1, 2, 3]);
+        assert_eq!(
+            ids(ListOptions {
+                sort_by_category: true,
+                ..Default::default()
+            }),
+            vec![2, 1, 3]
+        );
+        assert_eq!(
+            ids(ListOptions {
+                priorities: vec![Priority::High, Priority::Low],
+                ..Default::default()
+            }),
+            vec![1, 3]
+        );
+        assert_eq!(
+            ids(ListOptions {
+                include_done: true,
+                priorities: vec![Priority::Medium],
+                ..Default::default()
+            }),
+            vec![2, 4]
+        );
+    }
+
+    #[test]
+    fn test_edit_task() {
+        let conn = get_test_conn();
+        conn.add_task(&new_task("foo", None, None)).unwrap();
+
+        let task = conn
+            .edit_task(
+                1,
+                &TaskEdit {
+                    finish: Some(true),
+                    due_date: Some("2025-09-30".to_string()),
+                    category: Some("home".to_string()),
+                    info: Some("bar".to_string()),
+                    priority: Some(Priority::Low),
+                    tags: vec!["errand".to_string()],
+                    untag: Vec::new(),
+                    cascade: false,
+                    recurrence: None,
+                    stop_repeating: false,
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            task,
+            Task {
+                id: 1,
+                info: "bar".to_string(),
+                done: true,
+                due_date: Some("2025-09-30".to_string()),
+                category: Some("home".to_string()),
+                priority: Priority::Low,
+                tags: vec!["errand".to_string()],
+                parent: None,
+                progress: None,
+                blocked_by: Vec::new(),
+                recurrence: None,
+                created_at: task.created_at.clone(),
+                modified_at: task.modified_at.clone(),
+                completed_at: task.completed_at.clone(),
+            }
+        );
+        assert!(task.completed_at.is_some());
+    }
+
+    #[test]
+    fn test_tags() {
+        let conn = get_test_conn();
+        let tagged = |info: &str, tags: &[&str]| NewTask {
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            ..new_task(info, None, None)
+        };
+        let task = conn.add_task(&tagged("a", &["urgent", "phone"])).unwrap();
+        assert_eq!(task.tags, vec!["phone", "urgent"]);
+        conn.add_task(&tagged("b", &["phone"])).unwrap();
+        conn.add_task(&tagged("c", &[])).unwrap();
+
+        // adding a tag twice is a no-op, unknown tags are ignored on removal
+        let task = conn
+            .edit_task(
+                2,
+                &TaskEdit {
+                    tags: vec!["urgent".to_string(), "phone".to_string()],
+                    untag: vec!["email".to_string()],
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(task.tags, vec!["phone", "urgent"]);
+        let task = conn
+            .edit_task(
+                1,
+                &TaskEdit {
+                    finish: Some(true),
+                    untag: vec!["urgent".to_string()],
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(task.tags, vec!["phone"]);
+
+        assert!(matches!(
+            conn.add_task(&tagged("d", &[""])),
+            Err(Error::EmptyTagName)
+        ));
+        // the failed insert left nothing behind
+        assert_eq!(conn.list_tasks(&ListOptions::default()).unwrap().len(), 2);
+
+        assert_eq!(
+            conn.list_tags().unwrap(),
+            vec![
+                TagCount {
+                    name: "phone".to_string(),
+                    open: 1,
+                    done: 1,
+                },
+                TagCount {
+                    name: "urgent".to_string(),
+                    open: 1,
+                    done: 0,
+                },
+            ]
+        );
+
+        // a trashed task keeps its tags but is not counted, emptying the
+        // trash drops them and leaves the tag itself
+        let tag_rows = |id: i32| -> u32 {
+            conn.client
+                .query_row(
+                    "SELECT count(*) FROM task_tags WHERE task_id = ?1",
+                    [id],
+                    |row| row.get(0),
+                )
+                .unwrap()
+        };
+        conn.remove_task(2).unwrap();
+        assert_eq!(tag_rows(2), 2);
+        let urgent = TagCount {
+            name: "urgent".to_string(),
+            open: 0,
+            done: 0,
+        };
+        assert_eq!(conn.list_tags().unwrap()[1], urgent);
+
+        conn.empty_trash(0).unwrap();
+        assert_eq!(tag_rows(2), 0);
+        assert_eq!(conn.list_tags().unwrap()[1], urgent);
+    }
+
+    #[test]
+    fn test_list_tags_filter() {
+        let conn = get_test_conn();
+        for tags in [vec!["a", "b"], vec!["a"], vec!["b", "c"], vec![]] {
+            conn.add_task(&NewTask {
+                tags: tags.into_iter().map(str::to_string).collect(),
+                ..new_task("task", None, None)
+            })
+            .unwrap();
+        }
+
+        let strings = |names: &[&str]| names.iter().map(|n| n.to_string()).collect();
+        let ids = |all: &[&str], any: &[&str], none: &[&str]| {
+            let options = ListOptions {
+                tags: TagFilter {
+                    all: strings(all),
+                    any: strings(any),
+                    none: strings(none),
+                },
+                ..Default::default()
+            };
+            conn.list_tasks(&options)
+                .unwrap()
+                .iter()
+                .map(|task| task.id)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(ids(&[], &[], &[]), vec![1, 2, 3, 4]);
+        assert_eq!(ids(&["a"], &[], &[]), vec![1, 2]);
+        assert_eq!(ids(&["a", "b"], &[], &[]), vec![1]);
+        assert_eq!(ids(&[], &["a", "c"], &[]), vec![1, 2, 3]);
+        assert_eq!(ids(&[], &[], &["b"]), vec![2, 4]);
+        assert_eq!(ids(&["b"], &["a", "c"], &["c"]), vec![1]);
+        assert_eq!(ids(&["missing"], &[], &[]), Vec::<i32>::new());
+    }
+
+    #[test]
+    fn test_timestamps() {
+        let conn = get_test_conn();
+        for info in ["a", "b", "c"] {
+            conn.add_task(&new_task(info, None, None)).unwrap();
+        }
+        // pretend the tasks were made on earlier days
+        conn.client
+            .execute(
+                r"
+                UPDATE tasks SET created_at = '2025-09-0' || id || ' 12:00:00',
+                    modified_at = '2025-09-0' || id || ' 12:00:00'
+                ",
+                (),
+            )
+            .unwrap();
+        let unchanged = |id: i32| {
+            let task = conn.get_task(id).unwrap();
+            task.created_at == task.modified_at
+        };
+
+        // direct writes move modified_at too, writing the same value does not
+        conn.client
+            .execute("UPDATE tasks SET info = 'a2' WHERE id = 1", ())
+            .unwrap();
+        conn.client
+            .execute("UPDATE tasks SET info = 'b' WHERE id = 2", ())
+            .unwrap();
+        assert!(!unchanged(1));
+        assert!(unchanged(2));
+        let tag = TaskEdit {
+            tags: vec!["x".to_string()],
+            ..Default::default()
+        };
+        conn.edit_task(3, &tag).unwrap();
+        assert!(!unchanged(3));
+
+        // completed_at follows done
+        assert!(conn.get_task(2).unwrap().completed_at.is_none());
+        assert!(
+            conn.edit_task(2, &finish(false))
+                .unwrap()
+                .completed_at
+                .is_some()
+        );
+        let reopen = TaskEdit {
+            finish: Some(false),
+            ..Default::default()
+        };
+        assert!(conn.edit_task(2, &reopen).unwrap().completed_at.is_none());
+
+        conn.edit_task(2, &finish(false)).unwrap();
+        conn.edit_task(3, &finish(false)).unwrap();
+        conn.client
+            .execute(
+                "UPDATE tasks SET completed_at = '2025-09-1' || id || ' 12:00:00' WHERE done",
+                (),
+            )
+            .unwrap();
+
+        let ids = |options: ListOptions| {
+            conn.list_tasks(&options)
+                .unwrap()
+                .iter()
+                .map(|task| task.id)
+                .collect::<Vec<_>>()
+        };
+        let between = |from: &str, to: &str| ListOptions {
+            completed_between: Some((from.to_string(), to.to_string())),
+            ..Default::default()
+        };
+        assert_eq!(ids(between("2025-09-12", "2025-09-13")), vec![2, 3]);
+        assert_eq!(ids(between("2025-09-13", "2025-09-30")), vec![3]);
+        assert_eq!(
+            ids(ListOptions {
+                sort: SortBy::Completed,
+                ..between("2025-09-01", "2025-09-30")
+            }),
+            vec![3, 2]
+        );
+        assert_eq!(
+            ids(ListOptions {
+                created_since: Some("2025-09-02".to_string()),
+                include_done: true,
+                ..Default::default()
+            }),
+            vec![2, 3]
+        );
+        assert_eq!(
+            ids(ListOptions {
+                sort: SortBy::Created,
+                include_done: true,
+                ..Default::default()
+            }),
+            vec![3, 2, 1]
+        );
+        // tasks that are not done have no completion time and come last
+        assert_eq!(
+            ids(ListOptions {
+                sort: SortBy::Completed,
+                include_done: true,
+                ..Default::default()
+            }),
+            vec![3, 2, 1]
+        );
+    }
+
+    #[test]
+    fn test_edit_task_atomic() {
+        let conn = get_test_conn();
+        conn.add_task(&new_task("foo", None, None)).unwrap();
+
+        // the bad due date rolls back the info change
+        assert!(
+            conn.edit_task(
+                1,
+                &TaskEdit {
+                    info: Some("bar".to_string()),
+                    due_date: Some("2025-13-01".to_string()),
+                    ..Default::default()
+                },
+            )
+            .is_err()
+        );
+        assert_eq!(conn.get_task(1).unwrap().info, "foo");
+    }
+
+    #[test]
+    fn test_edit_missing_task() {
+        assert!(matches!(
+            get_test_conn().edit_task(7, &TaskEdit::default()),
+            Err(Error::TaskNotFound(7))
+        ));
+    }
+
+    #[test]
+    fn test_remove_task() {
+        let conn = get_test_conn();
+        conn.add_task(&new_task("foo", None, None)).unwrap();
+
+        assert_eq!(conn.remove_task(1).unwrap().info, "foo");
+        assert!(matches!(conn.get_task(1), Err(Error::TaskNotFound(1))));
+        assert!(matches!(conn.remove_task(1), Err(Error::TaskNotFound(1))));
+    }
+
+    fn subtask(info: &str, parent: i32) -> NewTask {
+        NewTask {
+            parent: Some(parent),
+            ..new_task(info, None, None)
+        }
+    }
+
+    fn finish(cascade: bool) -> TaskEdit {
+        TaskEdit {
+            finish: Some(true),
+            cascade,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_subtasks() {
+        let conn = get_test_conn();
+        conn.add_task(&new_task("trip", None, None)).unwrap();
+        let child = conn.add_task(&subtask("book hotel", 1)).unwrap();
+        assert_eq!(child.parent, Some(1));
+        assert_eq!(child.progress, None);
+        conn.add_task(&subtask("pack", 1)).unwrap();
+        conn.add_task(&subtask("buy socks", 3)).unwrap();
+
+        assert_eq!(
+            conn.get_task(1).unwrap().progress,
+            Some(Progress { done: 0, total: 2 })
+        );
+        conn.edit_task(2, &finish(false)).unwrap();
+        assert_eq!(
+            conn.get_task(1).unwrap().progress,
+            Some(Progress { done: 1, total: 2 })
+        );
+        assert_eq!(conn.count_subtasks(1).unwrap(), 3);
+
+        assert!(matches!(
+            conn.add_task(&subtask("orphan", 99)),
+            Err(Error::TaskNotFound(99))
+        ));
+        assert_eq!(conn.list_tasks(&ListOptions::default()).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_finish_with_open_subtasks() {
+        let conn = get_test_conn();
+        conn.add_task(&new_task("trip", None, None)).unwrap();
+        conn.add_task(&subtask("pack", 1)).unwrap();
+        conn.add_task(&subtask("buy socks", 2)).unwrap();
+
+        // refused without cascading, nothing changes
+        assert!(matches!(
+            conn.edit_task(1, &finish(false)),
+            Err(Error::OpenSubtasks { id: 1, open: 2 })
+        ));
+        assert!(!conn.get_task(1).unwrap().done);
+
+        // cascading finishes the whole subtree
+        assert!(conn.edit_task(1, &finish(true)).unwrap().done);
+        assert!(conn.get_task(2).unwrap().done);
+        assert!(conn.get_task(3).unwrap().done);
+
+        // reopening a parent leaves its subtasks alone
+        let reopen = TaskEdit {
+            finish: Some(false),
+            ..Default::default()
+        };
+        assert!(!conn.edit_task(1, &reopen).unwrap().done);
+        assert!(conn.get_task(2).unwrap().done);
+    }
+
+    #[test]
+    fn test_remove_parent_removes_subtasks() {
+        let conn = get_test_conn();
+        conn.add_task(&new_task("trip", None, None)).unwrap();
+        conn.add_task(&subtask("pack", 1)).unwrap();
+        conn.add_task(&subtask("buy socks", 2)).unwrap();
+        conn.add_task(&new_task("other", None, None)).unwrap();
+
+        // removing a subtask leaves its parent
+        conn.remove_task(3).unwrap();
+        assert_eq!(conn.count_subtasks(1).unwrap(), 1);
+
+        conn.add_task(&subtask("buy socks", 2)).unwrap();
+        conn.remove_task(1).unwrap();
+        let left: Vec<_> = conn
+            .list_tasks(&ListOptions::default())
+            .unwrap()
+            .into_iter()
+            .map(|task| task.info)
+            .collect();
+        assert_eq!(left, vec!["other"]);
+    }
+
+    #[test]
+    fn test_edit_tasks() {
+        let conn = get_test_conn();
+        for info in ["a", "b", "c"] {
+            conn.add_task(&new_task(info, None, None)).unwrap();
+        }
+        let edit = TaskEdit {
+            priority: Some(Priority::High),
+            ..Default::default()
+        };
+        let priorities = || -> Vec<_> {
+            (1..=3)
+                .map(|id| conn.get_task(id).unwrap().priority)
+                .collect()
+        };
+
+        // a dry run reports the changes without making them
+        let changes = conn.edit_tasks(&[1, 3], &edit, true).unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[1].0.priority, Priority::Medium);
+        assert_eq!(changes[1].1.priority, Priority::High);
+        assert_eq!(priorities(), vec![Priority::Medium; 3]);
+
+        // one missing task leaves every other one untouched
+        assert!(matches!(
+            conn.edit_tasks(&[1, 4, 3], &edit, false),
+            Err(Error::TaskNotFound(4))
+        ));
+        assert_eq!(priorities(), vec![Priority::Medium; 3]);
+
+        conn.edit_tasks(&[1, 3], &edit, false).unwrap();
+        assert_eq!(
+            priorities(),
+            vec![Priority::High, Priority::Medium, Priority::High]
+        );
+    }
+
+    #[test]
+    fn test_remove_tasks() {
+        let conn = get_test_conn();
+        conn.add_task(&new_task("trip", None, None)).unwrap();
+        conn.add_task(&subtask("pack", 1)).unwrap();
+        conn.add_task(&new_task("other", None, None)).unwrap();
+
+        assert!(matches!(
+            conn.remove_tasks(&[3, 9]),
+            Err(Error::TaskNotFound(9))
+        ));
+        assert!(conn.get_task(3).is_ok());
+
+        // a subtask may be removed along with its parent
+        let removed = conn.remove_tasks(&[1, 2, 3]).unwrap();
+        let removed: Vec<_> = removed.iter().map(|task| task.info.as_str()).collect();
+        assert_eq!(removed, vec!["trip", "pack", "other"]);
+        assert!(conn.list_tasks(&ListOptions::default()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_repeat() {
+        let conn = get_test_conn();
+        let repeating = |due_date: &str, rule: &str| NewTask {
+            tags: vec!["chore".to_string()],
+            recurrence: Some(rule.parse().unwrap()),
+            ..new_task("rent", Some("home"), Some(due_date))
+        };
+        conn.add_task(&repeating("2025-01-31", "monthly")).unwrap();
+        assert!(conn.next_instance(1).unwrap().is_none());
+
+        conn.edit_task(1, &finish(false)).unwrap();
+        let next = conn.next_instance(1).unwrap().unwrap();
+        assert_eq!(
+            (next.id, next.done, next.due_date.as_deref()),
+            (2, false, Some("2025-02-28"))
+        );
+        assert_eq!(
+            (next.info.as_str(), next.category.as_deref()),
+            ("rent", Some("home"))
+        );
+        assert_eq!(next.tags, vec!["chore"]);
+        assert_eq!(next.recurrence.unwrap().to_string(), "monthly on 31");
+        conn.edit_task(2, &finish(false)).unwrap();
+        assert_eq!(
+            conn.next_instance(2).unwrap().unwrap().due_date.as_deref(),
+            Some("2025-03-31")
+        );
+
+        // the last instance of a count adds nothing
+        conn.add_task(&repeating("2025-01-01", "daily count 2"))
+            .unwrap();
+        conn.edit_task(4, &finish(false)).unwrap();
+        let last = conn.next_instance(4).unwrap().unwrap();
+        assert_eq!(last.recurrence.unwrap().count, Some(1));
+        conn.edit_task(last.id, &finish(false)).unwrap();
+        assert!(conn.next_instance(last.id).unwrap().is_none());
+
+        // from completion counts from today, whatever the due date was
+        conn.add_task(&repeating("2020-01-01", "every 3 days from completion"))
+            .unwrap();
+        let id = conn.list_tasks(&ListOptions::default()).unwrap()[0].id;
+        conn.edit_task(id, &finish(false)).unwrap();
+        let expected = chrono::Local::now().date_naive() + chrono::Days::new(3);
+        assert_eq!(
+            conn.next_instance(id).unwrap().unwrap().due_date,
+            Some(expected.to_string())
+        );
+
+        // a task that stopped repeating is simply done
+        let stop = TaskEdit {
+            stop_repeating: true,
+            ..finish(false)
+        };
+        let open = conn.list_tasks(&ListOptions::default()).unwrap();
+        let task = conn.edit_task(open[0].id, &stop).unwrap();
+        assert!(task.recurrence.is_none());
+        assert!(conn.next_instance(task.id).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_nest_subtasks() {
+        let task = |id: i32, parent: Option<i32>| Task {
+            id,
+            info: id.to_string(),
+            done: false,
+            due_date: None,
+            category: None,
+            priority: Priority::Medium,
+            tags: Vec::new(),
+            parent,
+            progress: None,
+            blocked_by: Vec::new(),
+            recurrence: None,
+            created_at: None,
+            modified_at: None,
+            completed_at: None,
+        };
+        let tasks = vec![
+            task(3, Some(1)),
+            task(1, None),
+            task(4, Some(3)),
+            task(2, None),
+            task(5, Some(9)),
+            task(6, Some(1)),
+        ];
+        let nested: Vec<_> = nest_subtasks(tasks)
+            .into_iter()
+            .map(|(depth, task)| (depth, task.id))
+            .collect();
+        // 5's parent is not listed so it stays at the top
+        assert_eq!(nested, vec![(0, 1), (1, 3), (2, 4), (1, 6), (0, 2), (0, 5)]);
+    }
+
+    #[test]
+    fn test_task_display() {
+        let task = Task {
+            id: 4,
+            info: "foo".to_string(),
+            done: true,
+            due_date: Some("2025-09-30".to_string()),
+            category: Some("home".to_string()),
+            priority: Priority::Medium,
+            tags: Vec::new(),
+            parent: None,
+            progress: None,
+            blocked_by: Vec::new(),
+            recurrence: None,
+            created_at: None,
+            modified_at: None,
+            completed_at: None,
+        };
+        assert_eq!(task.to_string(), "  4. [x] foo | due 2025-09-30 | home");
+        let task = Task {
+            priority: Priority::High,
+            tags: vec!["errand".to_string(), "phone".to_string()],
+            ..task
+        };
+        assert_eq!(
+            task.to_string(),
+            "  4. [x] (H) foo | due 2025-09-30 | home | #errand #phone"
+        );
+        let task = Task {
+            progress: Some(Progress { done: 3, total: 5 }),
+            tags: Vec::new(),
+            ..task
+        };
+        assert_eq!(
+            task.to_string(),
+            "  4. [x] (H) foo [3/5] | due 2025-09-30 | home"
+        );
+        let task = Task {
+            progress: None,
+            blocked_by: vec![2, 3],
+            ..task
+        };
+        assert_eq!(
+            task.to_string(),
+            "  4. [x] (H) foo | due 2025-09-30 | home | blocked by 2, 3"
+        );
+        let task = Task {
+            blocked_by: Vec::new(),
+            recurrence: Some("every 2 weeks on mon".parse().unwrap()),
+            ..task
+        };
+        assert_eq!(
+            task.to_string(),
+            "  4. [x] (H) foo | due 2025-09-30 | repeats every 2 weeks on mon | home"
+        );
+    }
+
+    #[test]
+    fn test_task_json() {
+        let task = Task {
+            id: 4,
+            info: "foo".to_string(),
+            done: false,
+            due_date: Some("2025-09-30".to_string()),
+            category: None,
+            priority: Priority::High,
+            tags: vec!["errand".to_string()],
+            parent: Some(1),
+            progress: Some(Progress { done: 1, total: 2 }),
+            blocked_by: vec![2],
+            recurrence: Some("weekly".parse().unwrap()),
+            created_at: None,
+            modified_at: None,
+            completed_at: None,
+        };
+        assert_eq!(
+            serde_json::to_string(&task).unwrap(),
+            concat!(
+                r#"{"id":4,"info":"foo","done":false,"due_date":"2025-09-30","category":null,"#,
+                r#""priority":"high","tags":["errand"],"parent":1,"progress":{"done":1,"total":2},"#,
+                r#""blocked_by":[2],"recurrence":{"rrule":"FREQ=WEEKLY","from_completion":false},"#,
+                r#""created_at":null,"modified_at":null,"completed_at":null}"#
+            )
+        );
+    }
 }
 
-// use chrono::NaiveDate;
-// use rusqlite::{Connection, Result};
-//
-// pub struct TodoConn {
-//     client: Connection,
-// }
-//
-// impl TodoConn {
-//
-//     pub fn add_task(
-//         &self,
-//         task: &str,
-//         category: Option<&str>,
-//         due_date: Option<&str>,
-//     ) -> Result<()> {
-//         Ok(())
-//         // insert category if not there
-//         //
-//         // match (category, due_date) {
-//         //     (None, None) => {
-//         //         conn.execute("INSERT INTO tasks (info) VALUES (?1)", [&task])
-//         //     }
-//         //     (Some(c), None) => {
-//         //         conn.execute("INSERT INTO tasks (info, category) VALUES (?1, (SELECT id FROM categories WHERE name = ?2))", [task, c])
-//         //     } (None, Some(d)) => {
-//         //         let formatted_date = format_date(d, &Local::now().date_naive()).unwrap();
-//         //         conn.execute(
-//         //             "INSERT INTO tasks (info, due_date) VALUES (?1, ?2)",
-//         //             [task, &formatted_date],
-//         //         )
-//         //     }
-//         //     (Some(c), Some(d)) => {
-//         //         let formatted_date = format_date(d, &Local::now().date_naive()).unwrap();
-//         //         conn.execute(
-//         //            "INSERT INTO tasks (info, due_date, category) VALUES (?1, ?2, (SELECT id FROM categories WHERE name = ?3))",
-//         //            [task, &formatted_date, c],
-//         //        )
-//         //     }
-//         // }.map(|_| ())
-//     }
-//
-//     pub fn get_task(&self, sort_by_cat: bool, include_done: bool) -> Result<Vec<String>> {
-//         // should be tasks instead of string
-//         Ok(vec![])
-//         // let mut sql = String::from(
-//         //     r#"
-//         //     SELECT tasks.id,
-//         //         tasks.info,
-//         //         tasks.done,
-//         //         tasks.due_date,
-//         //         categories.name
-//         //     FROM tasks
-//         //     LEFT JOIN
-//         //         categories
-//         //     ON
-//         //         tasks.category = categories.id
-//         //     "#,
-//         // );
-//         // if !include_done {
-//         //     sql.push_str("WHERE tasks.done = false\n");
-//         // }
-//         // sql.push_str("ORDER BY\n");
-//         // if include_done {
-//         //     sql.push_str("tasks.done, \n");
-//         // }
-//         // if sort_by_cat {
-//         //     sql.push_str("categories.id, \n");
-//         // }
-//         // sql.push_str("tasks.due_date IS NULL, tasks.due_date");
-//         // conn.prepare(&sql)?
-//         //     .query_map((), |row| {
-//         //         Ok(Task {
-//         //             id: row.get(0)?,
-//         //             info: row.get(1)?,
-//         //             done: row.get(2)?,
-//         //             due_date: row.get(3)?,
-//         //             category: row.get(4)?,
-//         //         })
-//         //     })?
-//         //     .collect()
-//         // }
-//         //
-//     }
-//
-//     // make sure all the function signatures look good
-//
-//     pub fn edit_task(
-//         &self,
-//         id: i32,
-//         finish: Option<bool>,
-//         due_date: Option<&str>,
-//         category: Option<&str>,
-//         info: Option<&str>,
-//         remove: bool,
-//     ) -> Result<(), Box<dyn std::error::Error>> {
-//         Ok(())
-//         // ) -> Result<(), Box<dyn std::error::Error>> {
-//         //     Ok(())
-//         //
-//         //     //     // insert the category if it is not there
-//         //     //
-//         //     //     if let Some(f) = finish {
-//         //     //         conn.execute(
-//         //     //             "UPDATE tasks SET done = ?1 WHERE id = ?2",
-//         //     //             rusqlite::params![&f, &id],
-//         //     //         )?;
-//         //     //     }
-//         //     //
-//         //     //     if let Some(i) = info {
-//         //     //         conn.execute(
-//         //     //             "UPDATE tasks SET info = ?1 WHERE id = ?2",
-//         //     //             rusqlite::params![i, &id],
-//         //     //         )?;
-//         //     //     }
-//         //     //
-//         //     //     if let Some(d) = due_date {
-//         //     //         let fmt_date = format_date(d, &Local::now().date_naive())?;
-//         //     //         conn.execute(
-//         //     //             "UPDATE tasks SET due_date = ?1 WHERE id = ?2",
-//         //     //             rusqlite::params![&fmt_date, &id],
-//         //     //         )?;
-//         //     //     }
-//         //     //     if let Some(c) = category {
-//         //     //         conn.execute(
-//         //     //             r#"
-//         //     //         UPDATE tasks
-//         //     //         SET category = (SELECT id FROM categories WHERE name = ?1)
-//         //     //         WHERE id = ?2
-//         //     //         "#,
-//         //     //             rusqlite::params![c, &id],
-//         //     //         )?;
-//         //     //     }
-//         //     //
-//         //     //     if remove {
-//         //     //         conn.execute("DELETE FROM tasks WHERE id = ?1", [&id])?;
-//         //     //     }
-//         //     //
-//         //     //     Ok(())
-//         // }
-//         //
-//     }
-// }
-//
-// // test good case
-// // test bad case
-// // test today
-// // tests - and /
-// // test tmr
-// // tests for next month
-// // tests for next year
-// // test absolute
-// // tests for 1->4 years like each digit
-//
-// #[cfg(test)]
-// mod tests {
-//     use super::*;
-//
-//     fn test_date() -> NaiveDate {
-//         NaiveDate::from_ymd_opt(2025, 9, 20).unwrap()
-//     }
-//
-//     fn assert_fmt(input: &str, expected: &str) {
-//         assert_eq!(Ok(expected.to_string()), format_date(input, &test_date()));
-//     }
-//
-//     #[test]
-//     fn test_format_date_identity() {
-//         assert_fmt("2025-09-20", "2025-09-20")
-//     }
-//
-//     #[test]
-//     fn test_format_date_trim() {
-//         assert_fmt("\t  2025-09-20 \n ", "2025-09-20")
-//     }
-//
-//     #[test]
-//     fn test_format_date_delimeter() {
-//         assert_fmt("2025/09/20", "2025-09-20")
-//     }
-//
-//     // #[test]
-//     // fn test_format_date_one_part() {
-//     //     assert!(format_date("20", &test_date()).is_ok())
-//     // }
-//     //
-//     // #[test]
-//     // fn test_format_date_two_part() {
-//     //     assert!(format_date("9-20", &test_date()).is_ok())
-//     // }
-//     //
-//     // #[test]
-//     // fn test_format_date_four_part() {
-//     //     assert_eq!(
-//     //         Err("bad number of parts"),
-//     //         format_date("20-20-20-20", &test_date()),
-//     //     )
-//     // }
-// }
-//
-//
 // // //look into chrono for this
 // // //read env for time zones change time to my time
-// // // include env arguments for customazation or should i have
-// // // a lua/toml file
-// //
 // //
-// // test all these functions
-// // cargo doc with tests also make a custom connection that is a Connection
-// // under the hood, only can make it with tables
-// // make all the task methods on them
-// // pub struct Task {
-// //     id: u32,
-// //     info: String,
-// //     done: bool,
-// //     due_date: Option<String>,
-// //     category: Option<String>,
-// // }
-// //
-// // impl Display for Task {
-// //     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-// //         write!(
-// //             f,
-// //             "{}. {} | {} | {:?} | {:?}",
-// //             self.id, self.info, self.done, self.due_date, self.category,
-// //         )
-// //     }
-// // }
 // //
 // // test good case and bad case when entering into the db
-// // make custom err type
 // //rust cli to a supabase db
 // // makee sujre to add category
 //
 // write alot of documentation
-//
-//
-//
-//
-// pub fn format_date(date: &str, today: &NaiveDate) -> Result<String, &'static str> {
-//     let cleaned = date.trim().replace("/", "-");
-//     let parts: Vec<_> = cleaned.trim().split('-').collect();
-//
-//     // let parse_part = |part: &str, msg| part.parse::<u32>().map_err(|_| msg);
-//     // let (y, m, d) = match parts.len() {
-//     //     3 => (
-//     //         Some(parse_part(parts[0], "Y-M-D invalid year")?),
-//     //         Some(parse_part(parts[1], "Y-M-D invalid month")?),
-//     //         Some(parse_part(parts[2], "Y-M-D invalid day")?),
-//     //     ),
-//     //     2 => (
-//     //         None,
-//     //         Some(parse_part(parts[0], "M-D invalid month")?),
-//     //         Some(parse_part(parts[1], "M-D invalid day")?),
-//     //     ),
-//     //     1 => (None, None, Some(parse_part(parts[0], "D invalid day")?)),
-//     //     _ => return Err("bad number of parts"),
-//     // };
-//     //
-//     Ok(cleaned)
-//
-//     // fn make_date(y: i32, m: u32, d: u32) -> Result<String, &'static str> {
-//     //     if let Some(date) = NaiveDate::from_ymd_opt(y, m, d) {
-//     //         Ok(date.format("%Y-%m-%d").to_string())
-//     //     } else {
-//     //         Err("unable to parse date")
-//     //     }
-//     // }
-//     //
-//     // match (y, m, d) {
-//     //     // wont work past 2100 :(, will fix then
-//     //     (Some(year), Some(month), Some(day)) => make_date(
-//     //         (if year < 100 { year + 2000 } else { year }) as i32,
-//     //         month,
-//     //         day,
-//     //     ),
-//     //     (None, Some(month), Some(day)) => {
-//     //         if month > today.month() || (month == today.month() && day >= today.day()) {
-//     //             make_date(today.year(), month, day)
-//     //         } else {
-//     //             make_date(today.year() + 1, month, day)
-//     //         }
-//     //     }
-//     //     (None, None, Some(day)) => {
-//     //         if day >= today.day() {
-//     //             make_date(today.year(), today.month(), day)
-//     //         } else {
-//     //             let today_month = today.month();
-//     //             let (new_year, new_month) = if today_month == 12 {
-//     //                 (today.year() + 1, 1)
-//     //             } else {
-//     //                 (today.year(), today_month + 1)
-//     //             };
-//     //             make_date(new_year, new_month, day)
-//     //         }
-//     //     }
-//     //     _ => Err("cannot parse the date"),
-//     // }
-// }
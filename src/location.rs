@@ -0,0 +1,334 @@
+use crate::Config;
+use std::env;
+use std::ffi::OsString;
+use std::fmt::{self, Display, Formatter};
+use std::path::{Path, PathBuf};
+
+/// Environment variable naming the database file
+pub const DB_ENV: &str = "TODO_DB";
+
+/// Project lists live in `.todo/todo.db`, or `.todo.db` for a single file
+pub const LOCAL_DIR: &str = ".todo";
+pub const LOCAL_FILE: &str = ".todo.db";
+const DB_FILE: &str = "todo.db";
+
+/// Which setting picked the database file
+#[derive(Debug, Clone, PartialEq)]
+pub enum DbSource {
+    Flag,
+    Env,
+    /// Project list found in this directory or one of its parents
+    Local(PathBuf),
+    /// `db_path` in the config file at this path
+    Config(PathBuf),
+    DataDir,
+    /// No home directory to put the data directory in
+    WorkingDir,
+}
+
+impl Display for DbSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Flag => write!(f, "set by --db"),
+            Self::Env => write!(f, "set by {DB_ENV}"),
+            Self::Local(dir) => write!(f, "project list in {}", dir.display()),
+            Self::Config(path) => write!(f, "set by db_path in {}", path.display()),
+            Self::DataDir => write!(f, "default data directory"),
+            Self::WorkingDir => write!(f, "no home directory, using the current directory"),
+        }
+    }
+}
+
+/// The database file to open and why it was chosen
+#[derive(Debug, Clone, PartialEq)]
+pub struct DbLocation {
+    pub path: PathBuf,
+    pub source: DbSource,
+}
+
+impl DbLocation {
+    /// Picks the database from `--db`, then `TODO_DB`, then a project list in
+    /// the current directory or its parents, then the global database
+    pub fn resolve(flag: Option<&Path>, config: &Config) -> Self {
+        let cwd = env::current_dir().ok();
+        Self::resolve_with(flag, config, cwd.as_deref(), |key| env::var_os(key))
+    }
+
+    /// The database used outside of any project, from the config file or
+    /// `$XDG_DATA_HOME/todo/todo.db`
+    pub fn global(config: &Config) -> Self {
+        Self::global_with(config, |key| env::var_os(key))
+    }
+
+    /// Whether this is a project list rather than a global database
+    pub fn is_local(&self) -> bool {
+        matches!(self.source, DbSource::Local(_))
+    }
+
+    fn resolve_with(
+        flag: Option<&Path>,
+        config: &Config,
+        cwd: Option<&Path>,
+        env: impl Fn(&str) -> Option<OsString>,
+    ) -> Self {
+        if let Some(path) = flag {
+            return Self {
+                path: path.to_path_buf(),
+                source: DbSource::Flag,
+            };
+        }
+
+        if let Some(path) = env(DB_ENV).filter(|path| !path.is_empty()) {
+            return Self {
+                path: PathBuf::from(path),
+                source: DbSource::Env,
+            };
+        }
+
+        if let Some(location) = cwd.and_then(find_local) {
+            return location;
+        }
+
+        Self::global_with(config, env)
+    }
+
+    fn global_with(config: &Config, env: impl Fn(&str) -> Option<OsString>) -> Self {
+        if let (Some(path), Some(config_path)) = (&config.db_path, &config.path) {
+            let path = expand_home(path, &env);
+            let path = match config_path.parent() {
+                Some(dir) if path.is_relative() => dir.join(path),
+                _ => path,
+            };
+            return Self {
+                path,
+                source: DbSource::Config(config_path.clone()),
+            };
+        }
+
+        match xdg_dir("XDG_DATA_HOME", ".local/share", &env) {
+            Some(dir) => Self {
+                path: dir.join("todo").join(DB_FILE),
+                source: DbSource::DataDir,
+            },
+            None => Self {
+                path: PathBuf::from(DB_FILE),
+                source: DbSource::WorkingDir,
+            },
+        }
+    }
+}
+
+/// Walks up from `start` looking for a project list, the way git finds `.git`
+pub fn find_local(start: &Path) -> Option<DbLocation> {
+    start.ancestors().find_map(|dir| {
+        [dir.join(LOCAL_DIR).join(DB_FILE), dir.join(LOCAL_FILE)]
+            .into_iter()
+            .find(|path| path.is_file())
+            .map(|path| DbLocation {
+                path,
+                source: DbSource::Local(dir.to_path_buf()),
+            })
+    })
+}
+
+/// Where `todo init` creates a project list in `dir`
+pub fn local_path(dir: &Path) -> PathBuf {
+    dir.join(LOCAL_DIR).join(DB_FILE)
+}
+
+/// `$XDG_CONFIG_HOME/todo/config.toml`, falling back to `~/.config`
+pub fn config_file() -> Option<PathBuf> {
+    xdg_dir("XDG_CONFIG_HOME", ".config", &|key| env::var_os(key))
+        .map(|dir| dir.join("todo").join("config.toml"))
+}
+
+/// Reads an XDG base directory, ignoring relative values as the spec requires
+fn xdg_dir(
+    var: &str,
+    home_fallback: &str,
+    env: &impl Fn(&str) -> Option<OsString>,
+) -> Option<PathBuf> {
+    env(var)
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| home(env).map(|home| home.join(home_fallback)))
+}
+
+fn home(env: &impl Fn(&str) -> Option<OsString>) -> Option<PathBuf> {
+    env("HOME")
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Replaces a leading `~` with the home directory
+fn expand_home(path: &Path, env: &impl Fn(&str) -> Option<OsString>) -> PathBuf {
+    match (path.strip_prefix("~"), home(env)) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::fs;
+
+    fn resolve(flag: Option<&str>, config: &Config, vars: &[(&str, &str)]) -> DbLocation {
+        let vars: HashMap<_, _> = vars.iter().copied().collect();
+        DbLocation::resolve_with(flag.map(Path::new), config, None, |key| {
+            vars.get(key).map(OsString::from)
+        })
+    }
+
+    /// A fresh directory tree under the system temp dir for one test
+    fn temp_tree(name: &str) -> PathBuf {
+        let root = env::temp_dir().join(format!("todo-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("a/b/c")).unwrap();
+        root
+    }
+
+    fn config(db_path: &str) -> Config {
+        Config {
+            db_path: Some(PathBuf::from(db_path)),
+            path: Some(PathBuf::from("/home/me/.config/todo/config.toml")),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_resolve_order() {
+        let vars = [
+            ("TODO_DB", "/env/todo.db"),
+            ("HOME", "/home/me"),
+            ("XDG_DATA_HOME", "/data"),
+        ];
+        let config = config("/config/todo.db");
+
+        assert_eq!(
+            resolve(Some("flag.db"), &config, &vars),
+            DbLocation {
+                path: PathBuf::from("flag.db"),
+                source: DbSource::Flag
+            }
+        );
+        assert_eq!(
+            resolve(None, &config, &vars),
+            DbLocation {
+                path: PathBuf::from("/env/todo.db"),
+                source: DbSource::Env
+            }
+        );
+        assert_eq!(
+            resolve(None, &config, &vars[1..]),
+            DbLocation {
+                path: PathBuf::from("/config/todo.db"),
+                source: DbSource::Config(config.path.clone().unwrap())
+            }
+        );
+        assert_eq!(
+            resolve(None, &Config::default(), &vars[1..]),
+            DbLocation {
+                path: PathBuf::from("/data/todo/todo.db"),
+                source: DbSource::DataDir
+            }
+        );
+    }
+
+    #[test]
+    fn test_resolve_fallbacks() {
+        // empty env var is ignored
+        assert_eq!(
+            resolve(None, &Config::default(), &[("TODO_DB", ""), ("HOME", "/h")]).source,
+            DbSource::DataDir
+        );
+        // relative XDG_DATA_HOME is ignored
+        assert_eq!(
+            resolve(
+                None,
+                &Config::default(),
+                &[("XDG_DATA_HOME", "data"), ("HOME", "/h")]
+            )
+            .path,
+            PathBuf::from("/h/.local/share/todo/todo.db")
+        );
+        assert_eq!(
+            resolve(None, &Config::default(), &[]),
+            DbLocation {
+                path: PathBuf::from("todo.db"),
+                source: DbSource::WorkingDir
+            }
+        );
+    }
+
+    #[test]
+    fn test_resolve_config_paths() {
+        let vars = [("HOME", "/home/me")];
+        assert_eq!(
+            resolve(None, &config("~/notes/todo.db"), &vars).path,
+            PathBuf::from("/home/me/notes/todo.db")
+        );
+        assert_eq!(
+            resolve(None, &config("lists/todo.db"), &vars).path,
+            PathBuf::from("/home/me/.config/todo/lists/todo.db")
+        );
+    }
+
+    #[test]
+    fn test_find_local() {
+        let root = temp_tree("find-local");
+        let deep = root.join("a/b/c");
+        assert_eq!(find_local(&deep), None);
+
+        fs::write(root.join(LOCAL_FILE), "").unwrap();
+        assert_eq!(
+            find_local(&deep),
+            Some(DbLocation {
+                path: root.join(LOCAL_FILE),
+                source: DbSource::Local(root.clone())
+            })
+        );
+
+        // the closest list wins, .todo/todo.db before .todo.db
+        fs::create_dir_all(root.join("a/b").join(LOCAL_DIR)).unwrap();
+        fs::write(local_path(&root.join("a/b")), "").unwrap();
+        fs::write(root.join("a/b").join(LOCAL_FILE), "").unwrap();
+        assert_eq!(
+            find_local(&deep).unwrap().path,
+            local_path(&root.join("a/b"))
+        );
+
+        // lists below the starting directory are not found
+        assert_eq!(
+            find_local(&root.join("a")).unwrap().path,
+            root.join(LOCAL_FILE)
+        );
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_local() {
+        let root = temp_tree("resolve-local");
+        fs::write(root.join(LOCAL_FILE), "").unwrap();
+        let vars: HashMap<_, _> = [("HOME", "/home/me")].into_iter().collect();
+        let env = |key: &str| vars.get(key).map(OsString::from);
+        let cwd = root.join("a/b");
+
+        let local = DbLocation::resolve_with(None, &Config::default(), Some(&cwd), env);
+        assert!(local.is_local());
+        assert_eq!(local.path, root.join(LOCAL_FILE));
+
+        // explicit choices still win over the project list
+        let flag =
+            DbLocation::resolve_with(Some(Path::new("x.db")), &Config::default(), Some(&cwd), env);
+        assert_eq!(flag.source, DbSource::Flag);
+
+        let global = DbLocation::global_with(&Config::default(), env);
+        assert_eq!(global.source, DbSource::DataDir);
+        assert!(!global.is_local());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
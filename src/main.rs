@@ -1,16 +1,162 @@
-use chrono::Local;
+use chrono::{Local, NaiveDate};
 use clap::{Parser, Subcommand};
-use todo::Conn;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+use todo::location;
+use todo::migrations::SchemaStatus;
+use todo::{
+    Action, Config, Conn, DbLocation, Error, Export, Filter, IdRange, ListOptions, NewTask,
+    OnConflict, Priority, Recurrence, SortBy, TagFilter, Task, TaskEdit, describe_tasks,
+};
+
+/// The database could not be opened, read or written
+const EXIT_STORAGE: i32 = 1;
+/// Task info, category or tag name, due date, search or filter was rejected
+const EXIT_INVALID: i32 = 3;
+/// No task has the given id, no category the given name, or there is
+/// nothing to undo or redo
+const EXIT_NOT_FOUND: i32 = 4;
+/// The category already exists, or the change conflicts with subtasks,
+/// dependencies or later operations
+const EXIT_CONFLICT: i32 = 5;
+/// The user did not confirm a destructive operation
+const EXIT_CANCELLED: i32 = 6;
+/// The database was written by a newer version of todo
+const EXIT_SCHEMA: i32 = 7;
+/// The config file could not be parsed
+const EXIT_CONFIG: i32 = 8;
+
+/// Name of an exit code in the error objects of `--output json`
+fn exit_kind(code: i32) -> &'static str {
+    match code {
+        EXIT_STORAGE => "storage",
+        EXIT_INVALID => "invalid",
+        EXIT_NOT_FOUND => "not_found",
+        EXIT_CONFLICT => "conflict",
+        EXIT_CANCELLED => "cancelled",
+        EXIT_SCHEMA => "schema",
+        EXIT_CONFIG => "config",
+        _ => "error",
+    }
+}
+
+const EXIT_CODES_HELP: &str = "\
+Exit codes:
+  0  success
+  1  database could not be opened, read or written
+  2  invalid command line usage
+  3  invalid task info, category or tag name, due date, search, filter
+     or backup
+  4  no task with the given id or category with the given name, or
+     nothing to undo or redo
+  5  category already exists, task has open subtasks or prerequisites,
+     a dependency would make a cycle, or a later operation is in the way
+     of undo or redo
+  6  removal was not confirmed
+  7  database or backup was created by a newer version of todo
+  8  config file is invalid";
 
 ///A command line todo app
 #[derive(Debug, Parser)]
 #[command(name = "todo")]
 #[command(about = "A command line todo app", long_about = None)]
+#[command(after_help = EXIT_CODES_HELP)]
 struct Cli {
+    /// Database file, overrides TODO_DB, project lists and the config file
+    #[arg(long, global = true, value_name = "PATH")]
+    db: Option<PathBuf>,
+
+    /// Use the global database even inside a project with its own list
+    #[arg(short, long, global = true, action = clap::ArgAction::SetTrue)]
+    global: bool,
+
+    /// Print results as text, a JSON document or one JSON object per line
+    #[arg(long, global = true, value_name = "FORMAT", default_value = "text")]
+    output: Output,
+
     #[command(subcommand)]
     command: Commands,
 }
 
+/// How results are printed
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum Output {
+    #[default]
+    Text,
+    Json,
+    Ndjson,
+}
+
+impl FromStr for Output {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            "ndjson" => Ok(Self::Ndjson),
+            _ => Err(format!(
+                "'{s}' is not an output format, use text, json or ndjson"
+            )),
+        }
+    }
+}
+
+impl Output {
+    fn is_text(self) -> bool {
+        self == Self::Text
+    }
+
+    /// Prints a single result
+    fn one(self, value: &impl Serialize) {
+        let text = match self {
+            Self::Json => serde_json::to_string_pretty(value),
+            _ => serde_json::to_string(value),
+        };
+        println!("{}", text.expect("results serialize to JSON"));
+    }
+
+    /// Prints results as a JSON array, or one per line for ndjson
+    fn many<T: Serialize>(self, values: &[T]) {
+        match self {
+            Self::Ndjson => values.iter().for_each(|value| self.one(value)),
+            _ => self.one(&values),
+        }
+    }
+
+    /// Reports a failure on stderr and exits with `code`, text output
+    /// leads with what was being done
+    fn fail(self, code: i32, doing: &str, message: &str) -> ! {
+        match self {
+            Self::Text => eprintln!("{doing}: {message}"),
+            _ => eprintln!(
+                "{}",
+                json!({ "error": { "code": code, "kind": exit_kind(code), "message": message } })
+            ),
+        }
+        std::process::exit(code)
+    }
+}
+
+/// A task before and after `edit`, removed tasks have no after
+#[derive(Serialize)]
+struct Changed<'a> {
+    before: &'a Task,
+    after: Option<&'a Task>,
+}
+
+/// A task of `list --all-lists` with the list it is in
+#[derive(Serialize)]
+struct Listed<'a> {
+    list: &'a str,
+    #[serde(flatten)]
+    task: &'a Task,
+}
+
 #[derive(Debug, Subcommand)]
 enum Commands {
     /// Add todo list item
@@ -23,16 +169,121 @@ enum Commands {
         #[arg(short, long)]
         category: Option<String>,
 
-        /// Due date: YYYY-MM-DD, MM-DD, DD (slashes allowed, leading zeros optional)
+        /// Due date: YYYY-MM-DD, MM-DD, DD (slashes allowed, leading zeros optional),
+        /// today, tomorrow, fri, next tue, +3d, +2w, +1m, eow, eom, eoq or eoy
         #[arg(short, long)]
         due_date: Option<String>,
+
+        /// Priority: high, medium or low (h/m/l or 1-3), medium by default
+        #[arg(short, long)]
+        priority: Option<Priority>,
+
+        /// Tag the task, can be repeated
+        #[arg(short, long)]
+        tag: Vec<String>,
+
+        /// Make this a subtask of the task with this id
+        #[arg(long, value_name = "ID")]
+        parent: Option<i32>,
+
+        /// Add the next instance when the task is done: daily, weekly on
+        /// mon,thu, monthly on 15, every 2 weeks, yearly or an RRULE like
+        /// FREQ=MONTHLY;BYMONTHDAY=-1, optionally followed by until
+        /// YYYY-MM-DD or count N
+        #[arg(long, value_name = "RULE")]
+        repeat: Option<Recurrence>,
+
+        /// Count the next due date from the day the task is finished
+        /// instead of its due date
+        #[arg(long, requires = "repeat", action = clap::ArgAction::SetTrue)]
+        from_completion: bool,
     },
 
     /// List all todo items
     List {
-        /// Sort By Category
+        /// Group by category as a tree, or only show this category and the
+        /// ones below it, e.g. work or work/clientA
+        #[arg(short, long, num_args = 0..=1, value_name = "CATEGORY")]
+        category: Option<Option<String>>,
+
+        /// Include Finshed Tasks
+        #[arg(short, long, action = clap::ArgAction::SetTrue)]
+        include_done: bool,
+
+        /// Show the project list and the global list together
+        #[arg(short, long, action = clap::ArgAction::SetTrue)]
+        all_lists: bool,
+
+        /// Only show open tasks that are not waiting on another task
+        #[arg(short, long, action = clap::ArgAction::SetTrue)]
+        ready: bool,
+
+        /// Only show tasks of this priority, can be repeated
+        #[arg(short, long)]
+        priority: Vec<Priority>,
+
+        /// Only show tasks with this tag, repeat to require all of them
+        #[arg(short, long)]
+        tag: Vec<String>,
+
+        /// Only show tasks with at least one of these tags, can be repeated
+        #[arg(long, value_name = "TAG")]
+        any_tag: Vec<String>,
+
+        /// Hide tasks with this tag, can be repeated
+        #[arg(long, value_name = "TAG")]
+        not_tag: Vec<String>,
+
+        /// Only show tasks added on or after this date, e.g. 2025-09-01 or -7d
+        #[arg(long, value_name = "DATE", allow_hyphen_values = true)]
+        created_since: Option<String>,
+
+        /// Only show tasks finished between these dates, both included
+        #[arg(long, num_args = 2, value_names = ["FROM", "TO"], allow_hyphen_values = true)]
+        completed_between: Vec<String>,
+
+        /// Order by priority (default), or newest first by created, modified
+        /// or completed time
+        #[arg(short, long)]
+        sort: Option<SortBy>,
+
+        /// Only show tasks matching this search, see `todo search --help`
+        #[arg(long, value_name = "QUERY")]
+        search: Option<String>,
+
+        /// Only show tasks matching this expression, see `todo count --help`
+        #[arg(short, long, value_name = "EXPR")]
+        filter: Option<String>,
+
+        /// Show the list as it was at the end of this date, e.g. 2026-09-01
+        /// or -14d
+        #[arg(long, value_name = "DATE", allow_hyphen_values = true)]
+        as_of: Option<String>,
+    },
+
+    /// Print how many tasks match a filter
+    Count {
+        /// e.g. 'due:<eow and (cat:work or tag:urgent) and not done'. Fields
+        /// are due, created, modified and completed (optionally prefixed by
+        /// <, <=, > or >=), cat, tag, pri, id (also with <, >), parent and
+        /// text, with none for an empty field; done, open, blocked, ready,
+        /// overdue and repeating stand alone. Combine with and, or, not and
+        /// parentheses, quote values with spaces. Finished tasks only count
+        /// when the filter mentions them.
+        #[arg(short, long, value_name = "EXPR")]
+        filter: Option<String>,
+
+        /// Include Finshed Tasks
         #[arg(short, long, action = clap::ArgAction::SetTrue)]
-        category: bool,
+        include_done: bool,
+    },
+
+    /// Find tasks by the words in their info, best match first
+    #[command(arg_required_else_help = true)]
+    Search {
+        /// Words must all appear, "a phrase" in order, prefix* matches the
+        /// start of a word, and AND, OR, NOT and parentheses combine them
+        query: String,
 
         /// Include Finshed Tasks
         #[arg(short, long, action = clap::ArgAction::SetTrue)]
@@ -42,14 +293,20 @@ enum Commands {
     /// Edit todo list item
     #[command(arg_required_else_help = true)]
     Edit {
-        /// Id of task to finish
-        id: i32,
+        /// Ids of the tasks to change, or ranges of them like 3-7
+        #[arg(required_unless_present = "filter")]
+        ids: Vec<IdRange>,
+
+        /// Change every task matching this expression instead, see `todo
+        /// count --help`
+        #[arg(long, value_name = "EXPR", conflicts_with = "ids")]
+        filter: Option<String>,
 
         /// Set if problems is done with true or false
         #[arg(short, long)]
         finish: Option<bool>,
 
-        /// Set problem due date
+        /// Set problem due date, accepts the same formats as add
         #[arg(short, long)]
         due_date: Option<String>,
 
@@ -61,40 +318,1163 @@ enum Commands {
         #[arg(short, long)]
         info: Option<String>,
 
-        /// Remove problem (use with caution, must write 'delete')
-        #[arg(short, long, action = clap::ArgAction::SetTrue)]
+        /// Set problem priority: high, medium or low
+        #[arg(short, long)]
+        priority: Option<Priority>,
+
+        /// Add a tag, can be repeated
+        #[arg(short, long)]
+        tag: Vec<String>,
+
+        /// Remove a tag, can be repeated
+        #[arg(short, long, value_name = "TAG")]
+        untag: Vec<String>,
+
+        /// Move problem and its subtasks to the trash (must write 'delete')
+        #[arg(
+            short,
+            long,
+            action = clap::ArgAction::SetTrue,
+            conflicts_with_all = [
+                "finish", "due_date", "category", "info", "priority", "tag", "untag", "repeat",
+                "from_completion", "no_repeat", "force",
+            ],
+        )]
         remove: bool,
+
+        /// Show what would change without changing anything
+        #[arg(short = 'n', long, action = clap::ArgAction::SetTrue)]
+        dry_run: bool,
+
+        /// Remove without asking to type 'delete', for scripts
+        #[arg(short, long, action = clap::ArgAction::SetTrue)]
+        yes: bool,
+
+        /// Finish open subtasks too instead of asking
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        force: bool,
+
+        /// Make the task repeat, accepts the same rules as add
+        #[arg(long, value_name = "RULE")]
+        repeat: Option<Recurrence>,
+
+        /// Count the next due date from the day the task is finished
+        #[arg(long, requires = "repeat", action = clap::ArgAction::SetTrue)]
+        from_completion: bool,
+
+        /// Stop the task from repeating
+        #[arg(long, conflicts_with = "repeat", action = clap::ArgAction::SetTrue)]
+        no_repeat: bool,
+    },
+
+    /// List tags with their number of open and finished tasks
+    Tags,
+
+    /// Show who changed a task and how, or every change of the last days
+    Log {
+        /// Id of the task, deleted ones included
+        id: Option<i32>,
+
+        /// Every change to any task or category this recent, e.g. 7d
+        #[arg(long, value_name = "DAYS", value_parser = parse_days, conflicts_with = "id")]
+        since: Option<u32>,
+    },
+
+    /// Revert the last change, or the one with the given id
+    Undo {
+        /// Id of the operation, see --list
+        id: Option<i64>,
+
+        /// Show recent operations instead
+        #[arg(short, long, conflicts_with = "id", action = clap::ArgAction::SetTrue)]
+        list: bool,
+
+        /// Undo even if later operations changed the same tasks since
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        force: bool,
+    },
+
+    /// Apply the last undone change again, or the one with the given id
+    Redo {
+        /// Id of the operation, see `undo --list`
+        id: Option<i64>,
+
+        /// Redo even if later operations changed the same tasks since
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        force: bool,
+    },
+
+    /// List, restore and empty removed tasks
+    #[command(arg_required_else_help = true)]
+    Trash {
+        #[command(subcommand)]
+        command: TrashCommands,
+    },
+
+    /// Make tasks wait on each other
+    #[command(arg_required_else_help = true)]
+    Dep {
+        #[command(subcommand)]
+        command: DepCommands,
+    },
+
+    /// List, create, rename, merge and delete categories
+    #[command(arg_required_else_help = true)]
+    Category {
+        #[command(subcommand)]
+        command: CategoryCommands,
+    },
+
+    /// Print every task, category and tag, e.g. `todo export > backup.json`
+    Export {
+        /// Format to print, only json for now
+        #[arg(long, default_value = "json", value_parser = ["json"])]
+        format: String,
+
+        /// Only export tasks matching this expression, finished and trashed
+        /// ones included, see `todo count --help`
+        #[arg(short, long, value_name = "EXPR")]
+        filter: Option<String>,
+    },
+
+    /// Add the tasks, categories and tags of an export to this list
+    #[command(arg_required_else_help = true)]
+    Import {
+        /// File written by `todo export`
+        file: PathBuf,
+
+        /// What to do with tasks the list already has: skip, overwrite or
+        /// duplicate them
+        #[arg(long, value_name = "STRATEGY", default_value = "skip")]
+        on_conflict: OnConflict,
+    },
+
+    /// Create a project list in .todo/todo.db of the current directory
+    Init,
+
+    /// Read and change settings in config.toml
+    #[command(arg_required_else_help = true)]
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+
+    /// Manage the database file
+    #[command(arg_required_else_help = true)]
+    Db {
+        #[command(subcommand)]
+        command: DbCommands,
     },
 }
 
+#[derive(Debug, Subcommand)]
+enum DepCommands {
+    /// Make a task a prerequisite of another
+    #[command(arg_required_else_help = true)]
+    Add {
+        /// Id of the task that has to be done first
+        id: i32,
+
+        /// Id of the task that waits on it
+        #[arg(short, long, value_name = "ID")]
+        blocks: i32,
+    },
+
+    /// Stop a task from waiting on another
+    #[command(arg_required_else_help = true)]
+    Remove {
+        id: i32,
+
+        #[arg(short, long, value_name = "ID")]
+        blocks: i32,
+    },
+
+    /// List every dependency
+    List,
+}
+
+#[derive(Debug, Subcommand)]
+enum TrashCommands {
+    /// List removed tasks, most recent first
+    List,
+
+    /// Bring a task back along with the subtasks removed with it
+    #[command(arg_required_else_help = true)]
+    Restore { id: i32 },
+
+    /// Delete trashed tasks for good
+    Empty {
+        /// Only tasks removed at least this long ago, e.g. 30d
+        #[arg(long, value_name = "DAYS", value_parser = parse_days, default_value = "0d")]
+        older_than: u32,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum CategoryCommands {
+    /// List categories with their number of open and finished tasks
+    List {
+        /// Only show categories no task uses
+        #[arg(short, long, action = clap::ArgAction::SetTrue)]
+        orphaned: bool,
+    },
+
+    /// Create an empty category
+    #[command(arg_required_else_help = true)]
+    Add { name: String },
+
+    /// Rename a category, its tasks move along
+    #[command(arg_required_else_help = true)]
+    Rename { from: String, to: String },
+
+    /// Move every task of one category into another and delete the first
+    #[command(arg_required_else_help = true)]
+    Merge { from: String, into: String },
+
+    /// Delete a category, its tasks are left without one unless reassigned
+    #[command(arg_required_else_help = true)]
+    Delete {
+        name: String,
+
+        /// Move the tasks to this category instead
+        #[arg(short, long, value_name = "CATEGORY")]
+        reassign: Option<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ConfigCommands {
+    /// Print a setting, or every setting in a table like `aliases`
+    #[command(arg_required_else_help = true)]
+    Get {
+        /// Dotted key, e.g. list.category or dates.week_start
+        key: String,
+    },
+
+    /// Change a setting, creating the config file if needed
+    #[command(arg_required_else_help = true)]
+    Set {
+        /// Dotted key, e.g. aliases.t or categories.work.due_date
+        key: String,
+
+        /// New value, bare words are stored as text
+        value: String,
+    },
+
+    /// Print every setting, including defaults
+    List,
+}
+
+#[derive(Debug, Subcommand)]
+enum DbCommands {
+    /// Upgrade the database schema to the latest version
+    Migrate {
+        /// Only show the schema version and pending migrations
+        #[arg(short, long, action = clap::ArgAction::SetTrue)]
+        status: bool,
+    },
+
+    /// Print which database file is used and why
+    Path,
+}
+
 fn main() {
+    // a broken config has no aliases, and `config set` still runs so it can
+    // repair it
+    let config = Config::load_default();
+    let args = std::env::args().collect();
+    let cli = Cli::parse_from(match &config {
+        Ok(config) => config.expand_alias(args),
+        Err(_) => args,
+    });
+    let output = cli.output;
+    let config = match (config, &cli.command) {
+        (Ok(config), _) => config,
+        (
+            Err(_),
+            Commands::Config {
+                command: ConfigCommands::Set { .. },
+            },
+        ) => Config::default(),
+        (Err(err), _) => output.fail(exit_code(&err), "Could not load config", &err.to_string()),
+    };
+
+    // an export is meant to be redirected to a file as it is
+    let banners = output.is_text() && !matches!(cli.command, Commands::Export { .. });
+
     let today = Local::now().date_naive();
-    println!("Welcome to todo: {today}");
+    if banners {
+        println!("Welcome to todo: {today}");
+    }
 
-    let conn = Conn::build("./todo.db").unwrap_or_else(|err| {
-        eprintln!("Could not acess db: {err}");
-        std::process::exit(1)
-    });
+    let location = match (&cli.db, cli.global) {
+        (None, true) => DbLocation::global(&config),
+        (db, _) => DbLocation::resolve(db.as_deref(), &config),
+    };
+
+    let result = match cli.command {
+        Commands::Db { command } => run_db(command, &location, output),
+        Commands::Init => init(output),
+        Commands::Config { command } => run_config(command, &config, output),
+        command => {
+            let conn = Conn::build(&location.path)
+                .map(|conn| conn.strict_categories(config.strict_categories))
+                .unwrap_or_else(|err| {
+                    output.fail(exit_code(&err), "Could not acess db", &err.to_string())
+                });
+            run(command, &conn, &today, &location, &config, output)
+        }
+    };
 
-    match Cli::parse().command {
+    if let Err(err) = result {
+        output.fail(exit_code(&err), "Operation failed", &err.to_string())
+    }
+
+    if banners {
+        println!("Operation was a Success")
+    }
+}
+
+fn run(
+    command: Commands,
+    conn: &Conn,
+    today: &NaiveDate,
+    location: &DbLocation,
+    config: &Config,
+    output: Output,
+) -> todo::Result<()> {
+    let parse_date = |date: Option<String>| {
+        date.map(|d| todo::format_date(&d, today, &config.dates))
+            .transpose()
+    };
+    let color = config.theme.use_color();
+    let show = |task: &Task| config.theme.render(task, today, color);
+    let heading = |name: &str| match color {
+        true => config.theme.category.paint(name),
+        false => name.to_string(),
+    };
+
+    match command {
         Commands::Add {
             task,
             category,
             due_date,
-        } => println!("Add command"),
+            priority,
+            tag,
+            parent,
+            repeat,
+            from_completion,
+        } => {
+            let defaults = category
+                .as_deref()
+                .and_then(|name| config.categories.get(name));
+            let due_date = due_date.or_else(|| defaults?.due_date.clone());
+            let priority = priority.or_else(|| defaults?.priority);
+            let task = conn.add_task(&NewTask {
+                info: task,
+                category,
+                due_date: parse_date(due_date)?,
+                priority: priority.unwrap_or_default(),
+                tags: tag,
+                parent,
+                recurrence: repeat.map(|rule| Recurrence {
+                    from_completion: rule.from_completion || from_completion,
+                    ..rule
+                }),
+            })?;
+            match output {
+                Output::Text => println!("{}", show(&task)),
+                _ => output.one(&task),
+            }
+        }
         Commands::List {
             category,
             include_done,
-        } => println!("List command"),
+            all_lists,
+            ready,
+            priority,
+            tag,
+            any_tag,
+            not_tag,
+            created_since,
+            completed_between,
+            sort,
+            search,
+            filter,
+            as_of,
+        } => {
+            let as_of = parse_date(as_of)?;
+            let completed_between = match completed_between.as_slice() {
+                [from, to] => Some((
+                    todo::format_date(from, today, &config.dates)?,
+                    todo::format_date(to, today, &config.dates)?,
+                )),
+                _ => None,
+            };
+            let options = ListOptions {
+                sort_by_category: category.is_some() || config.list.category,
+                category: category.flatten(),
+                include_done: include_done || config.list.include_done,
+                ready,
+                priorities: priority,
+                tags: TagFilter {
+                    all: tag,
+                    any: any_tag,
+                    none: not_tag,
+                },
+                created_since: parse_date(created_since)?,
+                completed_between,
+                search,
+                filter: filter
+                    .map(|text| parse_filter(&text, today, config, output))
+                    .transpose()?,
+                sort: sort.unwrap_or_default(),
+            };
+            let list = |conn: &Conn| match &as_of {
+                Some(date) => conn.as_of(date)?.list_tasks(&options),
+                None => conn.list_tasks(&options),
+            };
+            if all_lists {
+                let global = DbLocation::global(config);
+                let mut stores = vec![(store_name(location), list(conn)?)];
+                if location.is_local() && global.path != location.path {
+                    let global_conn =
+                        Conn::build(&global.path)?.strict_categories(config.strict_categories);
+                    stores.push((store_name(&global), list(&global_conn)?));
+                }
+                if !output.is_text() {
+                    let listed: Vec<_> = stores
+                        .iter()
+                        .flat_map(|(list, tasks)| tasks.iter().map(|task| Listed { list, task }))
+                        .collect();
+                    output.many(&listed);
+                    return Ok(());
+                }
+                for (store, tasks) in stores {
+                    let store = format!("{store:<6} ");
+                    if options.sort_by_category {
+                        print_tree(&tasks, &store, &show, &heading);
+                    } else if options.sort != SortBy::Priority {
+                        print_by_time(&tasks, &store, options.sort, &show);
+                    } else {
+                        print_subtasks(tasks, &store, &show);
+                    }
+                }
+            } else {
+                let tasks = list(conn)?;
+                if !output.is_text() {
+                    output.many(&tasks);
+                } else if options.sort_by_category {
+                    print_tree(&tasks, "", &show, &heading);
+                } else if options.sort != SortBy::Priority {
+                    print_by_time(&tasks, "", options.sort, &show);
+                } else {
+                    print_subtasks(tasks, "", &show);
+                }
+            }
+        }
         Commands::Edit {
-            id,
+            ids,
+            filter,
             finish,
             due_date,
             category,
             info,
+            priority,
+            tag,
+            untag,
             remove,
-        } => println!("Edit command"),
+            force,
+            repeat,
+            from_completion,
+            no_repeat,
+            dry_run,
+            yes,
+        } => {
+            let ids = select_tasks(conn, &ids, filter.as_deref(), today, config, output)?;
+            if ids.is_empty() {
+                match output {
+                    Output::Text => println!("No tasks match"),
+                    _ => output.many::<Task>(&[]),
+                }
+            } else if remove {
+                if dry_run {
+                    let tasks = ids
+                        .iter()
+                        .map(|id| conn.get_task(*id))
+                        .collect::<todo::Result<Vec<_>>>()?;
+                    match output {
+                        Output::Text => tasks
+                            .iter()
+                            .for_each(|task| println!("Would remove: {}", show(task))),
+                        _ => output.many(
+                            &tasks
+                                .iter()
+                                .map(|task| Changed {
+                                    before: task,
+                                    after: None,
+                                })
+                                .collect::<Vec<_>>(),
+                        ),
+                    }
+                    return Ok(());
+                }
+                if !yes && !output.is_text() {
+                    output.fail(
+                        EXIT_CANCELLED,
+                        "Did not remove",
+                        "removing tasks needs --yes outside text output",
+                    )
+                }
+                let subtasks = ids
+                    .iter()
+                    .map(|id| conn.count_subtasks(*id))
+                    .sum::<todo::Result<u32>>()?;
+                if !yes && !confirm_delete(&ids, subtasks) {
+                    eprintln!("Did not remove {}", describe_tasks(&ids));
+                    std::process::exit(EXIT_CANCELLED)
+                }
+                let removed = conn.remove_tasks(&ids)?;
+                match output {
+                    Output::Text => removed
+                        .iter()
+                        .for_each(|task| println!("Moved to trash: {task}")),
+                    _ => output.many(
+                        &removed
+                            .iter()
+                            .map(|task| Changed {
+                                before: task,
+                                after: None,
+                            })
+                            .collect::<Vec<_>>(),
+                    ),
+                }
+            } else {
+                let mut edit = TaskEdit {
+                    finish,
+                    due_date: parse_date(due_date)?,
+                    category,
+                    info,
+                    priority,
+                    tags: tag,
+                    untag,
+                    cascade: force,
+                    recurrence: repeat.map(|rule| Recurrence {
+                        from_completion: rule.from_completion || from_completion,
+                        ..rule
+                    }),
+                    stop_repeating: no_repeat,
+                };
+                let changes = match conn.edit_tasks(&ids, &edit, dry_run) {
+                    Err(Error::OpenSubtasks { id, open })
+                        if output.is_text()
+                            && io::stdin().is_terminal()
+                            && confirm_cascade(id, open) =>
+                    {
+                        edit.cascade = true;
+                        conn.edit_tasks(&ids, &edit, dry_run)
+                    }
+                    result => result,
+                }?;
+
+                if !output.is_text() {
+                    output.many(
+                        &changes
+                            .iter()
+                            .map(|(before, after)| Changed {
+                                before,
+                                after: Some(after),
+                            })
+                            .collect::<Vec<_>>(),
+                    );
+                    return Ok(());
+                }
+                if let [(_, task)] = changes.as_slice()
+                    && !dry_run
+                {
+                    println!("{}", show(task));
+                } else {
+                    let mut changed = 0;
+                    for (before, after) in &changes {
+                        if before.to_string() != after.to_string() {
+                            println!("- {}", show(before));
+                            println!("+ {}", show(after));
+                            changed += 1;
+                        }
+                    }
+                    let verb = if dry_run { "Would change" } else { "Changed" };
+                    println!("{verb} {changed} of {} tasks", changes.len());
+                }
+                if finish == Some(true) && !dry_run {
+                    for (before, _) in changes.iter().filter(|(task, _)| task.recurrence.is_some())
+                    {
+                        match conn.next_instance(before.id)? {
+                            Some(next) => println!("Next: {}", show(&next)),
+                            None => println!("Task {} will not repeat again", before.id),
+                        }
+                    }
+                }
+            }
+        }
+        Commands::Count {
+            filter,
+            include_done,
+        } => {
+            let options = ListOptions {
+                include_done,
+                filter: filter
+                    .map(|text| parse_filter(&text, today, config, output))
+                    .transpose()?,
+                ..Default::default()
+            };
+            let count = conn.count_tasks(&options)?;
+            match output {
+                Output::Text => println!("{count}"),
+                _ => output.one(&json!({ "count": count })),
+            }
+        }
+        Commands::Search {
+            query,
+            include_done,
+        } => {
+            let (start, end) = match (output, color) {
+                (Output::Text, true) => config.theme.highlight.marks(),
+                (Output::Text, false) => ("[".to_string(), "]".to_string()),
+                _ => Default::default(),
+            };
+            let hits = conn.search(&query, include_done, (&start, &end))?;
+            if !output.is_text() {
+                output.many(&hits);
+                return Ok(());
+            }
+            if hits.is_empty() {
+                println!("No tasks match {query}");
+            }
+            for hit in hits {
+                let task = Task {
+                    info: hit.snippet,
+                    ..hit.task
+                };
+                println!("{}", show(&task));
+            }
+        }
+        Commands::Log { id, since } => {
+            let entries = match id {
+                Some(id) => conn.task_history(id)?,
+                None => conn.history_since(since.unwrap_or(7))?,
+            };
+            if !output.is_text() {
+                output.many(&entries);
+                return Ok(());
+            }
+            for entry in entries {
+                println!("{entry}");
+                if entry.action == Action::Delete {
+                    continue;
+                }
+                let none = || "none".to_string();
+                for change in entry.changes {
+                    // the id is in the heading, modified_at moves with
+                    // any other change
+                    if change.field == "id" || change.field == "modified_at" {
+                        continue;
+                    }
+                    match entry.action {
+                        Action::Insert => {
+                            println!("    {}: {}", change.field, change.new.unwrap_or_else(none))
+                        }
+                        _ => println!(
+                            "    {}: {} -> {}",
+                            change.field,
+                            change.old.unwrap_or_else(none),
+                            change.new.unwrap_or_else(none)
+                        ),
+                    }
+                }
+            }
+        }
+        Commands::Undo { id, list, force } => {
+            if list && !output.is_text() {
+                output.many(&conn.operations(20)?);
+            } else if list {
+                for operation in conn.operations(20)? {
+                    let undone = if operation.undone { " (undone)" } else { "" };
+                    println!(
+                        "{:>4}  {}  {}{undone}",
+                        operation.id, operation.at, operation.description
+                    );
+                }
+            } else {
+                let operation = conn.undo(id, force)?;
+                match output {
+                    Output::Text => println!("Undid {}: {}", operation.id, operation.description),
+                    _ => output.one(&operation),
+                }
+            }
+        }
+        Commands::Redo { id, force } => {
+            let operation = conn.redo(id, force)?;
+            match output {
+                Output::Text => println!("Redid {}: {}", operation.id, operation.description),
+                _ => output.one(&operation),
+            }
+        }
+        Commands::Tags if !output.is_text() => output.many(&conn.list_tags()?),
+        Commands::Tags => {
+            for tag in conn.list_tags()? {
+                println!("{:<20} {:>4} open {:>4} done", tag.name, tag.open, tag.done);
+            }
+        }
+        Commands::Category { command } => run_category(command, conn, output)?,
+        Commands::Trash { command } => match command {
+            TrashCommands::List if !output.is_text() => output.many(&conn.list_trash()?),
+            TrashCommands::List => {
+                for trashed in conn.list_trash()? {
+                    println!("{}  {}", trashed.deleted_at, show(&trashed.task));
+                }
+            }
+            TrashCommands::Restore { id } => {
+                let task = conn.restore_task(id)?;
+                match output {
+                    Output::Text => println!("Restored: {}", show(&task)),
+                    _ => output.one(&task),
+                }
+            }
+            TrashCommands::Empty { older_than } => {
+                let deleted = conn.empty_trash(older_than)?;
+                match output {
+                    Output::Text => println!("Deleted {deleted} tasks for good"),
+                    _ => output.one(&json!({ "deleted": deleted })),
+                }
+            }
+        },
+        Commands::Export { format: _, filter } => {
+            let filter = filter
+                .map(|text| parse_filter(&text, today, config, output))
+                .transpose()?;
+            let export = conn.export(filter.as_ref())?;
+            match output {
+                Output::Text => println!("{}", export.to_json()),
+                // one line for ndjson
+                _ => output.one(&export),
+            }
+        }
+        Commands::Import { file, on_conflict } => {
+            let export = Export::from_json(&std::fs::read_to_string(file)?)?;
+            let summary = conn.import(&export, on_conflict)?;
+            if !output.is_text() {
+                output.one(&summary);
+                return Ok(());
+            }
+            println!(
+                "Imported {} tasks, overwrote {} and skipped {}",
+                summary.added, summary.overwritten, summary.skipped
+            );
+            for (old, new) in summary.renumbered {
+                println!("Task {old} of the backup is now task {new}");
+            }
+        }
+        Commands::Dep { command } => match command {
+            DepCommands::Add { id, blocks } => {
+                conn.add_dependency(id, blocks)?;
+                match output {
+                    Output::Text => println!("Task {blocks} now waits on task {id}"),
+                    _ => output.one(&json!({ "blocker": id, "blocked": blocks })),
+                }
+            }
+            DepCommands::Remove { id, blocks } => {
+                conn.remove_dependency(id, blocks)?;
+                match output {
+                    Output::Text => println!("Task {blocks} no longer waits on task {id}"),
+                    _ => output.one(&json!({ "blocker": id, "blocked": blocks })),
+                }
+            }
+            DepCommands::List if !output.is_text() => output.many(&conn.list_dependencies()?),
+            DepCommands::List => {
+                for dep in conn.list_dependencies()? {
+                    println!("{:>3} blocks {}", dep.blocker, dep.blocked);
+                }
+            }
+        },
+        Commands::Db { .. } | Commands::Init | Commands::Config { .. } => {
+            unreachable!("runs before the database is opened")
+        }
     }
 
-    println!("Operation was a Success")
+    Ok(())
+}
+
+/// Prints tasks with their subtasks indented below them
+fn print_subtasks(tasks: Vec<Task>, store: &str, show: &impl Fn(&Task) -> String) {
+    for (depth, task) in todo::nest_subtasks(tasks) {
+        println!("{store}{}{}", "    ".repeat(depth), show(&task));
+    }
+}
+
+/// Prints tasks in the order given, each after the time they are sorted by
+fn print_by_time(tasks: &[Task], store: &str, sort: SortBy, show: &impl Fn(&Task) -> String) {
+    for task in tasks {
+        let time = sort.timestamp(task).unwrap_or("-");
+        println!("{store}{time:<19} {}", show(task));
+    }
+}
+
+/// Prints tasks under a tree of their categories, each heading with the
+/// number of open and finished tasks listed below it
+fn print_tree(
+    tasks: &[Task],
+    store: &str,
+    show: &impl Fn(&Task) -> String,
+    heading: &impl Fn(&str) -> String,
+) {
+    // finished tasks come last in the list, move them into their category
+    let mut tasks = tasks.to_vec();
+    fn tree_key(task: &Task) -> (bool, Vec<&str>) {
+        match &task.category {
+            Some(category) => (false, category.split('/').collect()),
+            None => (true, Vec::new()),
+        }
+    }
+    tasks.sort_by(|a, b| tree_key(a).cmp(&tree_key(b)));
+
+    let mut counts: BTreeMap<&str, (u32, u32)> = BTreeMap::new();
+    for task in &tasks {
+        let Some(category) = &task.category else {
+            continue;
+        };
+        let ends = category.match_indices('/').map(|(end, _)| end);
+        for end in ends.chain([category.len()]) {
+            let (open, done) = counts.entry(&category[..end]).or_default();
+            *if task.done { done } else { open } += 1;
+        }
+    }
+
+    let mut previous = None;
+    for (i, task) in tasks.iter().enumerate() {
+        let path: Vec<_> = task.category.iter().flat_map(|c| c.split('/')).collect();
+        if i == 0 || task.category != previous {
+            let above: Vec<_> = previous.iter().flat_map(|c| c.split('/')).collect();
+            let shared = above.iter().zip(&path).take_while(|(a, b)| a == b).count();
+            if path.is_empty() {
+                println!("{store}{}", heading("(no category)"));
+            }
+            for level in shared..path.len() {
+                let (open, done) = counts[path[..=level].join("/").as_str()];
+                println!(
+                    "{store}{}{}  {open} open, {done} done",
+                    "  ".repeat(level),
+                    heading(path[level])
+                );
+            }
+            previous = task.category.clone();
+        }
+        println!("{store}{}{}", "  ".repeat(path.len().max(1)), show(task));
+    }
+}
+
+fn run_category(command: CategoryCommands, conn: &Conn, output: Output) -> todo::Result<()> {
+    match command {
+        CategoryCommands::List { orphaned } if !output.is_text() => {
+            let categories: Vec<_> = conn
+                .list_categories()?
+                .into_iter()
+                .filter(|category| !orphaned || category.is_orphaned())
+                .collect();
+            output.many(&categories);
+        }
+        CategoryCommands::List { orphaned } => {
+            for category in conn.list_categories()? {
+                // orphans are listed by path, their parents may not be shown
+                let name = match orphaned {
+                    true if !category.is_orphaned() => continue,
+                    true => category.name.clone(),
+                    false => format!("{}{}", "  ".repeat(category.depth()), category.leaf()),
+                };
+                let trashed = match category.trashed {
+                    0 => String::new(),
+                    n => format!(" {n:>4} in trash"),
+                };
+                println!(
+                    "{name:<20} {:>4} open {:>4} done{trashed}",
+                    category.open, category.done
+                );
+            }
+        }
+        CategoryCommands::Add { name } => {
+            conn.add_category(&name)?;
+            match output {
+                Output::Text => println!("Added category {name}"),
+                _ => output.one(&json!({ "name": name })),
+            }
+        }
+        CategoryCommands::Rename { from, to } => {
+            conn.rename_category(&from, &to)?;
+            match output {
+                Output::Text => println!("Renamed category {from} to {to}"),
+                _ => output.one(&json!({ "from": from, "to": to })),
+            }
+        }
+        CategoryCommands::Merge { from, into } => {
+            let moved = conn.merge_categories(&from, &into)?;
+            match output {
+                Output::Text => println!("Merged {from} into {into}, moved {moved} tasks"),
+                _ => output.one(&json!({ "from": from, "into": into, "moved": moved })),
+            }
+        }
+        CategoryCommands::Delete { name, reassign } => {
+            let changed = conn.delete_category(&name, reassign.as_deref())?;
+            match (output, reassign) {
+                (Output::Text, Some(target)) => {
+                    println!("Deleted {name}, moved {changed} tasks to {target}")
+                }
+                (Output::Text, None) => {
+                    println!("Deleted {name}, {changed} tasks no longer have a category")
+                }
+                (_, reassign) => output.one(&json!({
+                    "name": name,
+                    "reassign": reassign,
+                    "changed": changed,
+                })),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Creates a project list in the current directory
+fn init(output: Output) -> todo::Result<()> {
+    let path = location::local_path(&std::env::current_dir()?);
+    let existed = path.exists();
+    Conn::build(&path)?;
+    if !output.is_text() {
+        output.one(&json!({ "path": path, "created": !existed }));
+    } else if existed {
+        println!("Project list already exists at {}", path.display());
+    } else {
+        println!("Created project list at {}", path.display());
+    }
+    Ok(())
+}
+
+/// Label for the store column of `list --all-lists`
+fn store_name(location: &DbLocation) -> &'static str {
+    if location.is_local() {
+        "local"
+    } else {
+        "global"
+    }
+}
+
+fn run_config(command: ConfigCommands, config: &Config, output: Output) -> todo::Result<()> {
+    let print_entries = |entries: Vec<(String, String)>| match output {
+        Output::Text => entries
+            .iter()
+            .for_each(|(key, value)| println!("{key} = {value}")),
+        _ => output.many(
+            &entries
+                .iter()
+                .map(|(key, value)| json!({ "key": key, "value": value }))
+                .collect::<Vec<_>>(),
+        ),
+    };
+    match command {
+        ConfigCommands::Get { key } => {
+            let entries = config.get(&key);
+            if entries.is_empty() {
+                return Err(Error::Config {
+                    path: todo::config::default_path()?,
+                    message: format!("no setting named '{key}'"),
+                });
+            }
+            print_entries(entries);
+        }
+        ConfigCommands::Set { key, value } => {
+            let path = todo::config::default_path()?;
+            todo::config::set_value(&path, &key, &value)?;
+            match output {
+                Output::Text => println!("Set {key} in {}", path.display()),
+                _ => output.one(&json!({ "key": key, "value": value, "path": path })),
+            }
+        }
+        ConfigCommands::List => print_entries(config.entries()),
+    }
+
+    Ok(())
+}
+
+fn run_db(command: DbCommands, location: &DbLocation, output: Output) -> todo::Result<()> {
+    match command {
+        DbCommands::Migrate { status } => {
+            let before = SchemaStatus::open(&location.path)?;
+            let migrations: Vec<_> = before
+                .pending()
+                .iter()
+                .map(|migration| {
+                    json!({ "version": migration.version, "description": migration.description })
+                })
+                .collect();
+            if !output.is_text() {
+                if !status {
+                    Conn::build(&location.path)?;
+                }
+                let done = if status { "pending" } else { "applied" };
+                output.one(&json!({
+                    "version": if status { before.current } else { before.latest },
+                    "latest": before.latest,
+                    done: migrations,
+                }));
+            } else if status {
+                println!(
+                    "Schema version {} (latest is {})",
+                    before.current, before.latest
+                );
+                for migration in before.pending() {
+                    println!("  pending {}: {}", migration.version, migration.description);
+                }
+            } else {
+                Conn::build(&location.path)?;
+                for migration in before.pending() {
+                    println!("  applied {}: {}", migration.version, migration.description);
+                }
+                println!("Schema is at version {}", before.latest);
+            }
+        }
+        DbCommands::Path => match output {
+            Output::Text => println!("{} ({})", location.path.display(), location.source),
+            _ => output.one(&json!({
+                "path": location.path,
+                "source": location.source.to_string(),
+            })),
+        },
+    }
+
+    Ok(())
+}
+
+/// Maps a library error onto the exit codes listed in `EXIT_CODES_HELP`
+fn exit_code(err: &Error) -> i32 {
+    match err {
+        Error::Storage(_) | Error::Io(_) => EXIT_STORAGE,
+        Error::EmptyTaskInfo
+        | Error::EmptyCategoryName
+        | Error::EmptyTagName
+        | Error::InvalidDueDate
+        | Error::InvalidSearch(_)
+        | Error::InvalidFilter(_)
+        | Error::Date(_)
+        | Error::InvalidBackup(_)
+        | Error::Constraint(_) => EXIT_INVALID,
+        Error::TaskNotFound(_)
+        | Error::CategoryNotFound(_)
+        | Error::NothingToUndo(_)
+        | Error::NothingToRedo(_)
+        | Error::OperationNotFound(_)
+        | Error::NotInTrash(_) => EXIT_NOT_FOUND,
+        Error::DuplicateCategory
+        | Error::OpenSubtasks { .. }
+        | Error::Blocked { .. }
+        | Error::DependencyCycle { .. }
+        | Error::UndoConflict { .. }
+        | Error::ParentInTrash { .. } => EXIT_CONFLICT,
+        Error::SchemaTooNew { .. } | Error::BackupTooNew { .. } => EXIT_SCHEMA,
+        Error::Config { .. } => EXIT_CONFIG,
+    }
+}
+
+/// Parses a number of days like `30d` or `30`
+fn parse_days(text: &str) -> Result<u32, String> {
+    text.strip_suffix('d')
+        .unwrap_or(text)
+        .parse()
+        .map_err(|_| format!("'{text}' is not a number of days, e.g. 30d"))
+}
+
+/// Parses a `--filter` expression, pointing at the problem on stderr when it
+/// does not parse
+fn parse_filter(
+    text: &str,
+    today: &NaiveDate,
+    config: &Config,
+    output: Output,
+) -> todo::Result<Filter> {
+    Filter::parse(text, today, &config.dates).map_err(|err| {
+        if output.is_text() {
+            eprintln!("  {text}");
+            eprintln!("  {:>1$}", "^", err.column);
+        }
+        err.into()
+    })
+}
+
+/// Asks the user to type 'delete' before tasks are removed
+fn confirm_delete(ids: &[i32], subtasks: u32) -> bool {
+    match subtasks {
+        0 => print!("Type 'delete' to remove {}: ", describe_tasks(ids)),
+        _ => print!(
+            "Type 'delete' to remove {} and {subtasks} subtasks: ",
+            describe_tasks(ids)
+        ),
+    }
+    let _ = io::stdout().flush();
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).is_ok() && answer.trim() == "delete"
+}
+
+/// Ids of the tasks `edit` works on: single ids as given, every task inside
+/// a range, or every task the filter lists
+fn select_tasks(
+    conn: &Conn,
+    ids: &[IdRange],
+    filter: Option<&str>,
+    today: &NaiveDate,
+    config: &Config,
+    output: Output,
+) -> todo::Result<Vec<i32>> {
+    let listed = |options: ListOptions| -> todo::Result<Vec<i32>> {
+        Ok(conn
+            .list_tasks(&options)?
+            .iter()
+            .map(|task| task.id)
+            .collect())
+    };
+    if let Some(text) = filter {
+        return listed(ListOptions {
+            filter: Some(parse_filter(text, today, config, output)?),
+            ..Default::default()
+        });
+    }
+
+    let mut selected = Vec::new();
+    for range in ids {
+        let found = match range.first == range.last {
+            true => vec![range.first],
+            false => {
+                let mut found = listed(ListOptions {
+                    include_done: true,
+                    filter: Some(range.filter()),
+                    ..Default::default()
+                })?;
+                found.sort();
+                found
+            }
+        };
+        for id in found {
+            if !selected.contains(&id) {
+                selected.push(id);
+            }
+        }
+    }
+    Ok(selected)
+}
+
+/// Asks whether finishing a task should finish its open subtasks too
+fn confirm_cascade(id: i32, open: u32) -> bool {
+    print!("Task {id} has {open} open subtasks, finish them too? [y/N] ");
+    let _ = io::stdout().flush();
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).is_ok()
+        && matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
 }
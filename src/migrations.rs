@@ -0,0 +1,832 @@
+use crate::{Error, Result};
+use rusqlite::Connection;
+use std::path::Path;
+
+/// One step of the schema, applied when `user_version` is below `version`
+#[derive(Debug)]
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    sql: &'static str,
+}
+
+/// Every schema change ever made, oldest first, never edit a released entry
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "create categories and tasks",
+        // databases made before migrations existed already have these tables
+        sql: r"
+            CREATE TABLE IF NOT EXISTS categories (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL UNIQUE CHECK(name != '')
+            );
+
+            CREATE TABLE IF NOT EXISTS tasks (
+                id INTEGER PRIMARY KEY,
+                info TEXT NOT NULL CHECK(info != ''),
+                done BOOLEAN NOT NULL DEFAULT false CHECK(done IN (0,1)),
+                due_date TEXT CHECK(
+                    due_date IS NULL OR
+                    (due_date GLOB '[0-9][0-9][0-9][0-9]-[0-1][0-9]-[0-3][0-9]' AND
+                        date(due_date) IS NOT NULL)
+            ),
+            category INTEGER,
+            FOREIGN KEY(category) REFERENCES categories(id)
+            );
+            ",
+    },
+    Migration {
+        version: 2,
+        description: "add task priority",
+        sql: r"
+            ALTER TABLE tasks
+            ADD COLUMN priority INTEGER NOT NULL DEFAULT 2 CHECK(priority IN (1,2,3));
+            ",
+    },
+    Migration {
+        version: 3,
+        description: "add tags",
+        sql: r"
+            CREATE TABLE tags (
+                id INTEGER PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE CONSTRAINT tag_name CHECK(name != '')
+            );
+
+            CREATE TABLE task_tags (
+                task_id INTEGER NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+                tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+                PRIMARY KEY (task_id, tag_id)
+            );
+            ",
+    },
+    Migration {
+        version: 4,
+        description: "nest categories",
+        // names holding a slash were flat until now, create the levels above
+        // them so every path has its parents
+        sql: r"
+            ALTER TABLE categories ADD COLUMN parent_id INTEGER REFERENCES categories(id);
+            CREATE INDEX categories_parent ON categories(parent_id);
+
+            INSERT OR IGNORE INTO categories (name)
+            WITH RECURSIVE prefixes(path, rest) AS (
+                SELECT '', name || '/' FROM categories
+                UNION
+                SELECT
+                    path || iif(path = '', '', '/') || substr(rest, 1, instr(rest, '/') - 1),
+                    substr(rest, instr(rest, '/') + 1)
+                FROM prefixes WHERE rest != ''
+            )
+            SELECT path FROM prefixes WHERE path != '' ORDER BY length(path);
+
+            UPDATE categories SET parent_id = (
+                SELECT parent.id FROM categories AS parent
+                WHERE parent.name = rtrim(rtrim(categories.name, replace(categories.name, '/', '')), '/')
+            )
+            WHERE instr(name, '/') > 0;
+            ",
+    },
+    Migration {
+        version: 5,
+        description: "add subtasks",
+        // removing a task removes everything below it
+        sql: r"
+            ALTER TABLE tasks
+            ADD COLUMN parent_id INTEGER REFERENCES tasks(id) ON DELETE CASCADE;
+            CREATE INDEX tasks_parent ON tasks(parent_id);
+            ",
+    },
+    Migration {
+        version: 6,
+        description: "add task dependencies",
+        sql: r"
+            CREATE TABLE task_deps (
+                blocker_id INTEGER NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+                blocked_id INTEGER NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+                PRIMARY KEY (blocker_id, blocked_id),
+                CHECK(blocker_id != blocked_id)
+            );
+            CREATE INDEX task_deps_blocked ON task_deps(blocked_id);
+            ",
+    },
+    Migration {
+        version: 7,
+        description: "add task timestamps",
+        // times are UTC, rows from before this migration keep NULL for what
+        // is not known instead of pretending they were made today
+        sql: r"
+            ALTER TABLE tasks ADD COLUMN created_at TEXT;
+            ALTER TABLE tasks ADD COLUMN modified_at TEXT;
+            ALTER TABLE tasks ADD COLUMN completed_at TEXT;
+
+            CREATE TRIGGER tasks_created AFTER INSERT ON tasks
+            BEGIN
+                UPDATE tasks SET
+                    created_at = coalesce(NEW.created_at, strftime('%Y-%m-%d %H:%M:%S')),
+                    modified_at = coalesce(
+                        NEW.modified_at, NEW.created_at, strftime('%Y-%m-%d %H:%M:%S')
+                    ),
+                    completed_at = iif(
+                        NEW.done, coalesce(NEW.completed_at, strftime('%Y-%m-%d %H:%M:%S')), NULL
+                    )
+                WHERE id = NEW.id;
+            END;
+
+            CREATE TRIGGER tasks_modified
+            AFTER UPDATE OF info, done, due_date, category, priority, parent_id ON tasks
+            WHEN OLD.info IS NOT NEW.info OR OLD.done IS NOT NEW.done
+                OR OLD.due_date IS NOT NEW.due_date OR OLD.category IS NOT NEW.category
+                OR OLD.priority IS NOT NEW.priority OR OLD.parent_id IS NOT NEW.parent_id
+            BEGIN
+                UPDATE tasks SET modified_at = strftime('%Y-%m-%d %H:%M:%S') WHERE id = NEW.id;
+            END;
+
+            CREATE TRIGGER tasks_completed AFTER UPDATE OF done ON tasks
+            WHEN OLD.done IS NOT NEW.done
+            BEGIN
+                UPDATE tasks SET completed_at = iif(NEW.done, strftime('%Y-%m-%d %H:%M:%S'), NULL)
+                WHERE id = NEW.id;
+            END;
+
+            CREATE TRIGGER task_tags_added AFTER INSERT ON task_tags
+            BEGIN
+                UPDATE tasks SET modified_at = strftime('%Y-%m-%d %H:%M:%S') WHERE id = NEW.task_id;
+            END;
+
+            CREATE TRIGGER task_tags_removed AFTER DELETE ON task_tags
+            BEGIN
+                UPDATE tasks SET modified_at = strftime('%Y-%m-%d %H:%M:%S') WHERE id = OLD.task_id;
+            END;
+            ",
+    },
+    Migration {
+        version: 8,
+        description: "add recurring tasks",
+        // `repeat_of` links an instance to the one whose completion made it
+        sql: r"
+            ALTER TABLE tasks ADD COLUMN recurrence TEXT CHECK(recurrence != '');
+            ALTER TABLE tasks ADD COLUMN repeat_from_completion BOOLEAN NOT NULL DEFAULT false
+                CHECK(repeat_from_completion IN (0,1));
+            ALTER TABLE tasks
+            ADD COLUMN repeat_of INTEGER REFERENCES tasks(id) ON DELETE SET NULL;
+
+            DROP TRIGGER tasks_modified;
+            CREATE TRIGGER tasks_modified
+            AFTER UPDATE OF info, done, due_date, category, priority, parent_id, recurrence,
+                repeat_from_completion ON tasks
+            WHEN OLD.info IS NOT NEW.info OR OLD.done IS NOT NEW.done
+                OR OLD.due_date IS NOT NEW.due_date OR OLD.category IS NOT NEW.category
+                OR OLD.priority IS NOT NEW.priority OR OLD.parent_id IS NOT NEW.parent_id
+                OR OLD.recurrence IS NOT NEW.recurrence
+                OR OLD.repeat_from_completion IS NOT NEW.repeat_from_completion
+            BEGIN
+                UPDATE tasks SET modified_at = strftime('%Y-%m-%d %H:%M:%S') WHERE id = NEW.id;
+            END;
+            ",
+    },
+    Migration {
+        version: 9,
+        description: "add full text search",
+        // the index reads its text from tasks, triggers tell it what changed
+        sql: r"
+            CREATE VIRTUAL TABLE tasks_fts USING fts5(
+                info,
+                content = 'tasks',
+                content_rowid = 'id',
+                tokenize = 'unicode61 remove_diacritics 2'
+            );
+            INSERT INTO tasks_fts (tasks_fts) VALUES ('rebuild');
+
+            CREATE TRIGGER tasks_fts_insert AFTER INSERT ON tasks
+            BEGIN
+                INSERT INTO tasks_fts (rowid, info) VALUES (NEW.id, NEW.info);
+            END;
+
+            CREATE TRIGGER tasks_fts_delete AFTER DELETE ON tasks
+            BEGIN
+                INSERT INTO tasks_fts (tasks_fts, rowid, info) VALUES ('delete', OLD.id, OLD.info);
+            END;
+
+            CREATE TRIGGER tasks_fts_update AFTER UPDATE OF info ON tasks
+            BEGIN
+                INSERT INTO tasks_fts (tasks_fts, rowid, info) VALUES ('delete', OLD.id, OLD.info);
+                INSERT INTO tasks_fts (rowid, info) VALUES (NEW.id, NEW.info);
+            END;
+            ",
+    },
+    Migration {
+        version: 10,
+        description: "add undo journal",
+        // each operation keeps the rows it touched as they were before it,
+        // `after` is filled in once it is done. Triggers only record while
+        // `journal_state` names an operation, so undo itself goes unrecorded.
+        // A migration adding a column to one of these tables has to recreate
+        // its triggers.
+        sql: r"
+            CREATE TABLE journal (
+                id INTEGER PRIMARY KEY,
+                description TEXT NOT NULL,
+                at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%S')),
+                -- order in which operations were undone, NULL while applied
+                undone INTEGER
+            );
+
+            CREATE TABLE journal_rows (
+                operation_id INTEGER NOT NULL REFERENCES journal(id) ON DELETE CASCADE,
+                row_table TEXT NOT NULL,
+                row_key TEXT NOT NULL,
+                before TEXT,
+                after TEXT,
+                PRIMARY KEY (operation_id, row_table, row_key)
+            );
+            CREATE INDEX journal_rows_row ON journal_rows(row_table, row_key);
+
+            CREATE TABLE journal_state (operation_id INTEGER);
+            INSERT INTO journal_state (operation_id) VALUES (NULL);
+
+            CREATE TRIGGER categories_journal_insert AFTER INSERT ON categories
+            WHEN (SELECT operation_id FROM journal_state) IS NOT NULL
+            BEGIN
+                INSERT OR IGNORE INTO journal_rows (operation_id, row_table, row_key, before)
+                SELECT operation_id, 'categories', json_array(NEW.id),
+                    NULL
+                FROM journal_state;
+            END;
+
+            CREATE TRIGGER categories_journal_update AFTER UPDATE ON categories
+            WHEN (SELECT operation_id FROM journal_state) IS NOT NULL
+            BEGIN
+                INSERT OR IGNORE INTO journal_rows (operation_id, row_table, row_key, before)
+                SELECT operation_id, 'categories', json_array(OLD.id),
+                    json_object('id', OLD.id, 'name', OLD.name, 'parent_id', OLD.parent_id)
+                FROM journal_state;
+            END;
+
+            CREATE TRIGGER categories_journal_delete AFTER DELETE ON categories
+            WHEN (SELECT operation_id FROM journal_state) IS NOT NULL
+            BEGIN
+                INSERT OR IGNORE INTO journal_rows (operation_id, row_table, row_key, before)
+                SELECT operation_id, 'categories', json_array(OLD.id),
+                    json_object('id', OLD.id, 'name', OLD.name, 'parent_id', OLD.parent_id)
+                FROM journal_state;
+            END;
+
+            CREATE TRIGGER tags_journal_insert AFTER INSERT ON tags
+            WHEN (SELECT operation_id FROM journal_state) IS NOT NULL
+            BEGIN
+                INSERT OR IGNORE INTO journal_rows (operation_id, row_table, row_key, before)
+                SELECT operation_id, 'tags', json_array(NEW.id),
+                    NULL
+                FROM journal_state;
+            END;
+
+            CREATE TRIGGER tags_journal_delete AFTER DELETE ON tags
+            WHEN (SELECT operation_id FROM journal_state) IS NOT NULL
+            BEGIN
+                INSERT OR IGNORE INTO journal_rows (operation_id, row_table, row_key, before)
+                SELECT operation_id, 'tags', json_array(OLD.id),
+                    json_object('id', OLD.id, 'name', OLD.name)
+                FROM journal_state;
+            END;
+
+            CREATE TRIGGER tasks_journal_insert AFTER INSERT ON tasks
+            WHEN (SELECT operation_id FROM journal_state) IS NOT NULL
+            BEGIN
+                INSERT OR IGNORE INTO journal_rows (operation_id, row_table, row_key, before)
+                SELECT operation_id, 'tasks', json_array(NEW.id),
+                    NULL
+                FROM journal_state;
+            END;
+
+            CREATE TRIGGER tasks_journal_update AFTER UPDATE ON tasks
+            WHEN (SELECT operation_id FROM journal_state) IS NOT NULL
+            BEGIN
+                INSERT OR IGNORE INTO journal_rows (operation_id, row_table, row_key, before)
+                SELECT operation_id, 'tasks', json_array(OLD.id),
+                    json_object(
+                        'id', OLD.id, 'info', OLD.info, 'done', OLD.done,
+                        'due_date', OLD.due_date, 'category', OLD.category,
+                        'priority', OLD.priority, 'parent_id', OLD.parent_id,
+                        'created_at', OLD.created_at, 'modified_at', OLD.modified_at,
+                        'completed_at', OLD.completed_at, 'recurrence', OLD.recurrence,
+                        'repeat_from_completion', OLD.repeat_from_completion,
+                        'repeat_of', OLD.repeat_of
+                    )
+                FROM journal_state;
+            END;
+
+            CREATE TRIGGER tasks_journal_delete AFTER DELETE ON tasks
+            WHEN (SELECT operation_id FROM journal_state) IS NOT NULL
+            BEGIN
+                INSERT OR IGNORE INTO journal_rows (operation_id, row_table, row_key, before)
+                SELECT operation_id, 'tasks', json_array(OLD.id),
+                    json_object(
+                        'id', OLD.id, 'info', OLD.info, 'done', OLD.done,
+                        'due_date', OLD.due_date, 'category', OLD.category,
+                        'priority', OLD.priority, 'parent_id', OLD.parent_id,
+                        'created_at', OLD.created_at, 'modified_at', OLD.modified_at,
+                        'completed_at', OLD.completed_at, 'recurrence', OLD.recurrence,
+                        'repeat_from_completion', OLD.repeat_from_completion,
+                        'repeat_of', OLD.repeat_of
+                    )
+                FROM journal_state;
+            END;
+
+            CREATE TRIGGER task_tags_journal_insert AFTER INSERT ON task_tags
+            WHEN (SELECT operation_id FROM journal_state) IS NOT NULL
+            BEGIN
+                INSERT OR IGNORE INTO journal_rows (operation_id, row_table, row_key, before)
+                SELECT operation_id, 'task_tags', json_array(NEW.task_id, NEW.tag_id),
+                    NULL
+                FROM journal_state;
+            END;
+
+            CREATE TRIGGER task_tags_journal_delete AFTER DELETE ON task_tags
+            WHEN (SELECT operation_id FROM journal_state) IS NOT NULL
+            BEGIN
+                INSERT OR IGNORE INTO journal_rows (operation_id, row_table, row_key, before)
+                SELECT operation_id, 'task_tags', json_array(OLD.task_id, OLD.tag_id),
+                    json_object('task_id', OLD.task_id, 'tag_id', OLD.tag_id)
+                FROM journal_state;
+            END;
+
+            CREATE TRIGGER task_deps_journal_insert AFTER INSERT ON task_deps
+            WHEN (SELECT operation_id FROM journal_state) IS NOT NULL
+            BEGIN
+                INSERT OR IGNORE INTO journal_rows (operation_id, row_table, row_key, before)
+                SELECT operation_id, 'task_deps', json_array(NEW.blocker_id, NEW.blocked_id),
+                    NULL
+                FROM journal_state;
+            END;
+
+            CREATE TRIGGER task_deps_journal_delete AFTER DELETE ON task_deps
+            WHEN (SELECT operation_id FROM journal_state) IS NOT NULL
+            BEGIN
+                INSERT OR IGNORE INTO journal_rows (operation_id, row_table, row_key, before)
+                SELECT operation_id, 'task_deps', json_array(OLD.blocker_id, OLD.blocked_id),
+                    json_object('blocker_id', OLD.blocker_id, 'blocked_id', OLD.blocked_id)
+                FROM journal_state;
+            END;
+            ",
+    },
+    Migration {
+        version: 11,
+        description: "add trash",
+        // removed tasks keep their row until the trash is emptied, so their
+        // ids are never handed out again and their categories stay in use
+        sql: r"
+            ALTER TABLE tasks ADD COLUMN deleted_at TEXT;
+            CREATE INDEX tasks_deleted ON tasks(deleted_at);
+
+            DROP TRIGGER tasks_journal_update;
+            DROP TRIGGER tasks_journal_delete;
+
+            CREATE TRIGGER tasks_journal_update AFTER UPDATE ON tasks
+            WHEN (SELECT operation_id FROM journal_state) IS NOT NULL
+            BEGIN
+                INSERT OR IGNORE INTO journal_rows (operation_id, row_table, row_key, before)
+                SELECT operation_id, 'tasks', json_array(OLD.id),
+                    json_object(
+                        'id', OLD.id, 'info', OLD.info, 'done', OLD.done,
+                        'due_date', OLD.due_date, 'category', OLD.category,
+                        'priority', OLD.priority, 'parent_id', OLD.parent_id,
+                        'created_at', OLD.created_at, 'modified_at', OLD.modified_at,
+                        'completed_at', OLD.completed_at, 'recurrence', OLD.recurrence,
+                        'repeat_from_completion', OLD.repeat_from_completion,
+                        'repeat_of', OLD.repeat_of, 'deleted_at', OLD.deleted_at
+                    )
+                FROM journal_state;
+            END;
+
+            CREATE TRIGGER tasks_journal_delete AFTER DELETE ON tasks
+            WHEN (SELECT operation_id FROM journal_state) IS NOT NULL
+            BEGIN
+                INSERT OR IGNORE INTO journal_rows (operation_id, row_table, row_key, before)
+                SELECT operation_id, 'tasks', json_array(OLD.id),
+                    json_object(
+                        'id', OLD.id, 'info', OLD.info, 'done', OLD.done,
+                        'due_date', OLD.due_date, 'category', OLD.category,
+                        'priority', OLD.priority, 'parent_id', OLD.parent_id,
+                        'created_at', OLD.created_at, 'modified_at', OLD.modified_at,
+                        'completed_at', OLD.completed_at, 'recurrence', OLD.recurrence,
+                        'repeat_from_completion', OLD.repeat_from_completion,
+                        'repeat_of', OLD.repeat_of, 'deleted_at', OLD.deleted_at
+                    )
+                FROM journal_state;
+            END;
+            ",
+    },
+    Migration {
+        version: 12,
+        description: "add task history",
+        // `image` is the whole row after the change, tasks with their tag
+        // names and the ids they wait on, so a past state is a lookup
+        // rather than a replay. Rows from before the history started are
+        // recorded as added when they were created, as they are now.
+        sql: r"
+            CREATE TABLE task_history (
+                id INTEGER PRIMARY KEY,
+                row_table TEXT NOT NULL CHECK(row_table IN ('tasks', 'categories')),
+                row_id INTEGER NOT NULL,
+                action TEXT NOT NULL CHECK(action IN ('insert', 'update', 'delete')),
+                -- {field: [old, new]} for every field that changed
+                changes TEXT NOT NULL,
+                image TEXT,
+                at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%S')),
+                user TEXT
+            );
+            CREATE INDEX task_history_row ON task_history(row_table, row_id);
+            CREATE INDEX task_history_at ON task_history(at);
+
+            INSERT INTO task_history (row_table, row_id, action, changes, image, at)
+            WITH images(row_table, row_id, image, at) AS (
+                SELECT 'categories', id, json_object('id', id, 'name', name, 'parent_id', parent_id),
+                    coalesce(
+                        (SELECT min(created_at) FROM tasks),
+                        strftime('%Y-%m-%d %H:%M:%S')
+                    )
+                FROM categories
+                UNION ALL
+                SELECT 'tasks', id,
+                    json_object(
+                        'id', id, 'info', info, 'done', done,
+                        'due_date', due_date, 'category', category,
+                        'priority', priority, 'parent_id', parent_id,
+                        'created_at', created_at, 'modified_at', modified_at,
+                        'completed_at', completed_at, 'recurrence', recurrence,
+                        'repeat_from_completion', repeat_from_completion,
+                        'repeat_of', repeat_of, 'deleted_at', deleted_at,
+                        'tags', json((
+                            SELECT json_group_array(name) FROM (
+                                SELECT tags.name FROM task_tags
+                                JOIN tags ON tags.id = task_tags.tag_id
+                                WHERE task_tags.task_id = tasks.id
+                                ORDER BY tags.name
+                            )
+                        )),
+                        'blockers', json((
+                            SELECT json_group_array(blocker_id) FROM (
+                                SELECT blocker_id FROM task_deps
+                                WHERE blocked_id = tasks.id
+                                ORDER BY blocker_id
+                            )
+                        ))
+                    ),
+                    coalesce(created_at, strftime('%Y-%m-%d %H:%M:%S'))
+                FROM tasks
+            )
+            SELECT row_table, row_id, 'insert',
+                (
+                    SELECT json_group_object(key, json_array(NULL, json(image -> fullkey)))
+                    FROM json_each(image) WHERE type != 'null'
+                ),
+                image, at
+            FROM images
+            ORDER BY at, row_table, row_id;
+
+            CREATE TRIGGER task_history_no_update BEFORE UPDATE ON task_history
+            BEGIN
+                SELECT RAISE(ABORT, 'task history is append only');
+            END;
+
+            CREATE TRIGGER task_history_no_delete BEFORE DELETE ON task_history
+            BEGIN
+                SELECT RAISE(ABORT, 'task history is append only');
+            END;
+            ",
+    },
+];
+
+/// The schema version this build creates and understands
+pub const LATEST_VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// Where a database stands relative to `LATEST_VERSION`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SchemaStatus {
+    pub current: u32,
+    pub latest: u32,
+}
+
+impl SchemaStatus {
+    /// Reads the version of the database at `file_path` without migrating it,
+    /// a missing file counts as version 0
+    pub fn open(file_path: impl AsRef<Path>) -> Result<Self> {
+        if !file_path.as_ref().exists() {
+            return Ok(Self {
+                current: 0,
+                latest: LATEST_VERSION,
+            });
+        }
+        Self::read(&Connection::open(file_path)?)
+    }
+
+    pub fn read(client: &Connection) -> Result<Self> {
+        Ok(Self {
+            current: client.pragma_query_value(None, "user_version", |row| row.get(0))?,
+            latest: LATEST_VERSION,
+        })
+    }
+
+    /// Migrations that would run the next time the database is opened
+    pub fn pending(&self) -> &'static [Migration] {
+        MIGRATIONS.get(self.current as usize..).unwrap_or_default()
+    }
+}
+
+/// Brings the schema up to `LATEST_VERSION`, one transaction per migration
+pub(crate) fn migrate(client: &mut Connection) -> Result<SchemaStatus> {
+    let status = SchemaStatus::read(client)?;
+    if status.current > status.latest {
+        return Err(Error::SchemaTooNew {
+            found: status.current,
+            supported: status.latest,
+        });
+    }
+
+    apply(client, status.pending())?;
+    Ok(status)
+}
+
+fn apply(client: &mut Connection, migrations: &[Migration]) -> Result<()> {
+    for migration in migrations {
+        let tx = client.transaction()?;
+        tx.execute_batch(migration.sql)?;
+        tx.pragma_update(None, "user_version", migration.version)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A database as written by builds from before migrations existed
+    fn v0_fixture() -> Connection {
+        let client = Connection::open_in_memory().unwrap();
+        client
+            .execute_batch(
+                r"
+                CREATE TABLE categories (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    name TEXT NOT NULL UNIQUE CHECK(name != '')
+                );
+                CREATE TABLE tasks (
+                    id INTEGER PRIMARY KEY,
+                    info TEXT NOT NULL CHECK(info != ''),
+                    done BOOLEAN NOT NULL DEFAULT false CHECK(done IN (0,1)),
+                    due_date TEXT CHECK(
+                        due_date IS NULL OR
+                        (due_date GLOB '[0-9][0-9][0-9][0-9]-[0-1][0-9]-[0-3][0-9]' AND
+                            date(due_date) IS NOT NULL)
+                    ),
+                    category INTEGER,
+                    FOREIGN KEY(category) REFERENCES categories(id)
+                );
+                INSERT INTO categories (name) VALUES ('work'), ('home');
+                INSERT INTO tasks (info, done, due_date, category)
+                VALUES
+                    ('ship it', 0, '2025-09-23', 1),
+                    ('clean', 1, NULL, 2),
+                    ('read', 0, NULL, NULL);
+                ",
+            )
+            .unwrap();
+        client
+    }
+
+    fn versions(migrations: &[Migration]) -> Vec<u32> {
+        migrations.iter().map(|m| m.version).collect()
+    }
+
+    #[test]
+    fn test_versions_are_sequential() {
+        for (i, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version as usize, i + 1);
+        }
+    }
+
+    #[test]
+    fn test_migrate_empty() {
+        let mut client = Connection::open_in_memory().unwrap();
+        let before = migrate(&mut client).unwrap();
+        assert_eq!(before.current, 0);
+        assert_eq!(versions(before.pending()), versions(MIGRATIONS));
+
+        let after = SchemaStatus::read(&client).unwrap();
+        assert_eq!(after.current, LATEST_VERSION);
+        assert!(after.pending().is_empty());
+
+        // running again is a no-op
+        assert_eq!(migrate(&mut client).unwrap(), after);
+    }
+
+    #[test]
+    fn test_migrate_v0_keeps_data() {
+        let mut client = v0_fixture();
+        migrate(&mut client).unwrap();
+
+        assert_eq!(SchemaStatus::read(&client).unwrap().current, LATEST_VERSION);
+
+        let rows = client
+            .prepare(
+                r"
+                SELECT tasks.id, tasks.info, tasks.done, tasks.due_date, categories.name,
+                    tasks.priority
+                FROM tasks
+                LEFT JOIN categories ON tasks.category = categories.id
+                ORDER BY tasks.id
+                ",
+            )
+            .unwrap()
+            .query_map((), |row| {
+                Ok((
+                    row.get::<_, i32>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, bool>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                    row.get::<_, i32>(5)?,
+                ))
+            })
+            .unwrap()
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(
+            rows,
+            vec![
+                (
+                    1,
+                    "ship it".to_string(),
+                    false,
+                    Some("2025-09-23".to_string()),
+                    Some("work".to_string()),
+                    2
+                ),
+                (
+                    2,
+                    "clean".to_string(),
+                    true,
+                    None,
+                    Some("home".to_string()),
+                    2
+                ),
+                (3, "read".to_string(), false, None, None, 2),
+            ]
+        );
+
+        // rows from before the history started are recorded as added
+        let history = client
+            .prepare("SELECT row_table, row_id, action FROM task_history ORDER BY id")
+            .unwrap()
+            .query_map((), |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, i32>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            })
+            .unwrap()
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .unwrap();
+        let added = |table: &str, id| (table.to_string(), id, "insert".to_string());
+        assert_eq!(
+            history,
+            vec![
+                added("categories", 1),
+                added("categories", 2),
+                added("tasks", 1),
+                added("tasks", 2),
+                added("tasks", 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_migrate_leaves_unknown_times_empty() {
+        let mut client = v0_fixture();
+        migrate(&mut client).unwrap();
+        client
+            .execute("INSERT INTO tasks (info, done) VALUES ('new', 1)", ())
+            .unwrap();
+
+        let times = client
+            .prepare(
+                r"
+                SELECT created_at IS NOT NULL, modified_at IS NOT NULL, completed_at IS NOT NULL
+                FROM tasks ORDER BY id
+                ",
+            )
+            .unwrap()
+            .query_map((), |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<Vec<(bool, bool, bool)>>>()
+            .unwrap();
+        // old rows, even the finished one, do not know when things happened
+        assert_eq!(
+            times,
+            vec![
+                (false, false, false),
+                (false, false, false),
+                (false, false, false),
+                (true, true, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_migrate_indexes_existing_tasks() {
+        let mut client = v0_fixture();
+        migrate(&mut client).unwrap();
+        let found: i32 = client
+            .query_row(
+                "SELECT rowid FROM tasks_fts WHERE tasks_fts MATCH 'ship'",
+                (),
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(found, 1);
+    }
+
+    #[test]
+    fn test_migrate_nests_slashed_categories() {
+        let mut client = v0_fixture();
+        client
+            .execute_batch(
+                r"
+                INSERT INTO categories (name) VALUES ('work/clientA/billing'), ('work/ops');
+                UPDATE tasks SET category = 3 WHERE id = 3;
+                ",
+            )
+            .unwrap();
+        migrate(&mut client).unwrap();
+
+        let tree = client
+            .prepare(
+                r"
+                SELECT categories.name, parent.name FROM categories
+                LEFT JOIN categories AS parent ON categories.parent_id = parent.id
+                ORDER BY categories.name
+                ",
+            )
+            .unwrap()
+            .query_map((), |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<Vec<(String, Option<String>)>>>()
+            .unwrap();
+        let node =
+            |name: &str, parent: Option<&str>| (name.to_string(), parent.map(str::to_string));
+        assert_eq!(
+            tree,
+            vec![
+                node("home", None),
+                node("work", None),
+                node("work/clientA", Some("work")),
+                node("work/clientA/billing", Some("work/clientA")),
+                node("work/ops", Some("work")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_failed_migration_rolls_back() {
+        let mut client = Connection::open_in_memory().unwrap();
+        let broken = [
+            Migration {
+                version: 1,
+                description: "works",
+                sql: "CREATE TABLE a (id INTEGER);",
+            },
+            Migration {
+                version: 2,
+                description: "fails halfway",
+                sql: "CREATE TABLE b (id INTEGER); INSERT INTO missing VALUES (1);",
+            },
+        ];
+
+        assert!(matches!(
+            apply(&mut client, &broken),
+            Err(Error::Storage(_))
+        ));
+        assert_eq!(SchemaStatus::read(&client).unwrap().current, 1);
+        assert!(client.prepare("SELECT * FROM a").is_ok());
+        assert!(client.prepare("SELECT * FROM b").is_err());
+    }
+
+    #[test]
+    fn test_migrate_refuses_newer() {
+        let mut client = Connection::open_in_memory().unwrap();
+        client
+            .pragma_update(None, "user_version", LATEST_VERSION + 1)
+            .unwrap();
+
+        assert!(matches!(
+            migrate(&mut client),
+            Err(Error::SchemaTooNew { found, supported })
+                if found == LATEST_VERSION + 1 && supported == LATEST_VERSION
+        ));
+        assert!(SchemaStatus::read(&client).unwrap().pending().is_empty());
+    }
+}
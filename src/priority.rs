@@ -0,0 +1,105 @@
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// How urgent a task is, stored as 1 (high) to 3 (low) so that sorting by
+/// the column puts the most urgent first
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(try_from = "String", into = "String")]
+pub enum Priority {
+    High = 1,
+    #[default]
+    Medium = 2,
+    Low = 3,
+}
+
+impl Priority {
+    /// Single letter used in `list` output
+    pub fn letter(&self) -> char {
+        match self {
+            Self::High => 'H',
+            Self::Medium => 'M',
+            Self::Low => 'L',
+        }
+    }
+}
+
+impl Display for Priority {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::High => write!(f, "high"),
+            Self::Medium => write!(f, "medium"),
+            Self::Low => write!(f, "low"),
+        }
+    }
+}
+
+impl FromStr for Priority {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "h" | "high" | "1" => Ok(Self::High),
+            "m" | "medium" | "2" => Ok(Self::Medium),
+            "l" | "low" | "3" => Ok(Self::Low),
+            _ => Err(format!("'{s}' is not a priority, use high, medium or low")),
+        }
+    }
+}
+
+impl TryFrom<String> for Priority {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Priority> for String {
+    fn from(priority: Priority) -> Self {
+        priority.to_string()
+    }
+}
+
+impl ToSql for Priority {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(*self as i64))
+    }
+}
+
+impl FromSql for Priority {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_i64()? {
+            1 => Ok(Self::High),
+            2 => Ok(Self::Medium),
+            3 => Ok(Self::Low),
+            other => Err(FromSqlError::OutOfRange(other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("H".parse(), Ok(Priority::High));
+        assert_eq!(" medium ".parse(), Ok(Priority::Medium));
+        assert_eq!("3".parse(), Ok(Priority::Low));
+        assert_eq!(
+            "urgent".parse::<Priority>(),
+            Err("'urgent' is not a priority, use high, medium or low".to_string())
+        );
+    }
+
+    #[test]
+    fn test_order() {
+        assert!(Priority::High < Priority::Medium);
+        assert!(Priority::Medium < Priority::Low);
+        assert_eq!(Priority::default(), Priority::Medium);
+    }
+}
@@ -0,0 +1,750 @@
+use crate::Result;
+use chrono::{Datelike, Days, Months, NaiveDate, Weekday};
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ValueRef};
+use rusqlite::{Connection, OptionalExtension};
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// When a task comes back after it is done, stored as an RFC 5545 RRULE
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recurrence {
+    pub frequency: Frequency,
+    /// Every how many days, weeks, months or years, at least 1
+    pub interval: u32,
+    /// Days of a weekly rule from Monday on, empty keeps the weekday of the
+    /// previous instance
+    pub weekdays: Vec<Weekday>,
+    /// Month of a yearly rule
+    pub month: Option<u32>,
+    /// Day of a monthly or yearly rule, -1 for the last day, days a month
+    /// does not have fall on its last day
+    pub month_day: Option<i32>,
+    /// No instance is due after this day
+    pub until: Option<NaiveDate>,
+    /// Instances left, this one included
+    pub count: Option<u32>,
+    /// Count from the day the task was finished instead of its due date
+    pub from_completion: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+impl Frequency {
+    fn unit(&self) -> &'static str {
+        match self {
+            Self::Daily => "day",
+            Self::Weekly => "week",
+            Self::Monthly => "month",
+            Self::Yearly => "year",
+        }
+    }
+}
+
+impl Recurrence {
+    pub fn new(frequency: Frequency) -> Self {
+        Self {
+            frequency,
+            interval: 1,
+            weekdays: Vec::new(),
+            month: None,
+            month_day: None,
+            until: None,
+            count: None,
+            from_completion: false,
+        }
+    }
+
+    /// Due date of the instance after one due or finished on `base`, with
+    /// the rule that instance carries, `None` once `until` or `count` is
+    /// used up
+    pub fn next(&self, base: NaiveDate) -> Option<(NaiveDate, Self)> {
+        if self.count == Some(1) {
+            return None;
+        }
+        let date = match self.frequency {
+            Frequency::Daily => base.checked_add_days(Days::new(self.interval.into()))?,
+            Frequency::Weekly => self.next_weekday(base)?,
+            Frequency::Monthly => {
+                let day = self.month_day.unwrap_or(base.day() as i32);
+                match day_in(base.year(), base.month(), day)? {
+                    date if date > base => date,
+                    _ => {
+                        let later = base
+                            .with_day(1)?
+                            .checked_add_months(Months::new(self.interval))?;
+                        day_in(later.year(), later.month(), day)?
+                    }
+                }
+            }
+            Frequency::Yearly => {
+                let month = self.month.unwrap_or(base.month());
+                let day = self.month_day.unwrap_or(base.day() as i32);
+                match day_in(base.year(), month, day)? {
+                    date if date > base => date,
+                    _ => day_in(base.year().checked_add(self.interval as i32)?, month, day)?,
+                }
+            }
+        };
+        if self.until.is_some_and(|until| date > until) {
+            return None;
+        }
+
+        let mut rule = self.clone();
+        rule.count = self.count.map(|count| count - 1);
+        // pin the day of the first instance so that once a short month moved
+        // one to its end, the ones after it go back to the 31st
+        if !self.from_completion {
+            match self.frequency {
+                Frequency::Monthly => {
+                    rule.month_day.get_or_insert(base.day() as i32);
+                }
+                Frequency::Yearly if self.month_day.is_none() => {
+                    rule.month = Some(base.month());
+                    rule.month_day = Some(base.day() as i32);
+                }
+                _ => {}
+            }
+        }
+        Some((date, rule))
+    }
+
+    /// The next listed weekday after `base`, later weeks are `interval`
+    /// weeks apart counting from the Monday of `base`'s week
+    fn next_weekday(&self, base: NaiveDate) -> Option<NaiveDate> {
+        let Some(&first) = self.weekdays.first() else {
+            return base.checked_add_days(Days::new(7 * u64::from(self.interval)));
+        };
+        let today = base.weekday().num_days_from_monday();
+        match self
+            .weekdays
+            .iter()
+            .find(|day| day.num_days_from_monday() > today)
+        {
+            Some(day) => {
+                base.checked_add_days(Days::new((day.num_days_from_monday() - today).into()))
+            }
+            None => base
+                .checked_sub_days(Days::new(today.into()))?
+                .checked_add_days(Days::new(
+                    7 * u64::from(self.interval) + u64::from(first.num_days_from_monday()),
+                )),
+        }
+    }
+
+    /// The rule as an RRULE value, without `from_completion`
+    pub fn rrule(&self) -> String {
+        let mut parts = vec![format!(
+            "FREQ={}",
+            match self.frequency {
+                Frequency::Daily => "DAILY",
+                Frequency::Weekly => "WEEKLY",
+                Frequency::Monthly => "MONTHLY",
+                Frequency::Yearly => "YEARLY",
+            }
+        )];
+        if self.interval != 1 {
+            parts.push(format!("INTERVAL={}", self.interval));
+        }
+        if !self.weekdays.is_empty() {
+            let days: Vec<_> = self.weekdays.iter().map(|day| rrule_day(*day)).collect();
+            parts.push(format!("BYDAY={}", days.join(",")));
+        }
+        if let Some(month) = self.month {
+            parts.push(format!("BYMONTH={month}"));
+        }
+        if let Some(day) = self.month_day {
+            parts.push(format!("BYMONTHDAY={day}"));
+        }
+        if let Some(until) = self.until {
+            parts.push(format!("UNTIL={}", until.format("%Y%m%d")));
+        }
+        if let Some(count) = self.count {
+            parts.push(format!("COUNT={count}"));
+        }
+        parts.join(";")
+    }
+
+    /// Parses `FREQ=WEEKLY;BYDAY=MO,TH` style rules, with or without the
+    /// `RRULE:` prefix
+    fn parse_rrule(rule: &str) -> std::result::Result<Self, String> {
+        let body = rule.trim();
+        let body = match body.get(..6) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("rrule:") => &body[6..],
+            _ => body,
+        };
+
+        let mut recurrence = Self::new(Frequency::Daily);
+        let mut frequency = None;
+        for part in body.split(';').filter(|part| !part.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("'{part}' should look like KEY=VALUE"))?;
+            match key.to_uppercase().as_str() {
+                "FREQ" => {
+                    frequency = Some(match value.to_uppercase().as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        _ => {
+                            return Err(format!(
+                                "FREQ={value} is not supported, use DAILY, WEEKLY, MONTHLY or YEARLY"
+                            ));
+                        }
+                    })
+                }
+                "INTERVAL" => recurrence.interval = positive(value)?,
+                "BYDAY" => {
+                    recurrence.weekdays = value
+                        .split(',')
+                        .map(|code| {
+                            parse_rrule_day(code)
+                                .ok_or_else(|| format!("'{code}' is not a day like MO or TH"))
+                        })
+                        .collect::<std::result::Result<_, _>>()?
+                }
+                "BYMONTH" => recurrence.month = Some(parse_month(value)?),
+                "BYMONTHDAY" => recurrence.month_day = Some(parse_month_day(value)?),
+                "UNTIL" => {
+                    let date = value.get(..8).unwrap_or(value);
+                    recurrence.until = Some(
+                        NaiveDate::parse_from_str(date, "%Y%m%d")
+                            .map_err(|_| format!("UNTIL={value} should look like YYYYMMDD"))?,
+                    );
+                }
+                "COUNT" => recurrence.count = Some(positive(value)?),
+                // weeks start on Monday anyway
+                "WKST" if value.eq_ignore_ascii_case("MO") => {}
+                _ => return Err(format!("RRULE part '{part}' is not supported")),
+            }
+        }
+        recurrence.frequency = frequency.ok_or("an RRULE needs a FREQ")?;
+        recurrence.check()
+    }
+
+    /// Parses `every 2 weeks on mon,thu until 2025-12-31` style rules
+    fn parse_words(rule: &str) -> std::result::Result<Self, String> {
+        let text = rule.trim().to_lowercase();
+        let mut words = text.split_whitespace().peekable();
+        let usage = || {
+            format!(
+                "'{}' is not a repeat rule, try daily, weekly on mon,thu, monthly on 15, \
+                every 2 weeks, yearly or an RRULE",
+                rule.trim()
+            )
+        };
+
+        let mut recurrence = match words.next() {
+            Some("daily") => Self::new(Frequency::Daily),
+            Some("weekly") => Self::new(Frequency::Weekly),
+            Some("monthly") => Self::new(Frequency::Monthly),
+            Some("yearly" | "annually") => Self::new(Frequency::Yearly),
+            Some("every") => {
+                let (interval, unit) = match words.next().ok_or_else(usage)? {
+                    count if count.starts_with(|c: char| c.is_ascii_digit()) => {
+                        (positive(count)?, words.next().ok_or_else(usage)?)
+                    }
+                    unit => (1, unit),
+                };
+                let frequency = match unit.strip_suffix('s').unwrap_or(unit) {
+                    "day" => Frequency::Daily,
+                    "week" => Frequency::Weekly,
+                    "month" => Frequency::Monthly,
+                    "year" => Frequency::Yearly,
+                    _ => return Err(usage()),
+                };
+                Self {
+                    interval,
+                    ..Self::new(frequency)
+                }
+            }
+            _ => return Err(usage()),
+        };
+
+        let keyword = |word: &&str| ["on", "until", "count", "from"].contains(word);
+        while let Some(word) = words.next() {
+            let value = words.next().ok_or_else(usage)?;
+            match word {
+                "on" => {
+                    let mut value = value.to_string();
+                    while let Some(more) = words.next_if(|word| !keyword(word)) {
+                        value.push(',');
+                        value.push_str(more);
+                    }
+                    recurrence.parse_on(&value)?;
+                }
+                "until" => {
+                    recurrence.until = Some(
+                        NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                            .map_err(|_| format!("until '{value}' should look like YYYY-MM-DD"))?,
+                    )
+                }
+                "count" => recurrence.count = Some(positive(value)?),
+                "from" => {
+                    recurrence.from_completion = match value {
+                        "completion" => true,
+                        "due" => false,
+                        _ => return Err(format!("repeat from '{value}', use completion or due")),
+                    }
+                }
+                _ => return Err(usage()),
+            }
+        }
+        recurrence.check()
+    }
+
+    /// Reads what follows `on`: weekdays, a day of the month or `MM-DD`
+    fn parse_on(&mut self, value: &str) -> std::result::Result<(), String> {
+        match self.frequency {
+            Frequency::Weekly => {
+                self.weekdays = value
+                    .split(',')
+                    .filter(|day| !day.is_empty())
+                    .map(|day| {
+                        day.parse()
+                            .map_err(|_| format!("'{day}' is not a day of the week"))
+                    })
+                    .collect::<std::result::Result<_, _>>()?
+            }
+            Frequency::Monthly if value == "last" => self.month_day = Some(-1),
+            Frequency::Monthly => self.month_day = Some(parse_month_day(value)?),
+            Frequency::Yearly => {
+                let (month, day) = value
+                    .split_once('-')
+                    .ok_or_else(|| format!("'{value}' should look like MM-DD"))?;
+                self.month = Some(parse_month(month)?);
+                self.month_day = Some(parse_month_day(day)?);
+            }
+            Frequency::Daily => return Err("a daily rule cannot repeat on a given day".into()),
+        }
+        Ok(())
+    }
+
+    /// Sorts the weekdays and rejects parts that do not fit the frequency
+    fn check(mut self) -> std::result::Result<Self, String> {
+        self.weekdays.sort_by_key(Weekday::num_days_from_monday);
+        self.weekdays.dedup();
+        if !self.weekdays.is_empty() && self.frequency != Frequency::Weekly {
+            return Err("days of the week only fit a weekly rule".into());
+        }
+        match (self.frequency, self.month, self.month_day) {
+            (Frequency::Yearly, Some(month), Some(day)) if day > 0 => {
+                // February 29th is fine, other years use the 28th
+                let longest = day_in(2000, month, 31).map_or(31, |date| date.day());
+                if day as u32 > longest {
+                    return Err(format!("month {month} does not have a day {day}"));
+                }
+            }
+            (_, None, None) | (Frequency::Monthly, None, _) => {}
+            (Frequency::Yearly, _, _) => {
+                return Err("a yearly rule needs a month and a day from 1 to 31".into());
+            }
+            (_, Some(_), _) => return Err("a month only fits a yearly rule".into()),
+            _ => return Err("a day of the month only fits a monthly or yearly rule".into()),
+        }
+        if self.until.is_some() && self.count.is_some() {
+            return Err("use either until or count, not both".into());
+        }
+        Ok(self)
+    }
+}
+
+/// `{"rrule": "FREQ=WEEKLY;BYDAY=MO", "from_completion": false}` in JSON
+impl Serialize for Recurrence {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Recurrence", 2)?;
+        state.serialize_field("rrule", &self.rrule())?;
+        state.serialize_field("from_completion", &self.from_completion)?;
+        state.end()
+    }
+}
+
+/// The friendly form `--repeat` accepts, e.g. `every 2 weeks on mon,thu`
+impl Display for Recurrence {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match (self.interval, self.frequency) {
+            (1, Frequency::Daily) => write!(f, "daily")?,
+            (1, Frequency::Weekly) => write!(f, "weekly")?,
+            (1, Frequency::Monthly) => write!(f, "monthly")?,
+            (1, Frequency::Yearly) => write!(f, "yearly")?,
+            (interval, frequency) => write!(f, "every {interval} {}s", frequency.unit())?,
+        }
+        if !self.weekdays.is_empty() {
+            let days: Vec<_> = self
+                .weekdays
+                .iter()
+                .map(|day| day.to_string().to_lowercase())
+                .collect();
+            write!(f, " on {}", days.join(","))?;
+        }
+        match (self.month, self.month_day) {
+            (Some(month), Some(day)) => write!(f, " on {month:02}-{day:02}")?,
+            (None, Some(-1)) => write!(f, " on last")?,
+            (None, Some(day)) => write!(f, " on {day}")?,
+            _ => {}
+        }
+        if let Some(until) = self.until {
+            write!(f, " until {until}")?;
+        }
+        if let Some(count) = self.count {
+            write!(f, " count {count}")?;
+        }
+        if self.from_completion {
+            write!(f, " from completion")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Recurrence {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        if s.to_uppercase().contains("FREQ=") {
+            Self::parse_rrule(s)
+        } else {
+            Self::parse_words(s)
+        }
+    }
+}
+
+/// Reads the RRULE column, `from_completion` is a column of its own
+impl FromSql for Recurrence {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value
+            .as_str()?
+            .parse()
+            .map_err(|err: String| FromSqlError::Other(err.into()))
+    }
+}
+
+/// Adds the instance after task `id` if its rule has one, counting from
+/// `today` when the task has no due date or repeats from completion
+pub(crate) fn repeat(client: &Connection, id: i32, today: NaiveDate) -> Result<Option<i32>> {
+    let row = client
+        .query_row(
+            r"
+            SELECT due_date, recurrence, repeat_from_completion FROM tasks
+            WHERE id = ?1 AND recurrence IS NOT NULL
+            ",
+            [id],
+            |row| {
+                Ok((
+                    row.get::<_, Option<String>>(0)?,
+                    Recurrence {
+                        from_completion: row.get(2)?,
+                        ..row.get(1)?
+                    },
+                ))
+            },
+        )
+        .optional()?;
+    let Some((due_date, rule)) = row else {
+        return Ok(None);
+    };
+
+    let base = due_date
+        .filter(|_| !rule.from_completion)
+        .and_then(|date| NaiveDate::parse_from_str(&date, "%Y-%m-%d").ok())
+        .unwrap_or(today);
+    let Some((date, next)) = rule.next(base) else {
+        return Ok(None);
+    };
+
+    client.execute(
+        r"
+        INSERT INTO tasks (info, due_date, category, priority, parent_id, recurrence,
+            repeat_from_completion, repeat_of)
+        SELECT info, ?2, category, priority, parent_id, ?3, repeat_from_completion, id
+        FROM tasks WHERE id = ?1
+        ",
+        rusqlite::params![id, date.format("%Y-%m-%d").to_string(), next.rrule()],
+    )?;
+    let next_id = client.last_insert_rowid() as i32;
+    client.execute(
+        "INSERT INTO task_tags (task_id, tag_id) SELECT ?1, tag_id FROM task_tags WHERE task_id = ?2",
+        [next_id, id],
+    )?;
+    Ok(Some(next_id))
+}
+
+/// `day` of a month, its last day for -1 or a day the month does not have
+fn day_in(year: i32, month: u32, day: i32) -> Option<NaiveDate> {
+    let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+    let last = first.checked_add_months(Months::new(1))?.pred_opt()?.day();
+    first.with_day(if day < 0 { last } else { last.min(day as u32) })
+}
+
+fn positive(value: &str) -> std::result::Result<u32, String> {
+    match value.parse() {
+        Ok(0) | Err(_) => Err(format!("'{value}' should be a whole number above 0")),
+        Ok(number) => Ok(number),
+    }
+}
+
+fn parse_month(value: &str) -> std::result::Result<u32, String> {
+    value
+        .parse()
+        .ok()
+        .filter(|month| (1..=12).contains(month))
+        .ok_or_else(|| format!("'{value}' is not a month between 1 and 12"))
+}
+
+fn parse_month_day(value: &str) -> std::result::Result<i32, String> {
+    value
+        .parse()
+        .ok()
+        .filter(|day| *day == -1 || (1..=31).contains(day))
+        .ok_or_else(|| format!("'{value}' is not a day between 1 and 31, or -1 for the last"))
+}
+
+fn rrule_day(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+fn parse_rrule_day(code: &str) -> Option<Weekday> {
+    let code = code.trim().to_uppercase();
+    [
+        Weekday::Mon,
+        Weekday::Tue,
+        Weekday::Wed,
+        Weekday::Thu,
+        Weekday::Fri,
+        Weekday::Sat,
+        Weekday::Sun,
+    ]
+    .into_iter()
+    .find(|day| rrule_day(*day) == code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    /// Due dates of the instances after `start`, stopping after `limit`
+    fn series(rule: &str, start: &str, limit: usize) -> Vec<String> {
+        let mut rule: Recurrence = rule.parse().unwrap();
+        let mut due = date(start);
+        let mut dates = Vec::new();
+        while dates.len() < limit {
+            let Some((next, next_rule)) = rule.next(due) else {
+                break;
+            };
+            dates.push(next.to_string());
+            (due, rule) = (next, next_rule);
+        }
+        dates
+    }
+
+    #[test]
+    fn test_parse() {
+        let weekly = Recurrence {
+            weekdays: vec![Weekday::Mon, Weekday::Thu],
+            ..Recurrence::new(Frequency::Weekly)
+        };
+        assert_eq!("weekly on thu,mon".parse(), Ok(weekly.clone()));
+        assert_eq!("Weekly on mon, thursday".parse(), Ok(weekly.clone()));
+        assert_eq!("RRULE:FREQ=WEEKLY;BYDAY=MO,TH;WKST=MO".parse(), Ok(weekly));
+        assert_eq!(
+            "every 2 weeks".parse(),
+            Ok(Recurrence {
+                interval: 2,
+                ..Recurrence::new(Frequency::Weekly)
+            })
+        );
+        assert_eq!(
+            "monthly on 15 count 3 from completion".parse(),
+            Ok(Recurrence {
+                month_day: Some(15),
+                count: Some(3),
+                from_completion: true,
+                ..Recurrence::new(Frequency::Monthly)
+            })
+        );
+        assert_eq!(
+            "freq=yearly;bymonth=2;bymonthday=29;until=20300101T000000Z".parse(),
+            Ok(Recurrence {
+                month: Some(2),
+                month_day: Some(29),
+                until: Some(date("2030-01-01")),
+                ..Recurrence::new(Frequency::Yearly)
+            })
+        );
+        assert_eq!("daily".parse(), Ok(Recurrence::new(Frequency::Daily)));
+
+        for bad in [
+            "sometimes",
+            "every 0 days",
+            "every fortnight",
+            "weekly on funday",
+            "daily on mon",
+            "monthly on 32",
+            "yearly on 02-30",
+            "daily until tomorrow",
+            "daily count 2 until 2030-01-01",
+            "FREQ=HOURLY",
+            "FREQ=MONTHLY;BYDAY=1MO",
+            "FREQ=WEEKLY;BYSETPOS=1",
+            "INTERVAL=2",
+        ] {
+            assert!(bad.parse::<Recurrence>().is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for rule in [
+            "daily",
+            "every 3 days until 2025-12-31",
+            "weekly on mon,thu",
+            "every 2 weeks on tue count 4",
+            "monthly on last",
+            "monthly on 15 from completion",
+            "yearly on 02-29",
+        ] {
+            let parsed: Recurrence = rule.parse().unwrap();
+            assert_eq!(parsed.to_string(), rule);
+            let mut stored: Recurrence = parsed.rrule().parse().unwrap();
+            stored.from_completion = parsed.from_completion;
+            assert_eq!(stored, parsed);
+        }
+        assert_eq!(
+            "every 2 weeks on mon,thu count 4"
+                .parse::<Recurrence>()
+                .unwrap()
+                .rrule(),
+            "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,TH;COUNT=4"
+        );
+    }
+
+    #[test]
+    fn test_daily_and_weekly() {
+        assert_eq!(
+            series("daily", "2024-02-27", 4),
+            ["2024-02-28", "2024-02-29", "2024-03-01", "2024-03-02"]
+        );
+        assert_eq!(
+            series("every 10 days", "2023-12-25", 2),
+            ["2024-01-04", "2024-01-14"]
+        );
+        // 2025-09-29 is a Monday
+        assert_eq!(
+            series("weekly", "2025-10-01", 2),
+            ["2025-10-08", "2025-10-15"]
+        );
+        assert_eq!(
+            series("weekly on mon,thu", "2025-09-29", 4),
+            ["2025-10-02", "2025-10-06", "2025-10-09", "2025-10-13"]
+        );
+        // a due date off the listed days moves onto them
+        assert_eq!(series("weekly on mon,thu", "2025-10-04", 1), ["2025-10-06"]);
+        assert_eq!(
+            series("every 2 weeks on mon,thu", "2025-09-29", 4),
+            ["2025-10-02", "2025-10-13", "2025-10-16", "2025-10-27"]
+        );
+        // across the end of a year
+        assert_eq!(series("weekly on fri", "2025-12-26", 1), ["2026-01-02"]);
+    }
+
+    #[test]
+    fn test_month_ends() {
+        // the 31st lands on the last day of shorter months and comes back
+        assert_eq!(
+            series("monthly", "2025-01-31", 4),
+            ["2025-02-28", "2025-03-31", "2025-04-30", "2025-05-31"]
+        );
+        assert_eq!(
+            series("monthly", "2024-01-31", 2),
+            ["2024-02-29", "2024-03-31"]
+        );
+        assert_eq!(
+            series("monthly on 30", "2025-01-30", 3),
+            ["2025-02-28", "2025-03-30", "2025-04-30"]
+        );
+        assert_eq!(
+            series("monthly on last", "2024-01-31", 4),
+            ["2024-02-29", "2024-03-31", "2024-04-30", "2024-05-31"]
+        );
+        // a day later in the same month comes first
+        assert_eq!(
+            series("monthly on 15", "2025-10-03", 2),
+            ["2025-10-15", "2025-11-15"]
+        );
+        assert_eq!(
+            series("every 3 months", "2025-11-30", 2),
+            ["2026-02-28", "2026-05-30"]
+        );
+        assert_eq!(
+            series("every 2 months on 31", "2025-12-31", 3),
+            ["2026-02-28", "2026-04-30", "2026-06-30"]
+        );
+        // without pinning from completion follows whatever day it was done
+        let rule: Recurrence = "monthly from completion".parse().unwrap();
+        let (due, next) = rule.next(date("2025-01-31")).unwrap();
+        assert_eq!(due, date("2025-02-28"));
+        assert_eq!(next.next(due).unwrap().0, date("2025-03-28"));
+    }
+
+    #[test]
+    fn test_leap_years() {
+        assert_eq!(
+            series("yearly", "2024-02-29", 5),
+            [
+                "2025-02-28",
+                "2026-02-28",
+                "2027-02-28",
+                "2028-02-29",
+                "2029-02-28"
+            ]
+        );
+        assert_eq!(
+            series("every 4 years", "2096-02-29", 2),
+            ["2100-02-28", "2104-02-29"]
+        );
+        assert_eq!(
+            series("yearly on 02-29", "2025-03-01", 2),
+            ["2026-02-28", "2027-02-28"]
+        );
+        assert_eq!(
+            series("yearly on 12-25", "2025-10-18", 2),
+            ["2025-12-25", "2026-12-25"]
+        );
+    }
+
+    #[test]
+    fn test_limits() {
+        assert_eq!(
+            series("daily count 3", "2025-01-01", 10),
+            ["2025-01-02", "2025-01-03"]
+        );
+        assert_eq!(
+            series("weekly until 2025-01-15", "2025-01-01", 10),
+            ["2025-01-08", "2025-01-15"]
+        );
+        assert_eq!(
+            series("FREQ=MONTHLY;UNTIL=20250301", "2025-01-31", 10),
+            ["2025-02-28"]
+        );
+        let rule: Recurrence = "daily count 3".parse().unwrap();
+        assert_eq!(rule.next(date("2025-01-01")).unwrap().1.count, Some(2));
+    }
+}
@@ -0,0 +1,192 @@
+use crate::{Conn, Error, Result, Task};
+use rusqlite::{Connection, Error::SqliteFailure};
+use serde::Serialize;
+
+/// Matches tasks whose info matches the FTS5 query bound to the `?`
+pub(crate) const MATCHES: &str =
+    "tasks.id IN (SELECT rowid FROM tasks_fts WHERE tasks_fts MATCH ?)";
+
+/// A task found by `Conn::search`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SearchHit {
+    #[serde(flatten)]
+    pub task: Task,
+    /// The info around the matches, each match wrapped in the given marks
+    pub snippet: String,
+    /// bm25 score, lower is a better match
+    pub rank: f64,
+}
+
+impl Conn {
+    /// Tasks whose info matches `query`, best match first. Queries use the
+    /// FTS5 syntax: words, "a phrase", prefix*, AND, OR, NOT and parentheses.
+    pub fn search(
+        &self,
+        query: &str,
+        include_done: bool,
+        marks: (&str, &str),
+    ) -> Result<Vec<SearchHit>> {
+        check_query(&self.client, query)?;
+        let hits = self
+            .client
+            .prepare(
+                r"
+                SELECT tasks.id, snippet(tasks_fts, 0, ?2, ?3, '…', 16), tasks_fts.rank
+                FROM tasks_fts
+                JOIN tasks ON tasks.id = tasks_fts.rowid
+                WHERE tasks_fts MATCH ?1 AND (?4 OR NOT tasks.done)
+                    AND tasks.deleted_at IS NULL
+                ORDER BY tasks_fts.rank, tasks.id
+                ",
+            )?
+            .query_map(
+                rusqlite::params![query, marks.0, marks.1, include_done],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )?
+            .collect::<rusqlite::Result<Vec<(i32, String, f64)>>>()?;
+
+        hits.into_iter()
+            .map(|(id, snippet, rank)| {
+                Ok(SearchHit {
+                    task: self.get_task(id)?,
+                    snippet,
+                    rank,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Fails with `InvalidSearch` unless FTS5 understands `query`
+pub(crate) fn check_query(client: &Connection, query: &str) -> Result<()> {
+    client
+        .query_row(
+            "SELECT count(*) FROM tasks_fts WHERE tasks_fts MATCH ?1",
+            [query],
+            |_| Ok(()),
+        )
+        .map_err(|err| match err {
+            SqliteFailure(_, Some(msg)) => Error::InvalidSearch(msg),
+            err => err.into(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ListOptions, NewTask, TaskEdit};
+
+    fn get_test_conn() -> Conn {
+        let conn = Conn::build(":memory:").unwrap();
+        for info in [
+            "call the plumber about the kitchen sink",
+            "buy milk and eggs",
+            "plan kitchen renovation",
+            "Café opening party",
+            "buy a new kitchen table",
+        ] {
+            conn.add_task(&NewTask {
+                info: info.to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+        }
+        conn
+    }
+
+    fn ids(conn: &Conn, query: &str) -> Vec<i32> {
+        conn.search(query, true, ("[", "]"))
+            .unwrap()
+            .iter()
+            .map(|hit| hit.task.id)
+            .collect()
+    }
+
+    fn sorted(mut ids: Vec<i32>) -> Vec<i32> {
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn test_queries() {
+        let conn = get_test_conn();
+        assert_eq!(sorted(ids(&conn, "kitchen")), vec![1, 3, 5]);
+        assert_eq!(ids(&conn, "\"kitchen sink\""), vec![1]);
+        assert_eq!(ids(&conn, "\"sink kitchen\""), Vec::<i32>::new());
+        assert_eq!(sorted(ids(&conn, "plu* OR eggs")), vec![1, 2]);
+        assert_eq!(ids(&conn, "buy AND kitchen"), vec![5]);
+        assert_eq!(ids(&conn, "kitchen NOT (plumber OR table)"), vec![3]);
+        // case and accents are ignored
+        assert_eq!(ids(&conn, "CAFE"), vec![4]);
+    }
+
+    #[test]
+    fn test_rank_and_snippet() {
+        let conn = get_test_conn();
+        conn.add_task(&NewTask {
+            info: "kitchen kitchen kitchen".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        let hits = conn.search("kitchen", false, ("<", ">")).unwrap();
+        assert_eq!(hits[0].task.id, 6);
+        assert!(hits.windows(2).all(|pair| pair[0].rank <= pair[1].rank));
+        let sink = hits.iter().find(|hit| hit.task.id == 1).unwrap();
+        assert_eq!(sink.snippet, "call the plumber about the <kitchen> sink");
+    }
+
+    #[test]
+    fn test_index_follows_tasks() {
+        let conn = get_test_conn();
+        conn.edit_task(
+            2,
+            &TaskEdit {
+                info: Some("buy oat milk".to_string()),
+                finish: Some(true),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(ids(&conn, "oat"), vec![2]);
+        assert!(ids(&conn, "eggs").is_empty());
+        // done tasks only show up when asked for
+        assert!(conn.search("oat", false, ("", "")).unwrap().is_empty());
+
+        conn.remove_task(2).unwrap();
+        assert!(ids(&conn, "milk").is_empty());
+
+        let options = ListOptions {
+            search: Some("kitchen NOT plumber".to_string()),
+            ..Default::default()
+        };
+        let listed: Vec<_> = conn
+            .list_tasks(&options)
+            .unwrap()
+            .iter()
+            .map(|task| task.id)
+            .collect();
+        assert_eq!(listed, vec![3, 5]);
+    }
+
+    #[test]
+    fn test_invalid_query() {
+        let conn = get_test_conn();
+        for query in ["", "kitchen AND", "\"open", "color:red"] {
+            assert!(
+                matches!(
+                    conn.search(query, true, ("", "")),
+                    Err(Error::InvalidSearch(_))
+                ),
+                "{query}"
+            );
+        }
+        let options = ListOptions {
+            search: Some("(".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            conn.list_tasks(&options),
+            Err(Error::InvalidSearch(_))
+        ));
+    }
+}
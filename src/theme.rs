@@ -0,0 +1,243 @@
+use crate::{Priority, Task};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::io::{self, IsTerminal};
+
+/// When to colour output
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMode {
+    /// Only when stdout is a terminal and `NO_COLOR` is unset
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// A single ANSI style, `none` leaves the text as is
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Color {
+    None,
+    Bold,
+    Dim,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+}
+
+impl Color {
+    fn code(&self) -> Option<u8> {
+        match self {
+            Self::None => None,
+            Self::Bold => Some(1),
+            Self::Dim => Some(2),
+            Self::Red => Some(31),
+            Self::Green => Some(32),
+            Self::Yellow => Some(33),
+            Self::Blue => Some(34),
+            Self::Magenta => Some(35),
+            Self::Cyan => Some(36),
+        }
+    }
+
+    /// Wraps `text` in this style's escape codes
+    pub fn paint(&self, text: &str) -> String {
+        let (start, end) = self.marks();
+        format!("{start}{text}{end}")
+    }
+
+    /// Escape codes that start and end this style, empty for `none`
+    pub fn marks(&self) -> (String, String) {
+        match self.code() {
+            Some(code) => (format!("\x1b[{code}m"), "\x1b[0m".to_string()),
+            None => Default::default(),
+        }
+    }
+}
+
+/// Colours for `list` output, the `[theme]` table of the config file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    pub color: ColorMode,
+    pub overdue: Color,
+    pub due_today: Color,
+    pub done: Color,
+    /// Open tasks waiting on another one
+    pub blocked: Color,
+    pub category: Color,
+    pub tag: Color,
+    pub high: Color,
+    pub low: Color,
+    /// Words matching a search
+    pub highlight: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            color: ColorMode::Auto,
+            overdue: Color::Red,
+            due_today: Color::Yellow,
+            done: Color::Dim,
+            blocked: Color::Dim,
+            category: Color::Cyan,
+            tag: Color::Blue,
+            high: Color::Bold,
+            low: Color::Dim,
+            highlight: Color::Magenta,
+        }
+    }
+}
+
+impl Theme {
+    /// Whether output to stdout should be coloured
+    pub fn use_color(&self) -> bool {
+        match self.color {
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+            ColorMode::Auto => env::var_os("NO_COLOR").is_none() && io::stdout().is_terminal(),
+        }
+    }
+
+    /// Formats `task` like its `Display` impl, coloured when `color` is set
+    pub fn render(&self, task: &Task, today: &NaiveDate, color: bool) -> String {
+        if !color {
+            return task.to_string();
+        }
+        if task.done {
+            return self.done.paint(&task.to_string());
+        }
+        if !task.blocked_by.is_empty() {
+            return self.blocked.paint(&task.to_string());
+        }
+
+        let mut line = format!("{:>3}. [ ] ", task.id);
+        let (info, style) = match task.priority {
+            Priority::Medium => (task.info.clone(), Color::None),
+            priority => {
+                let info = format!("({}) {}", priority.letter(), task.info);
+                let style = if priority == Priority::High {
+                    self.high
+                } else {
+                    self.low
+                };
+                (info, style)
+            }
+        };
+        line.push_str(&style.paint(&info));
+        if let Some(progress) = &task.progress {
+            line.push_str(&format!(" [{progress}]"));
+        }
+        if let Some(due_date) = &task.due_date {
+            let due = format!("due {due_date}");
+            let style = match NaiveDate::parse_from_str(due_date, "%Y-%m-%d") {
+                Ok(date) if date < *today => self.overdue,
+                Ok(date) if date == *today => self.due_today,
+                _ => Color::None,
+            };
+            line.push_str(" | ");
+            line.push_str(&style.paint(&due));
+        }
+        if let Some(recurrence) = &task.recurrence {
+            line.push_str(&format!(" | repeats {recurrence}"));
+        }
+        if let Some(category) = &task.category {
+            line.push_str(" | ");
+            line.push_str(&self.category.paint(category));
+        }
+        if !task.tags.is_empty() {
+            line.push_str(" | ");
+            line.push_str(&self.tag.paint(&task.hashtags()));
+        }
+        line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(done: bool, due_date: Option<&str>) -> Task {
+        Task {
+            id: 2,
+            info: "foo".to_string(),
+            done,
+            due_date: due_date.map(str::to_string),
+            category: Some("work".to_string()),
+            priority: Priority::Medium,
+            tags: Vec::new(),
+            parent: None,
+            progress: None,
+            blocked_by: Vec::new(),
+            recurrence: None,
+            created_at: None,
+            modified_at: None,
+            completed_at: None,
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let today = NaiveDate::from_ymd_opt(2025, 9, 20).unwrap();
+        let theme = Theme::default();
+
+        let overdue = task(false, Some("2025-09-19"));
+        assert_eq!(theme.render(&overdue, &today, false), overdue.to_string());
+        assert_eq!(
+            theme.render(&overdue, &today, true),
+            "  2. [ ] foo | \x1b[31mdue 2025-09-19\x1b[0m | \x1b[36mwork\x1b[0m"
+        );
+        assert_eq!(
+            theme.render(&task(false, Some("2025-09-20")), &today, true),
+            "  2. [ ] foo | \x1b[33mdue 2025-09-20\x1b[0m | \x1b[36mwork\x1b[0m"
+        );
+        assert_eq!(
+            theme.render(&task(false, Some("2025-09-21")), &today, true),
+            "  2. [ ] foo | due 2025-09-21 | \x1b[36mwork\x1b[0m"
+        );
+        assert_eq!(
+            theme.render(&task(true, Some("2025-09-19")), &today, true),
+            "\x1b[2m  2. [x] foo | due 2025-09-19 | work\x1b[0m"
+        );
+
+        let urgent = Task {
+            priority: Priority::High,
+            ..task(false, None)
+        };
+        assert_eq!(theme.render(&urgent, &today, false), urgent.to_string());
+        assert_eq!(
+            theme.render(&urgent, &today, true),
+            "  2. [ ] \x1b[1m(H) foo\x1b[0m | \x1b[36mwork\x1b[0m"
+        );
+        let someday = Task {
+            priority: Priority::Low,
+            tags: vec!["a".to_string(), "b".to_string()],
+            ..task(false, None)
+        };
+        assert_eq!(theme.render(&someday, &today, false), someday.to_string());
+        assert_eq!(
+            theme.render(&someday, &today, true),
+            "  2. [ ] \x1b[2m(L) foo\x1b[0m | \x1b[36mwork\x1b[0m | \x1b[34m#a #b\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_use_color() {
+        let always = Theme {
+            color: ColorMode::Always,
+            ..Default::default()
+        };
+        assert!(always.use_color());
+        let never = Theme {
+            color: ColorMode::Never,
+            ..Default::default()
+        };
+        assert!(!never.use_color());
+    }
+}
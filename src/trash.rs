@@ -0,0 +1,244 @@
+use crate::{Conn, Error, Result, TASK_SELECT, Task, journal};
+use rusqlite::OptionalExtension;
+use serde::Serialize;
+
+/// Trashed tasks are rows with `deleted_at` set. Every other query leaves
+/// them out, but they keep their category, tags and dependencies until the
+/// trash is emptied, so restoring one brings it back as it was.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TrashedTask {
+    #[serde(flatten)]
+    pub task: Task,
+    /// Local `YYYY-MM-DD HH:MM:SS` time the task was removed
+    pub deleted_at: String,
+}
+
+/// `restored` holds task `?1` and the subtasks removed along with it
+const RESTORED: &str = r"
+    WITH RECURSIVE restored(id) AS (
+        SELECT ?1
+        UNION
+        SELECT tasks.id FROM tasks JOIN restored ON tasks.parent_id = restored.id
+        WHERE tasks.deleted_at = (SELECT deleted_at FROM tasks WHERE id = ?1)
+    )";
+
+impl Conn {
+    /// Every task in the trash, most recently removed first
+    pub fn list_trash(&self) -> Result<Vec<TrashedTask>> {
+        let rows = self
+            .client
+            .prepare(
+                r"
+                SELECT id, datetime(deleted_at, 'localtime') FROM tasks
+                WHERE deleted_at IS NOT NULL
+                ORDER BY deleted_at DESC, id
+                ",
+            )?
+            .query_map((), |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<Vec<(i32, String)>>>()?;
+
+        rows.into_iter()
+            .map(|(id, deleted_at)| {
+                let task = self.client.query_row(
+                    &format!("{TASK_SELECT} WHERE tasks.id = ?1"),
+                    [id],
+                    Task::from_row,
+                )?;
+                Ok(TrashedTask { task, deleted_at })
+            })
+            .collect()
+    }
+
+    /// Takes a task out of the trash along with the subtasks removed with it
+    pub fn restore_task(&self, id: i32) -> Result<Task> {
+        let tx = self.client.unchecked_transaction()?;
+        let parent = tx
+            .query_row(
+                r"
+                SELECT parent.id, parent.deleted_at IS NOT NULL FROM tasks
+                LEFT JOIN tasks AS parent ON tasks.parent_id = parent.id
+                WHERE tasks.id = ?1 AND tasks.deleted_at IS NOT NULL
+                ",
+                [id],
+                |row| Ok((row.get::<_, Option<i32>>(0)?, row.get::<_, bool>(1)?)),
+            )
+            .optional()?
+            .ok_or(Error::NotInTrash(id))?;
+        if let (Some(parent), true) = parent {
+            return Err(Error::ParentInTrash { id, parent });
+        }
+
+        journal::begin(&tx)?;
+        tx.execute(
+            &format!("{RESTORED} UPDATE tasks SET deleted_at = NULL WHERE id IN restored"),
+            [id],
+        )?;
+        journal::end(&tx, &format!("restore task {id}"))?;
+        tx.commit()?;
+        self.get_task(id)
+    }
+
+    /// Deletes for good every task that has been in the trash for at least
+    /// `older_than_days`, all of them for 0. Returns how many were deleted.
+    pub fn empty_trash(&self, older_than_days: u32) -> Result<usize> {
+        let tx = self.client.unchecked_transaction()?;
+        let cutoff = format!("-{older_than_days} days");
+        // subtasks are removed with or before their parent, so the cascade
+        // never reaches a task that is not itself old enough
+        const OLD_ENOUGH: &str = "deleted_at <= strftime('%Y-%m-%d %H:%M:%S', 'now', ?1)";
+        let count: usize = tx.query_row(
+            &format!("SELECT count(*) FROM tasks WHERE {OLD_ENOUGH}"),
+            [&cutoff],
+            |row| row.get(0),
+        )?;
+
+        journal::begin(&tx)?;
+        tx.execute(&format!("DELETE FROM tasks WHERE {OLD_ENOUGH}"), [&cutoff])?;
+        journal::end(&tx, "empty trash")?;
+        tx.commit()?;
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Conn, Error, ListOptions, NewTask};
+
+    fn get_test_conn() -> Conn {
+        Conn::build(":memory:").unwrap()
+    }
+
+    fn add(conn: &Conn, info: &str, parent: Option<i32>) -> i32 {
+        conn.add_task(&NewTask {
+            info: info.to_string(),
+            category: Some("work".to_string()),
+            parent,
+            ..Default::default()
+        })
+        .unwrap()
+        .id
+    }
+
+    fn listed(conn: &Conn) -> Vec<i32> {
+        conn.list_tasks(&ListOptions::default())
+            .unwrap()
+            .iter()
+            .map(|task| task.id)
+            .collect()
+    }
+
+    fn trashed(conn: &Conn) -> Vec<i32> {
+        let mut ids: Vec<_> = conn
+            .list_trash()
+            .unwrap()
+            .iter()
+            .map(|trashed| trashed.task.id)
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    /// Pretends the trashed tasks were removed `days` ago
+    fn age(conn: &Conn, days: u32) {
+        conn.client
+            .execute(
+                "UPDATE tasks SET deleted_at = datetime(deleted_at, ?1) WHERE deleted_at IS NOT NULL",
+                [format!("-{days} days")],
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_remove_moves_to_trash() {
+        let conn = get_test_conn();
+        let parent = add(&conn, "parent", None);
+        let child = add(&conn, "child", Some(parent));
+        let other = add(&conn, "other", None);
+
+        conn.remove_task(parent).unwrap();
+        assert_eq!(listed(&conn), vec![other]);
+        assert_eq!(trashed(&conn), vec![parent, child]);
+        assert!(matches!(
+            conn.get_task(child),
+            Err(Error::TaskNotFound(id)) if id == child
+        ));
+        assert!(matches!(
+            conn.restore_task(other),
+            Err(Error::NotInTrash(id)) if id == other
+        ));
+
+        // the subtask cannot come back without its parent
+        assert!(matches!(
+            conn.restore_task(child),
+            Err(Error::ParentInTrash { id, parent: p }) if id == child && p == parent
+        ));
+        let restored = conn.restore_task(parent).unwrap();
+        assert_eq!(restored.progress.unwrap().to_string(), "0/1");
+        assert_eq!(listed(&conn), vec![parent, child, other]);
+        assert!(trashed(&conn).is_empty());
+    }
+
+    #[test]
+    fn test_restore_keeps_separately_removed_subtasks() {
+        let conn = get_test_conn();
+        let parent = add(&conn, "parent", None);
+        let first = add(&conn, "first", Some(parent));
+        let second = add(&conn, "second", Some(parent));
+
+        conn.remove_task(first).unwrap();
+        age(&conn, 1);
+        conn.remove_task(parent).unwrap();
+        conn.restore_task(parent).unwrap();
+        assert_eq!(listed(&conn), vec![parent, second]);
+        assert_eq!(trashed(&conn), vec![first]);
+    }
+
+    #[test]
+    fn test_empty_trash() {
+        let conn = get_test_conn();
+        let old = add(&conn, "old", None);
+        let old_child = add(&conn, "old child", Some(old));
+        let recent = add(&conn, "recent", None);
+
+        conn.remove_task(old).unwrap();
+        age(&conn, 40);
+        conn.remove_task(recent).unwrap();
+
+        assert_eq!(conn.empty_trash(30).unwrap(), 2);
+        assert_eq!(trashed(&conn), vec![recent]);
+        assert!(matches!(
+            conn.restore_task(old_child),
+            Err(Error::NotInTrash(_))
+        ));
+        assert_eq!(conn.empty_trash(0).unwrap(), 1);
+        assert!(trashed(&conn).is_empty());
+    }
+
+    #[test]
+    fn test_trash_keeps_categories() {
+        let conn = get_test_conn();
+        let id = add(&conn, "only task", None);
+        conn.remove_task(id).unwrap();
+
+        let work = &conn.list_categories().unwrap()[0];
+        assert_eq!((work.open, work.done, work.trashed), (0, 0, 1));
+        assert!(!work.is_orphaned());
+
+        conn.empty_trash(0).unwrap();
+        assert!(conn.list_categories().unwrap()[0].is_orphaned());
+    }
+
+    #[test]
+    fn test_undo_remove() {
+        let conn = get_test_conn();
+        let id = add(&conn, "task", None);
+        conn.remove_task(id).unwrap();
+        conn.undo(None, false).unwrap();
+        assert_eq!(listed(&conn), vec![id]);
+
+        conn.remove_task(id).unwrap();
+        conn.empty_trash(0).unwrap();
+        conn.undo(None, false).unwrap();
+        assert_eq!(trashed(&conn), vec![id]);
+    }
+}
@@ -0,0 +1,85 @@
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+/// Runs the binary against its own database in a fresh directory, away from
+/// any config or project list of the machine
+fn todo(dir: &PathBuf, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_todo"))
+        .args(args)
+        .current_dir(dir)
+        .env("HOME", dir)
+        .env("XDG_CONFIG_HOME", dir)
+        .env("TODO_DB", dir.join("todo.db"))
+        .output()
+        .unwrap()
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("todo-cli-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn test_export_output_modes() {
+    let dir = temp_dir("export");
+    assert!(todo(&dir, &["add", "first"]).status.success());
+    assert!(
+        todo(&dir, &["add", "second", "-c", "work"])
+            .status
+            .success()
+    );
+
+    let text = String::from_utf8(todo(&dir, &["export"]).stdout).unwrap();
+    let json = String::from_utf8(todo(&dir, &["--output", "json", "export"]).stdout).unwrap();
+    let ndjson = String::from_utf8(todo(&dir, &["--output", "ndjson", "export"]).stdout).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    // text and json are the same pretty document, without any banner
+    assert!(text.starts_with("{\n  \"schema_version\": 1,"));
+    assert_eq!(text, json);
+    // ndjson is the same export on a single line
+    assert_eq!(ndjson.lines().count(), 1);
+    let parse = |text: &str| text.parse::<serde_json::Value>().unwrap();
+    assert_eq!(parse(&ndjson), parse(&text));
+}
+
+#[test]
+fn test_remove_conflicts_with_edits() {
+    let dir = temp_dir("remove");
+    assert!(todo(&dir, &["add", "first"]).status.success());
+
+    let output = todo(
+        &dir,
+        &["edit", "1", "--remove", "--info", "x", "-p", "high"],
+    );
+    let listed = String::from_utf8(todo(&dir, &["list"]).stdout).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    // a usage error, the task is neither removed nor changed
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("cannot be used with"));
+    assert!(listed.contains("1. [ ] first"));
+}
+
+#[test]
+fn test_export_filter() {
+    let dir = temp_dir("export-filter");
+    assert!(todo(&dir, &["add", "first"]).status.success());
+    assert!(
+        todo(&dir, &["add", "second", "-c", "work"])
+            .status
+            .success()
+    );
+
+    let output = todo(&dir, &["export", "--filter", "cat:work"]);
+    let invalid = todo(&dir, &["export", "--filter", "due:"]);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let export: serde_json::Value = String::from_utf8(output.stdout).unwrap().parse().unwrap();
+    let tasks = export["tasks"].as_array().unwrap();
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0]["info"], "second");
+    assert!(!invalid.status.success());
+}